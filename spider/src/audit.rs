@@ -0,0 +1,168 @@
+use hyperware_process_lib::{
+    our,
+    vfs::{create_drive, open_dir, open_file, remove_file},
+};
+
+use crate::types::{AuditEvent, AuditLog, ListAuditEventsRequest};
+
+const AUDIT_DRIVE: &str = "audit";
+
+/// Start a new segment once the current one grows past this size
+const MAX_SEGMENT_BYTES: u64 = 1024 * 1024;
+
+/// Oldest segments beyond this count are deleted
+const MAX_SEGMENTS: usize = 10;
+
+pub(crate) const OUTCOME_SUCCESS: &str = "success";
+pub(crate) const OUTCOME_DENIED: &str = "denied";
+pub(crate) const OUTCOME_FAILURE: &str = "failure";
+
+/// Classify a handler result; authorization failures are recorded separately from other errors
+pub(crate) fn outcome_of<T>(result: &Result<T, String>) -> (&'static str, Option<String>) {
+    match result {
+        Ok(_) => (OUTCOME_SUCCESS, None),
+        Err(e) if e.starts_with("Unauthorized") || e.starts_with("Forbidden") => {
+            (OUTCOME_DENIED, Some(e.clone()))
+        }
+        Err(e) => (OUTCOME_FAILURE, Some(e.clone())),
+    }
+}
+
+// Segment names sort chronologically: audit-<unix seconds, zero padded>.jsonl
+fn segment_name(timestamp: u64) -> String {
+    format!("audit-{:012}.jsonl", timestamp)
+}
+
+/// Existing segment paths, oldest first
+fn list_segments(drive_path: &str) -> Result<Vec<String>, String> {
+    let dir = open_dir(drive_path, false, None)
+        .map_err(|e| format!("Failed to open audit drive: {:?}", e))?;
+    let entries = dir
+        .read()
+        .map_err(|e| format!("Failed to read audit drive: {:?}", e))?;
+
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry.path.rsplit('/').next().map(|n| n.to_string()))
+        .filter(|name| name.starts_with("audit-") && name.ends_with(".jsonl"))
+        .collect();
+    names.sort();
+
+    Ok(names
+        .into_iter()
+        .map(|name| format!("{}/{}", drive_path, name))
+        .collect())
+}
+
+/// Create the audit drive and pick up the segments already on it
+pub(crate) fn open_log() -> Result<AuditLog, String> {
+    let drive_path = create_drive(our().package_id(), AUDIT_DRIVE, None)
+        .map_err(|e| format!("Failed to create audit drive: {:?}", e))?;
+    let segments = list_segments(&drive_path)?;
+    let current_len = segments
+        .last()
+        .map(|path| {
+            open_file(path, false, None)
+                .and_then(|f| f.metadata())
+                .map(|m| m.len)
+                .unwrap_or(0)
+        })
+        .unwrap_or(0);
+
+    Ok(AuditLog {
+        drive_path,
+        segments,
+        current_len,
+    })
+}
+
+/// Append an event to the current segment, rotating when it is full
+pub(crate) fn append_event(log: &mut AuditLog, event: &AuditEvent) -> Result<(), String> {
+    if log.drive_path.is_empty() {
+        return Err("Audit drive is not open".to_string());
+    }
+
+    let mut line = serde_json::to_string(event)
+        .map_err(|e| format!("Failed to serialize audit event: {}", e))?;
+    line.push('\n');
+
+    if log.segments.is_empty() || log.current_len >= MAX_SEGMENT_BYTES {
+        let path = format!("{}/{}", log.drive_path, segment_name(event.timestamp));
+        // A rotation within the same second keeps appending to that second's segment
+        if log.segments.last() != Some(&path) {
+            log.segments.push(path);
+        }
+        log.current_len = 0;
+    }
+    let path = &log.segments[log.segments.len() - 1];
+
+    let mut file = open_file(path, true, None)
+        .map_err(|e| format!("Failed to open audit segment: {:?}", e))?;
+    file.append(line.as_bytes())
+        .map_err(|e| format!("Failed to append audit event: {:?}", e))?;
+    log.current_len += line.len() as u64;
+
+    if log.segments.len() > MAX_SEGMENTS {
+        let excess = log.segments.len() - MAX_SEGMENTS;
+        for old in log.segments.drain(..excess) {
+            if let Err(e) = remove_file(&old, None) {
+                println!(
+                    "Spider: Failed to remove old audit segment {}: {:?}",
+                    old, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn matches(event: &AuditEvent, filter: &ListAuditEventsRequest) -> bool {
+    filter.actor.as_ref().is_none_or(|a| &event.actor == a)
+        && filter
+            .action
+            .as_ref()
+            .is_none_or(|a| event.action.starts_with(a.as_str()))
+        && filter
+            .target
+            .as_ref()
+            .is_none_or(|t| event.target.contains(t.as_str()))
+        && filter.outcome.as_ref().is_none_or(|o| &event.outcome == o)
+        && filter.since.is_none_or(|s| event.timestamp >= s)
+        && filter.until.is_none_or(|u| event.timestamp <= u)
+}
+
+/// Read events matching the filter, newest first
+pub(crate) fn query_events(
+    log: &AuditLog,
+    filter: &ListAuditEventsRequest,
+) -> Result<Vec<AuditEvent>, String> {
+    if log.drive_path.is_empty() {
+        return Err("Audit drive is not open".to_string());
+    }
+    let limit = filter.limit.unwrap_or(100) as usize;
+
+    let mut events = Vec::new();
+    for path in log.segments.iter().rev() {
+        let file = open_file(path, false, None)
+            .map_err(|e| format!("Failed to open audit segment: {:?}", e))?;
+        let content = file
+            .read()
+            .map_err(|e| format!("Failed to read audit segment: {:?}", e))?;
+
+        let mut segment_events: Vec<AuditEvent> = String::from_utf8_lossy(&content)
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(|event| matches(event, filter))
+            .collect();
+        segment_events.reverse();
+        events.extend(segment_events);
+
+        if events.len() >= limit {
+            break;
+        }
+    }
+
+    events.truncate(limit);
+    Ok(events)
+}
@@ -0,0 +1,142 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::types::{Conversation, Message, MessageNode};
+
+/// Give conversations saved before branching existed a tree: one linear branch over their messages
+pub(crate) fn ensure_tree(conversation: &mut Conversation) {
+    if !conversation.nodes.is_empty() {
+        return;
+    }
+    let messages = std::mem::take(&mut conversation.messages);
+    conversation.active_leaf = None;
+    append(conversation, &messages);
+}
+
+/// Add messages to the end of the active branch
+pub(crate) fn append(conversation: &mut Conversation, messages: &[Message]) {
+    for message in messages {
+        let id = Uuid::new_v4().to_string();
+        conversation.nodes.push(MessageNode {
+            id: id.clone(),
+            parent_id: conversation.active_leaf.clone(),
+            message: message.clone(),
+        });
+        conversation.active_leaf = Some(id);
+    }
+    sync_active_path(conversation);
+}
+
+/// Move the active branch back to the message at `index` of the current path, so the next turn
+/// starts a new branch after it. With `content`, the message itself is replaced by an edited
+/// sibling instead, leaving the original branch intact.
+pub(crate) fn fork(
+    conversation: &mut Conversation,
+    index: usize,
+    content: Option<String>,
+) -> Result<(), String> {
+    let path = active_path_ids(conversation);
+    let node_id = path.get(index).ok_or_else(|| {
+        format!(
+            "Message index {} is out of range; the active branch has {} messages",
+            index,
+            path.len()
+        )
+    })?;
+    let node = find(conversation, node_id)?.clone();
+
+    match content {
+        Some(content) => {
+            if node.message.role != "user" {
+                return Err("Only user messages can be edited".to_string());
+            }
+            let id = Uuid::new_v4().to_string();
+            conversation.nodes.push(MessageNode {
+                id: id.clone(),
+                parent_id: node.parent_id,
+                message: Message {
+                    content,
+                    timestamp: Utc::now().timestamp() as u64,
+                    ..node.message
+                },
+            });
+            conversation.active_leaf = Some(id);
+        }
+        None => conversation.active_leaf = Some(node.id),
+    }
+    sync_active_path(conversation);
+    Ok(())
+}
+
+/// Move the active branch back to its last user message so the reply after it can be generated
+/// again as a sibling branch
+pub(crate) fn rewind_to_last_user(conversation: &mut Conversation) -> Result<(), String> {
+    let path = active_path_ids(conversation);
+    let last_user = path
+        .iter()
+        .rev()
+        .find(|id| {
+            conversation
+                .nodes
+                .iter()
+                .any(|n| &n.id == *id && n.message.role == "user")
+        })
+        .cloned()
+        .ok_or_else(|| "The conversation has no user message to respond to".to_string())?;
+    conversation.active_leaf = Some(last_user);
+    sync_active_path(conversation);
+    Ok(())
+}
+
+/// Make the branch through `message_id` active, following its most recent continuation down to
+/// a leaf
+pub(crate) fn select(conversation: &mut Conversation, message_id: &str) -> Result<(), String> {
+    let mut leaf = find(conversation, message_id)?.id.clone();
+    while let Some(child) = conversation
+        .nodes
+        .iter()
+        .rev()
+        .find(|n| n.parent_id.as_deref() == Some(leaf.as_str()))
+    {
+        leaf = child.id.clone();
+    }
+    conversation.active_leaf = Some(leaf);
+    sync_active_path(conversation);
+    Ok(())
+}
+
+fn find<'a>(conversation: &'a Conversation, id: &str) -> Result<&'a MessageNode, String> {
+    conversation
+        .nodes
+        .iter()
+        .find(|n| n.id == id)
+        .ok_or_else(|| format!("Message {} not found in conversation", id))
+}
+
+// Node ids from the root to the active leaf
+fn active_path_ids(conversation: &Conversation) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut current = conversation.active_leaf.clone();
+    while let Some(id) = current {
+        let Some(node) = conversation.nodes.iter().find(|n| n.id == id) else {
+            break;
+        };
+        // Guard against a corrupted tree with a cycle
+        if ids.contains(&node.id) {
+            break;
+        }
+        ids.push(node.id.clone());
+        current = node.parent_id.clone();
+    }
+    ids.reverse();
+    ids
+}
+
+// `messages` mirrors the active branch so everything reading a conversation linearly keeps working
+fn sync_active_path(conversation: &mut Conversation) {
+    conversation.messages = active_path_ids(conversation)
+        .iter()
+        .filter_map(|id| conversation.nodes.iter().find(|n| &n.id == id))
+        .map(|n| n.message.clone())
+        .collect();
+}
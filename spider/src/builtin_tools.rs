@@ -0,0 +1,380 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use hyperware_process_lib::{
+    http::{client::send_request_await_response, Method},
+    our,
+    vfs::{create_drive, open_dir, open_file},
+};
+
+use crate::types::{BuiltinToolsConfig, Tool};
+
+pub(crate) const WORKSPACE_READ: &str = "workspace_read";
+pub(crate) const WORKSPACE_WRITE: &str = "workspace_write";
+pub(crate) const WORKSPACE_LIST: &str = "workspace_list";
+pub(crate) const HTTP_FETCH: &str = "http_fetch";
+pub(crate) const CURRENT_TIME: &str = "current_time";
+pub(crate) const SEARCH_CONVERSATIONS: &str = "search_conversations";
+
+pub(crate) const NAMES: [&str; 6] = [
+    WORKSPACE_READ,
+    WORKSPACE_WRITE,
+    WORKSPACE_LIST,
+    HTTP_FETCH,
+    CURRENT_TIME,
+    SEARCH_CONVERSATIONS,
+];
+
+/// VFS drive the workspace tools are confined to
+const WORKSPACE_DRIVE: &str = "workspace";
+
+const FETCH_TIMEOUT_MS: u64 = 30000;
+const MAX_FETCH_REDIRECTS: usize = 5;
+
+pub(crate) fn is_builtin(name: &str) -> bool {
+    NAMES.contains(&name)
+}
+
+pub(crate) fn check_names(names: &[String]) -> Result<(), String> {
+    match names.iter().find(|n| !is_builtin(n)) {
+        Some(unknown) => Err(format!(
+            "Unknown built-in tool: {} (expected one of: {})",
+            unknown,
+            NAMES.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Add the enabled built-in tools to a run's tools. A built-in shadows any MCP tool of the same name.
+pub(crate) fn add_enabled(tools: &mut Vec<Tool>, enabled: &[String]) {
+    let enabled: Vec<&str> = NAMES
+        .into_iter()
+        .filter(|name| enabled.iter().any(|e| e == name))
+        .collect();
+    tools.retain(|t| !enabled.contains(&t.name.as_str()));
+    tools.extend(enabled.into_iter().map(definition));
+}
+
+fn definition(name: &str) -> Tool {
+    let (description, schema) = match name {
+        WORKSPACE_READ => (
+            "Read a text file from Spider's workspace drive",
+            json!({
+                "type": "object",
+                "properties": { "path": { "type": "string", "description": "Path inside the workspace, e.g. notes/todo.md" } },
+                "required": ["path"]
+            }),
+        ),
+        WORKSPACE_WRITE => (
+            "Write a text file to Spider's workspace drive, replacing any existing file and creating missing directories",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path inside the workspace, e.g. notes/todo.md" },
+                    "content": { "type": "string" }
+                },
+                "required": ["path", "content"]
+            }),
+        ),
+        WORKSPACE_LIST => (
+            "List the files and directories in a directory of Spider's workspace drive",
+            json!({
+                "type": "object",
+                "properties": { "path": { "type": "string", "description": "Directory inside the workspace; the workspace root when omitted" } }
+            }),
+        ),
+        HTTP_FETCH => (
+            "Fetch a URL with an HTTP GET. Only domains on Spider's allow-list can be fetched.",
+            json!({
+                "type": "object",
+                "properties": { "url": { "type": "string", "description": "http or https URL" } },
+                "required": ["url"]
+            }),
+        ),
+        CURRENT_TIME => (
+            "Get the current date and time in UTC",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        _ => (
+            "Search the text of stored conversations",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 50 }
+                },
+                "required": ["query"]
+            }),
+        ),
+    };
+    Tool {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters: schema.to_string(),
+        input_schema_json: Some(schema.to_string()),
+        output_schema_json: None,
+    }
+}
+
+/// Normalize a model-supplied path inside the workspace: "." and empty segments are dropped and
+/// ".." is refused, so a path can never leave the drive
+pub(crate) fn workspace_path(path: &str) -> Result<String, String> {
+    let mut segments = Vec::new();
+    for segment in path.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => return Err(format!("Path {} leaves the workspace", path)),
+            segment => segments.push(segment),
+        }
+    }
+    Ok(segments.join("/"))
+}
+
+/// Only http(s) URLs whose host is an allowed domain, or a subdomain of one, may be fetched
+pub(crate) fn check_fetch_url(url: &str, allowed_domains: &[String]) -> Result<url::Url, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!(
+            "Only http and https URLs can be fetched, not {}",
+            url
+        ));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| format!("URL {} has no host", url))?
+        .to_ascii_lowercase();
+    let allowed = allowed_domains.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches("*.").to_ascii_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    });
+    if !allowed {
+        return Err(format!("Fetching from {} is not allowed", host));
+    }
+    Ok(parsed)
+}
+
+/// Where a redirect from `from` leads, if that is also allowed. `location` may be relative.
+pub(crate) fn check_redirect(
+    from: &url::Url,
+    location: &str,
+    allowed_domains: &[String],
+) -> Result<url::Url, String> {
+    let target = from
+        .join(location)
+        .map_err(|e| format!("Invalid redirect to {}: {}", location, e))?;
+    check_fetch_url(target.as_str(), allowed_domains)
+        .map_err(|e| format!("Refused redirect from {}: {}", from, e))
+}
+
+fn truncate(text: &mut String, max_bytes: usize) -> bool {
+    if text.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    true
+}
+
+fn string_arg<'a>(params: &'a Value, name: &str) -> Option<&'a str> {
+    params.get(name).and_then(Value::as_str)
+}
+
+pub(crate) fn current_time() -> String {
+    let now = Utc::now();
+    json!({ "utc": now.to_rfc3339(), "unix": now.timestamp() }).to_string()
+}
+
+fn drive() -> Result<String, String> {
+    create_drive(our().package_id(), WORKSPACE_DRIVE, None)
+        .map_err(|e| format!("Failed to open workspace drive: {:?}", e))
+}
+
+pub(crate) async fn workspace_read(
+    params: &Value,
+    config: &BuiltinToolsConfig,
+) -> Result<String, String> {
+    let path = workspace_path(string_arg(params, "path").unwrap_or_default())?;
+    if path.is_empty() {
+        return Err("A file path is required".to_string());
+    }
+    let file = open_file(&format!("{}/{}", drive()?, path), false, None)
+        .map_err(|e| format!("Failed to open {}: {:?}", path, e))?;
+    let bytes = file
+        .read()
+        .map_err(|e| format!("Failed to read {}: {:?}", path, e))?;
+    let mut content =
+        String::from_utf8(bytes).map_err(|_| format!("{} is not a text file", path))?;
+    if truncate(&mut content, config.max_output_bytes as usize) {
+        content.push_str("\n[truncated]");
+    }
+    Ok(content)
+}
+
+pub(crate) async fn workspace_write(params: &Value) -> Result<String, String> {
+    let path = workspace_path(string_arg(params, "path").unwrap_or_default())?;
+    if path.is_empty() {
+        return Err("A file path is required".to_string());
+    }
+    let content = string_arg(params, "content").unwrap_or_default();
+    let drive = drive()?;
+
+    // Create missing parent directories one level at a time
+    let mut dir = drive.clone();
+    if let Some((parents, _)) = path.rsplit_once('/') {
+        for segment in parents.split('/') {
+            dir = format!("{}/{}", dir, segment);
+            open_dir(&dir, true, None)
+                .map_err(|e| format!("Failed to create directory {}: {:?}", dir, e))?;
+        }
+    }
+
+    let file = open_file(&format!("{}/{}", drive, path), true, None)
+        .map_err(|e| format!("Failed to open {}: {:?}", path, e))?;
+    file.write(content.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {:?}", path, e))?;
+    Ok(format!("Wrote {} bytes to {}", content.len(), path))
+}
+
+pub(crate) async fn workspace_list(params: &Value) -> Result<String, String> {
+    let path = workspace_path(string_arg(params, "path").unwrap_or_default())?;
+    let drive = drive()?;
+    let dir_path = if path.is_empty() {
+        drive.clone()
+    } else {
+        format!("{}/{}", drive, path)
+    };
+    let entries = open_dir(&dir_path, false, None)
+        .and_then(|dir| dir.read())
+        .map_err(|e| format!("Failed to list {}: {:?}", dir_path, e))?;
+
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            // Entries come back as drive paths; the model only ever sees workspace paths
+            let relative = entry
+                .path
+                .split_once(&format!("/{}/", WORKSPACE_DRIVE))
+                .map_or(entry.path.as_str(), |(_, rest)| rest);
+            json!({ "path": relative, "type": format!("{:?}", entry.file_type) })
+        })
+        .collect();
+    Ok(json!({ "entries": entries }).to_string())
+}
+
+pub(crate) async fn http_fetch(
+    params: &Value,
+    config: &BuiltinToolsConfig,
+) -> Result<String, String> {
+    let mut url = check_fetch_url(
+        string_arg(params, "url").unwrap_or_default(),
+        &config.fetch_allowed_domains,
+    )?;
+    // Redirects are followed here rather than by the client so every hop is checked against
+    // the allow-list
+    let mut redirects = 0;
+    let response = loop {
+        let response = send_request_await_response(
+            Method::GET,
+            url.clone(),
+            None,
+            FETCH_TIMEOUT_MS,
+            Vec::new(),
+        )
+        .await
+        .map_err(|e| format!("HTTP request failed: {:?}", e))?;
+        let location = response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok());
+        let Some(location) = location.filter(|_| response.status().is_redirection()) else {
+            break response;
+        };
+        if redirects == MAX_FETCH_REDIRECTS {
+            return Err(format!("Too many redirects fetching {}", url));
+        }
+        redirects += 1;
+        url = check_redirect(&url, location, &config.fetch_allowed_domains)?;
+    };
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mut body = String::from_utf8_lossy(response.body()).into_owned();
+    let truncated = truncate(&mut body, config.max_output_bytes as usize);
+    Ok(json!({
+        "status": response.status().as_u16(),
+        "contentType": content_type,
+        "body": body,
+        "truncated": truncated,
+    })
+    .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_paths_cannot_escape_the_drive() {
+        assert_eq!(
+            workspace_path("/notes//./todo.md").unwrap(),
+            "notes/todo.md"
+        );
+        assert_eq!(workspace_path("").unwrap(), "");
+        assert!(workspace_path("notes/../../secrets").is_err());
+        assert!(workspace_path("..\\spider:sys/api_keys").is_err());
+    }
+
+    #[test]
+    fn fetch_is_limited_to_allowed_domains_and_their_subdomains() {
+        let allowed = vec!["example.com".to_string(), "*.docs.rs".to_string()];
+        assert!(check_fetch_url("https://example.com/a", &allowed).is_ok());
+        assert!(check_fetch_url("https://API.example.com/a", &allowed).is_ok());
+        assert!(check_fetch_url("https://serde.docs.rs/", &allowed).is_ok());
+        assert!(check_fetch_url("https://badexample.com/", &allowed).is_err());
+        assert!(check_fetch_url("https://example.com.evil.net/", &allowed).is_err());
+        assert!(check_fetch_url("file:///etc/passwd", &allowed).is_err());
+        assert!(check_fetch_url("https://example.com/", &[]).is_err());
+    }
+
+    #[test]
+    fn redirects_must_stay_on_allowed_domains() {
+        let allowed = vec!["example.com".to_string()];
+        let from = url::Url::parse("https://example.com/docs/a").unwrap();
+        assert_eq!(
+            check_redirect(&from, "b?page=2", &allowed)
+                .unwrap()
+                .as_str(),
+            "https://example.com/docs/b?page=2"
+        );
+        assert!(check_redirect(&from, "https://cdn.example.com/a", &allowed).is_ok());
+        assert!(
+            check_redirect(&from, "http://169.254.169.254/latest/meta-data", &allowed).is_err()
+        );
+        assert!(check_redirect(&from, "//evil.net/a", &allowed).is_err());
+        assert!(check_redirect(&from, "file:///etc/passwd", &allowed).is_err());
+    }
+
+    #[test]
+    fn enabled_tools_shadow_mcp_tools_of_the_same_name() {
+        let mcp = definition(CURRENT_TIME);
+        let mut tools = vec![Tool {
+            description: "MCP clock".to_string(),
+            ..mcp
+        }];
+        add_enabled(
+            &mut tools,
+            &[CURRENT_TIME.to_string(), HTTP_FETCH.to_string()],
+        );
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, [HTTP_FETCH, CURRENT_TIME]);
+        assert!(tools.iter().all(|t| t.description != "MCP clock"));
+        assert!(check_names(&["delete_everything".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use chrono::Utc;
+
+use crate::types::{ContextConfig, Message, Tool, ToolResult};
+
+/// Rough characters-per-token ratio used for estimation.
+/// This intentionally overestimates slightly for English text so that we compact early rather than late.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Fixed per-message overhead (role markers, separators) charged by providers
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+pub(crate) const STRATEGY_NONE: &str = "none";
+pub(crate) const STRATEGY_TRUNCATE: &str = "truncate-tool-results";
+pub(crate) const STRATEGY_SUMMARIZE: &str = "summarize";
+
+pub(crate) fn is_valid_strategy(strategy: &str) -> bool {
+    matches!(
+        strategy,
+        STRATEGY_NONE | STRATEGY_TRUNCATE | STRATEGY_SUMMARIZE
+    )
+}
+
+fn estimate_text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Estimate the number of tokens a message will consume once sent to the provider
+pub(crate) fn estimate_tokens(message: &Message) -> usize {
+    MESSAGE_OVERHEAD_TOKENS
+        + estimate_text_tokens(&message.content)
+        + message
+            .tool_calls_json
+            .as_deref()
+            .map(estimate_text_tokens)
+            .unwrap_or(0)
+        + message
+            .tool_results_json
+            .as_deref()
+            .map(estimate_text_tokens)
+            .unwrap_or(0)
+}
+
+pub(crate) fn estimate_total_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_tokens).sum()
+}
+
+/// Estimate the tokens consumed by tool definitions, which are sent with every request
+pub(crate) fn estimate_tools_tokens(tools: &[Tool]) -> usize {
+    tools
+        .iter()
+        .map(|t| {
+            estimate_text_tokens(&t.name)
+                + estimate_text_tokens(&t.description)
+                + estimate_text_tokens(t.input_schema_json.as_deref().unwrap_or(&t.parameters))
+        })
+        .sum()
+}
+
+/// Token budget available for conversation history
+pub(crate) fn history_budget(
+    config: &ContextConfig,
+    max_output_tokens: u32,
+    tools: &[Tool],
+) -> usize {
+    (config.max_context_tokens as usize)
+        .saturating_sub(max_output_tokens as usize)
+        .saturating_sub(estimate_tools_tokens(tools))
+}
+
+/// Index of the first message that must be kept verbatim.
+/// Never splits an assistant tool call from the tool results that answer it.
+pub(crate) fn recent_window_start(messages: &[Message], keep_recent: usize) -> usize {
+    let mut start = messages.len().saturating_sub(keep_recent);
+    while start > 0 && messages[start].role == "tool" {
+        start -= 1;
+    }
+    start
+}
+
+pub(crate) fn truncate_with_preview(text: &str, preview_chars: usize, note: &str) -> String {
+    let total = text.chars().count();
+    if total <= preview_chars {
+        return text.to_string();
+    }
+    let preview: String = text.chars().take(preview_chars).collect();
+    format!(
+        "{}\n\n[Truncated: showing first {} of {} characters. {}]",
+        preview, preview_chars, total, note
+    )
+}
+
+/// Shrink tool results in messages[..protect_from], oldest first, until the history fits the budget.
+/// Returns true if the messages now fit.
+pub(crate) fn truncate_old_tool_results(
+    messages: &mut [Message],
+    protect_from: usize,
+    budget: usize,
+    preview_chars: usize,
+) -> bool {
+    let mut total = estimate_total_tokens(messages);
+
+    for message in messages.iter_mut().take(protect_from) {
+        if total <= budget {
+            break;
+        }
+        let Some(ref results_json) = message.tool_results_json else {
+            continue;
+        };
+        let Ok(mut results) = serde_json::from_str::<Vec<ToolResult>>(results_json) else {
+            continue;
+        };
+
+        let before = estimate_tokens(message);
+        for result in results.iter_mut() {
+            result.result = truncate_with_preview(
+                &result.result,
+                preview_chars,
+                "Older tool output elided to fit the context window",
+            );
+        }
+        message.tool_results_json = Some(serde_json::to_string(&results).unwrap());
+        total = total - before + estimate_tokens(message);
+    }
+
+    total <= budget
+}
+
+/// Drop the oldest messages until the history fits, keeping at least the recent window. The
+/// trimmed history opens on a user turn, as providers reject one starting with an assistant
+/// message or orphaned tool results.
+pub(crate) fn drop_oldest_messages(messages: &mut Vec<Message>, keep_recent: usize, budget: usize) {
+    let mut dropped = false;
+    while messages.len() > keep_recent.max(1) && estimate_total_tokens(messages) > budget {
+        messages.remove(0);
+        dropped = true;
+    }
+    if dropped {
+        let is_user_turn = |m: &Message| m.role == "user" && m.tool_results_json.is_none();
+        if let Some(first_user) = messages.iter().position(is_user_turn) {
+            messages.drain(..first_user);
+        }
+    }
+}
+
+// Plain-text rendering of messages for summarization prompts, with tool traffic previewed
+fn render_transcript(messages: &[Message], preview_chars: usize) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        transcript.push_str(&format!("[{}] {}\n", message.role, message.content));
+        if let Some(ref calls) = message.tool_calls_json {
+            transcript.push_str(&format!(
+                "Tool calls: {}\n",
+                truncate_with_preview(calls, preview_chars, "elided")
+            ));
+        }
+        if let Some(ref results) = message.tool_results_json {
+            transcript.push_str(&format!(
+                "Tool results: {}\n",
+                truncate_with_preview(results, preview_chars, "elided")
+            ));
+        }
+    }
+    transcript
+}
+
+/// Build the request sent to the model to summarize earlier turns.
+/// `previous_summary` lets summaries roll forward instead of re-reading the whole history.
+pub(crate) fn build_summary_request(
+    previous_summary: Option<&str>,
+    messages: &[Message],
+    preview_chars: usize,
+) -> Vec<Message> {
+    let mut transcript = String::new();
+    if let Some(summary) = previous_summary {
+        transcript.push_str("Summary so far:\n");
+        transcript.push_str(summary);
+        transcript.push_str("\n\nNew messages:\n");
+    }
+    transcript.push_str(&render_transcript(messages, preview_chars));
+
+    vec![Message {
+        role: "user".to_string(),
+        content: format!(
+            "Summarize the following conversation so that it can replace the original messages in \
+             an assistant's context. Preserve the user's goals, decisions made, facts learned from \
+             tool results (ids, names, values) and any open tasks. Be concise and do not add \
+             commentary.\n\n{}",
+            transcript
+        ),
+        tool_calls_json: None,
+        tool_results_json: None,
+        timestamp: Utc::now().timestamp() as u64,
+    }]
+}
+
+/// Build the request asking the summary model for a short conversation title
+pub(crate) fn build_title_request(messages: &[Message], preview_chars: usize) -> Vec<Message> {
+    vec![Message {
+        role: "user".to_string(),
+        content: format!(
+            "Write a title of at most six words for the following conversation. Reply with the \
+             title only, without quotes or punctuation at the end.\n\n{}",
+            render_transcript(messages, preview_chars)
+        ),
+        tool_calls_json: None,
+        tool_results_json: None,
+        timestamp: Utc::now().timestamp() as u64,
+    }]
+}
+
+/// Put a summary of earlier turns in front of the recent messages. Providers reject two user
+/// turns in a row, so when the recent window starts with a user message the summary is merged
+/// into it instead of becoming a message of its own.
+pub(crate) fn with_summary(summary: &str, recent: &[Message]) -> Vec<Message> {
+    let summary = format!(
+        "[Summary of earlier conversation, compacted to fit the context window]\n{}",
+        summary
+    );
+    let mut compacted = recent.to_vec();
+    match compacted.first_mut() {
+        Some(first) if first.role == "user" => {
+            first.content = format!("{}\n\n{}", summary, first.content);
+        }
+        _ => compacted.insert(
+            0,
+            Message {
+                role: "user".to_string(),
+                content: summary,
+                tool_calls_json: None,
+                tool_results_json: None,
+                timestamp: Utc::now().timestamp() as u64,
+            },
+        ),
+    }
+    compacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls_json: None,
+            tool_results_json: None,
+            timestamp: 0,
+        }
+    }
+
+    fn tool_results(result: &str) -> Message {
+        Message {
+            tool_results_json: Some(
+                serde_json::to_string(&[ToolResult {
+                    tool_call_id: "call_1".to_string(),
+                    result: result.to_string(),
+                    is_error: false,
+                }])
+                .unwrap(),
+            ),
+            ..message("tool", "Tool execution results")
+        }
+    }
+
+    #[test]
+    fn estimates_round_up_per_character_plus_overhead() {
+        assert_eq!(
+            estimate_tokens(&message("user", "")),
+            MESSAGE_OVERHEAD_TOKENS
+        );
+        assert_eq!(
+            estimate_tokens(&message("user", "hello")),
+            MESSAGE_OVERHEAD_TOKENS + 2
+        );
+        // Characters, not bytes: four multi-byte characters are one token
+        assert_eq!(
+            estimate_tokens(&message("user", "éééé")),
+            MESSAGE_OVERHEAD_TOKENS + 1
+        );
+        let with_calls = Message {
+            tool_calls_json: Some("12345678".to_string()),
+            ..message("assistant", "abcd")
+        };
+        assert_eq!(
+            estimate_tokens(&with_calls),
+            MESSAGE_OVERHEAD_TOKENS + 1 + 2
+        );
+    }
+
+    #[test]
+    fn recent_window_never_starts_on_tool_results() {
+        let messages = vec![
+            message("user", "a"),
+            message("assistant", "calling"),
+            tool_results("out"),
+            message("assistant", "done"),
+        ];
+        // Keeping two would start on the tool results; the call that produced them comes along
+        assert_eq!(recent_window_start(&messages, 2), 1);
+        assert_eq!(recent_window_start(&messages, 1), 3);
+        assert_eq!(recent_window_start(&messages, 10), 0);
+    }
+
+    #[test]
+    fn truncates_only_tool_results_before_the_protected_window() {
+        let long = "x".repeat(4_000);
+        let mut messages = vec![
+            message("user", "a"),
+            tool_results(&long),
+            message("user", "b"),
+            tool_results(&long),
+        ];
+        let budget = estimate_total_tokens(&messages) - 500;
+        assert!(truncate_old_tool_results(&mut messages, 2, budget, 100));
+        assert!(messages[1]
+            .tool_results_json
+            .as_ref()
+            .unwrap()
+            .contains("[Truncated: showing first 100 of 4000 characters."));
+        assert!(!messages[3]
+            .tool_results_json
+            .as_ref()
+            .unwrap()
+            .contains("Truncated"));
+    }
+
+    #[test]
+    fn dropping_leaves_the_history_opening_on_a_user_turn() {
+        let long = "x".repeat(400);
+        let mut messages = vec![
+            message("user", &long),
+            message("assistant", &long),
+            tool_results("out"),
+            message("assistant", "done"),
+            message("user", "next"),
+            message("assistant", "ok"),
+        ];
+        let budget = estimate_total_tokens(&messages[1..]);
+        drop_oldest_messages(&mut messages, 2, budget);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content, "next");
+
+        // A history that already fits is left alone
+        let mut messages = vec![message("assistant", "hi"), message("user", "a")];
+        drop_oldest_messages(&mut messages, 1, usize::MAX);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn summaries_merge_into_a_leading_user_message() {
+        let merged = with_summary(
+            "earlier",
+            &[message("user", "next"), message("assistant", "ok")],
+        );
+        assert_eq!(merged.len(), 2);
+        assert!(merged[0].content.contains("earlier") && merged[0].content.ends_with("next"));
+
+        let prefixed = with_summary("earlier", &[message("assistant", "ok")]);
+        assert_eq!(prefixed.len(), 2);
+        assert_eq!(prefixed[0].role, "user");
+        assert_eq!(prefixed[1].content, "ok");
+    }
+}
@@ -0,0 +1,165 @@
+use crate::types::{Conversation, ConversationInfo, ConversationSearchHit, Message, ToolResult};
+
+/// Characters of context kept on each side of the first match in a search snippet
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_CHARS: usize = 32;
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Whether a conversation belongs in a listing, search or export
+pub(crate) fn is_visible(
+    conversation: &Conversation,
+    client: Option<&str>,
+    tag: Option<&str>,
+    include_deleted: bool,
+) -> bool {
+    (include_deleted || conversation.deleted_at.is_none())
+        && client.is_none_or(|c| conversation.metadata.client == c)
+        && tag.is_none_or(|t| conversation.tags.iter().any(|tag| tag == t))
+}
+
+/// Listing entry for a conversation
+pub(crate) fn info(conversation: &Conversation) -> ConversationInfo {
+    ConversationInfo {
+        id: conversation.id.clone(),
+        title: conversation.title.clone(),
+        summary: conversation.summary.clone(),
+        tags: conversation.tags.clone(),
+        metadata: conversation.metadata.clone(),
+        llm_provider: conversation.llm_provider.clone(),
+        message_count: conversation.messages.len() as u32,
+        deleted_at: conversation.deleted_at,
+        parent_conversation_id: conversation.parent_conversation_id.clone(),
+    }
+}
+
+/// Tidy a model-generated title: first line only, without surrounding quotes or a trailing period
+pub(crate) fn clean_generated_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let line = line
+        .strip_prefix("Title:")
+        .unwrap_or(line)
+        .trim()
+        .trim_matches(|c| matches!(c, '"' | '\'' | '*' | '#'))
+        .trim_end_matches('.')
+        .trim();
+    let title: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Trim a user-supplied title; an empty title clears it
+pub(crate) fn normalize_title(title: &str) -> Result<Option<String>, String> {
+    let title = title.trim();
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(format!(
+            "Conversation titles are limited to {} characters",
+            MAX_TITLE_CHARS
+        ));
+    }
+    Ok((!title.is_empty()).then(|| title.to_string()))
+}
+
+/// Lowercase, trim and de-duplicate tags, rejecting ones that would be awkward to filter on
+pub(crate) fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_CHARS || tag.chars().any(|c| c.is_whitespace()) {
+            return Err(format!(
+                "Invalid tag '{}': tags are single words of at most {} characters",
+                tag, MAX_TAG_CHARS
+            ));
+        }
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("A conversation can have at most {} tags", MAX_TAGS));
+    }
+    Ok(normalized)
+}
+
+// Everything in a message a user might search for: its text and any tool output it carries
+fn searchable_text(message: &Message) -> String {
+    let mut text = message.content.clone();
+    if let Some(results) = message
+        .tool_results_json
+        .as_ref()
+        .and_then(|json| serde_json::from_str::<Vec<ToolResult>>(json).ok())
+    {
+        for result in results {
+            text.push('\n');
+            text.push_str(&result.result);
+        }
+    }
+    text
+}
+
+// A window of the text around the first occurrence of `term`
+fn snippet(text: &str, lowered: &str, term: &str) -> String {
+    let char_pos = lowered
+        .find(term)
+        .map(|pos| lowered[..pos].chars().count())
+        .unwrap_or(0);
+    let start = char_pos.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let len = SNIPPET_CONTEXT_CHARS * 2 + term.chars().count();
+
+    let mut window: String = text.chars().skip(start).take(len).collect();
+    window = window.split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        window.insert(0, '…');
+    }
+    if text.chars().count() > start + len {
+        window.push('…');
+    }
+    window
+}
+
+/// Messages whose text or tool output contains every whitespace-separated term of the query,
+/// compared case-insensitively
+pub(crate) fn search(conversation: &Conversation, query: &str) -> Vec<ConversationSearchHit> {
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    conversation
+        .messages
+        .iter()
+        .enumerate()
+        .filter_map(|(index, message)| {
+            let text = searchable_text(message);
+            let lowered = text.to_lowercase();
+            if !terms.iter().all(|term| lowered.contains(term.as_str())) {
+                return None;
+            }
+            Some(ConversationSearchHit {
+                conversation_id: conversation.id.clone(),
+                title: conversation.title.clone(),
+                message_index: index as u32,
+                role: message.role.clone(),
+                snippet: snippet(&text, &lowered, &terms[0]),
+                timestamp: message.timestamp,
+            })
+        })
+        .collect()
+}
+
+/// Whether a trashed conversation has outlived the retention window (0 keeps it forever)
+pub(crate) fn retention_expired(
+    conversation: &Conversation,
+    retention_days: u32,
+    now: u64,
+) -> bool {
+    match conversation.deleted_at {
+        Some(deleted_at) if retention_days > 0 => {
+            now.saturating_sub(deleted_at) >= u64::from(retention_days) * 24 * 60 * 60
+        }
+        _ => false,
+    }
+}
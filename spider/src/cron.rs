@@ -0,0 +1,242 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+/// How far ahead to look for a matching minute before deciding an expression never fires
+/// (e.g. "0 0 30 2 *")
+const SEARCH_LIMIT_DAYS: i64 = 366 * 5;
+
+/// A parsed five-field cron expression (minute hour day-of-month month day-of-week), evaluated
+/// in UTC
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Like Vixie cron, when both day fields are restricted a day matching either one fires
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    pub(crate) fn parse(expression: &str) -> Result<CronSchedule, String> {
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields (minute hour day-of-month month day-of-week), got {}",
+                expression,
+                fields.len()
+            ));
+        };
+
+        let field = |value: &str, name: &str, min: u32, max: u32| {
+            parse_field(value, min, max)
+                .map_err(|e| format!("Invalid cron expression '{}': {} {}", expression, name, e))
+        };
+        let mut days_of_week = field(day_of_week, "day-of-week", 0, 7)?;
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(CronSchedule {
+            minutes: field(minute, "minute", 0, 59)?,
+            hours: field(hour, "hour", 0, 23)?,
+            days_of_month: field(day_of_month, "day-of-month", 1, 31)?,
+            months: field(month, "month", 1, 12)?,
+            days_of_week,
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+        })
+    }
+
+    /// The first matching minute strictly after `after` (unix seconds), if any
+    pub(crate) fn next_after(&self, after: u64) -> Option<u64> {
+        let start = Utc.timestamp_opt(after as i64, 0).single()?;
+        let mut time = start.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(SEARCH_LIMIT_DAYS);
+
+        while time <= limit {
+            if !has(self.months, time.month()) {
+                time = start_of_next_month(time)?;
+                continue;
+            }
+            if !self.day_matches(&time) {
+                time = start_of_day(time)? + Duration::days(1);
+                continue;
+            }
+            if !has(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !has(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+            return Some(time.timestamp() as u64);
+        }
+        None
+    }
+
+    fn day_matches(&self, time: &DateTime<Utc>) -> bool {
+        let by_month = has(self.days_of_month, time.day());
+        let by_week = has(self.days_of_week, time.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => by_week,
+            (false, true) => by_month,
+            (false, false) => by_month || by_week,
+        }
+    }
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn start_of_day(time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    time.with_hour(0)?.with_minute(0)
+}
+
+fn start_of_next_month(time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let (year, month) = if time.month() == 12 {
+        (time.year() + 1, 1)
+    } else {
+        (time.year(), time.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()
+}
+
+// One field as a bit mask: comma-separated values, ranges ("1-5") and steps ("*/15", "10-30/5")
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("has an invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("has a step of 0".to_string());
+                }
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // "5/15" means every 15 starting at 5
+            (value, if step.is_some() { max } else { value })
+        };
+        if start > end {
+            return Err(format!("has a backwards range '{}'", range));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    let parsed: u32 = value
+        .parse()
+        .map_err(|_| format!("has an invalid value '{}'", value))?;
+    if parsed < min || parsed > max {
+        return Err(format!("value {} is outside {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> u64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    #[test]
+    fn steps_ranges_and_lists() {
+        let schedule = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // Friday 2024-03-01 17:50 -> next is Monday 09:00
+        assert_eq!(
+            schedule.next_after(at(2024, 3, 1, 17, 50)),
+            Some(at(2024, 3, 4, 9, 0))
+        );
+        assert_eq!(
+            schedule.next_after(at(2024, 3, 4, 9, 0)),
+            Some(at(2024, 3, 4, 9, 15))
+        );
+
+        let schedule = CronSchedule::parse("0 8,20 * * *").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2024, 3, 1, 8, 0)),
+            Some(at(2024, 3, 1, 20, 0))
+        );
+    }
+
+    #[test]
+    fn day_fields_match_either_when_both_are_restricted() {
+        // The 13th of the month or any Friday
+        let schedule = CronSchedule::parse("0 0 13 * 5").unwrap();
+        // Tuesday 2024-02-06 -> Friday 2024-02-09
+        assert_eq!(
+            schedule.next_after(at(2024, 2, 6, 12, 0)),
+            Some(at(2024, 2, 9, 0, 0))
+        );
+        // Saturday 2024-02-10 -> Tuesday the 13th
+        assert_eq!(
+            schedule.next_after(at(2024, 2, 10, 0, 0)),
+            Some(at(2024, 2, 13, 0, 0))
+        );
+    }
+
+    #[test]
+    fn crosses_month_and_year_boundaries() {
+        let schedule = CronSchedule::parse("@yearly").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2024, 6, 15, 10, 30)),
+            Some(at(2025, 1, 1, 0, 0))
+        );
+        let schedule = CronSchedule::parse("30 6 29 2 *").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2024, 3, 1, 0, 0)),
+            Some(at(2028, 2, 29, 6, 30))
+        );
+        // Sunday written as 7
+        let schedule = CronSchedule::parse("0 12 * * 7").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2024, 3, 1, 0, 0)),
+            Some(at(2024, 3, 3, 12, 0))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 31 2 *")
+            .unwrap()
+            .next_after(at(2024, 1, 1, 0, 0))
+            .is_none());
+    }
+}
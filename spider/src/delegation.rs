@@ -0,0 +1,210 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::types::{AgentProfile, ChatResponse, Tool};
+
+pub(crate) const DELEGATE_TOOL: &str = "delegate";
+
+/// Nested runs allowed below a top-level conversation; the deepest level is not offered `delegate`
+pub(crate) const MAX_DEPTH: u32 = 3;
+
+/// Iteration budget of a delegated run when the caller does not pick one
+const DEFAULT_MAX_ITERATIONS: u32 = 10;
+const MAX_ITERATIONS: u32 = 50;
+
+/// What a run may do and where it sits in a delegation chain. Top-level runs use the default:
+/// every tool of their MCP servers and no iteration limit.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RunScope {
+    pub(crate) depth: u32,
+    pub(crate) parent_conversation_id: Option<String>,
+    pub(crate) tools: Option<Vec<String>>, // Names the run may use; None allows all
+    pub(crate) max_iterations: Option<u32>,
+    pub(crate) cancel_job_id: Option<String>, // Job whose cancellation also stops this run
+    pub(crate) pinned_key_id: Option<String>,
+    pub(crate) read_conversations: bool, // Acts for a caller with conversations:read
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct DelegateArgs {
+    pub(crate) task: String,
+    pub(crate) profile: Option<String>,
+    pub(crate) tools: Option<Vec<String>>,
+    pub(crate) max_iterations: Option<u32>,
+}
+
+/// The `delegate` tool as offered to the model, listing the profiles it can pick from
+pub(crate) fn tool(profiles: &[AgentProfile]) -> Tool {
+    let mut profile = json!({
+        "type": "string",
+        "description": "Agent profile (provider, model and MCP servers) to run the sub-agent with; defaults to your own settings"
+    });
+    if !profiles.is_empty() {
+        profile["enum"] = json!(profiles.iter().map(|p| &p.name).collect::<Vec<_>>());
+    }
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "task": {
+                "type": "string",
+                "description": "Everything the sub-agent needs to know; it does not see this conversation"
+            },
+            "profile": profile,
+            "tools": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Names of your tools the sub-agent may use; defaults to all of them"
+            },
+            "max_iterations": {
+                "type": "integer",
+                "minimum": 1,
+                "maximum": MAX_ITERATIONS,
+                "description": format!("Model calls the sub-agent gets before it must answer (default {})", DEFAULT_MAX_ITERATIONS)
+            }
+        },
+        "required": ["task"]
+    });
+    Tool {
+        name: DELEGATE_TOOL.to_string(),
+        description: "Hand a self-contained subtask to a sub-agent and get its final answer back. \
+                      Useful for work that needs many tool calls whose details you do not need."
+            .to_string(),
+        parameters: schema.to_string(),
+        input_schema_json: Some(schema.to_string()),
+        output_schema_json: None,
+    }
+}
+
+/// Restrict a run's tools to its scope and offer `delegate` while the chain may still grow. Runs
+/// without any tools (e.g. peers without tool access) are not offered it either.
+pub(crate) fn scope_tools(tools: &mut Vec<Tool>, scope: &RunScope, profiles: &[AgentProfile]) {
+    // A built-in name shadows any MCP tool called the same
+    tools.retain(|t| t.name != DELEGATE_TOOL);
+    if let Some(allowed) = &scope.tools {
+        tools.retain(|t| allowed.contains(&t.name));
+    }
+    let may_delegate = scope
+        .tools
+        .as_ref()
+        .is_none_or(|allowed| allowed.iter().any(|t| t == DELEGATE_TOOL));
+    if may_delegate && scope.depth < MAX_DEPTH && !tools.is_empty() {
+        tools.push(tool(profiles));
+    }
+}
+
+/// The scope of a run delegated from `parent`. The child can only narrow the parent's tools.
+pub(crate) fn child_scope(
+    parent: &RunScope,
+    parent_conversation_id: &str,
+    parent_tools: &[Tool],
+    args: &DelegateArgs,
+) -> Result<RunScope, String> {
+    if parent.depth >= MAX_DEPTH {
+        return Err(format!(
+            "Delegation is limited to {} nested levels",
+            MAX_DEPTH
+        ));
+    }
+    let available: Vec<String> = parent_tools.iter().map(|t| t.name.clone()).collect();
+    let tools = match &args.tools {
+        Some(requested) => {
+            if let Some(unknown) = requested.iter().find(|t| !available.contains(t)) {
+                return Err(format!("Tool {} is not available to delegate", unknown));
+            }
+            requested.clone()
+        }
+        None => available,
+    };
+    Ok(RunScope {
+        depth: parent.depth + 1,
+        parent_conversation_id: Some(parent_conversation_id.to_string()),
+        tools: Some(tools),
+        max_iterations: Some(
+            args.max_iterations
+                .unwrap_or(DEFAULT_MAX_ITERATIONS)
+                .clamp(1, MAX_ITERATIONS),
+        ),
+        cancel_job_id: parent.cancel_job_id.clone(),
+        pinned_key_id: parent.pinned_key_id.clone(),
+        read_conversations: parent.read_conversations,
+    })
+}
+
+/// Tool result for a finished delegated run
+pub(crate) fn output(response: &ChatResponse) -> String {
+    json!({
+        "conversationId": response.conversation_id,
+        "answer": response.response.content,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: "{}".to_string(),
+            input_schema_json: None,
+            output_schema_json: None,
+        }
+    }
+
+    fn args(tools: Option<Vec<&str>>) -> DelegateArgs {
+        DelegateArgs {
+            task: "Summarize the open issues".to_string(),
+            profile: None,
+            tools: tools.map(|t| t.into_iter().map(String::from).collect()),
+            max_iterations: Some(500),
+        }
+    }
+
+    #[test]
+    fn children_only_narrow_their_parents_tools() {
+        let parent_tools = vec![named("search"), named("fetch"), tool(&[])];
+        let scope = child_scope(
+            &RunScope::default(),
+            "parent",
+            &parent_tools,
+            &args(Some(vec!["search"])),
+        )
+        .unwrap();
+        assert_eq!(scope.depth, 1);
+        assert_eq!(scope.parent_conversation_id.as_deref(), Some("parent"));
+        assert_eq!(scope.max_iterations, Some(MAX_ITERATIONS));
+
+        let mut tools = vec![named("search"), named("fetch"), named("write")];
+        scope_tools(&mut tools, &scope, &[]);
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["search"]);
+
+        assert!(child_scope(
+            &RunScope::default(),
+            "parent",
+            &parent_tools,
+            &args(Some(vec!["write"]))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn delegate_is_withheld_at_the_depth_limit() {
+        let mut scope = RunScope::default();
+        let mut tools = vec![named("search")];
+        scope_tools(&mut tools, &scope, &[]);
+        assert!(tools.iter().any(|t| t.name == DELEGATE_TOOL));
+
+        scope.depth = MAX_DEPTH;
+        let mut tools = vec![named("search"), named(DELEGATE_TOOL)];
+        scope_tools(&mut tools, &scope, &[]);
+        assert!(tools.iter().all(|t| t.name != DELEGATE_TOOL));
+        assert!(child_scope(&scope, "parent", &tools, &args(None)).is_err());
+
+        let mut tools = Vec::new();
+        scope_tools(&mut tools, &RunScope::default(), &[]);
+        assert!(tools.is_empty());
+    }
+}
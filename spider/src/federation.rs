@@ -0,0 +1,52 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use hyperware_process_lib::{hyperapp::send, our, Address, Request};
+
+use crate::types::{FederationPeer, RemoteCallToolRequest, Tool};
+
+/// Seconds to wait for a remote Spider; remote tool calls may themselves be slow
+const REMOTE_TIMEOUT_SECS: u64 = 120;
+
+/// Spider on another node: peers run the same package, so only the node differs
+pub(crate) fn peer_address(node: &str) -> Address {
+    Address::new(node, our().process.clone())
+}
+
+pub(crate) fn find_peer<'a>(peers: &'a [FederationPeer], node: &str) -> Option<&'a FederationPeer> {
+    peers.iter().find(|p| p.node == node)
+}
+
+async fn call_peer<T: DeserializeOwned>(node: &str, body: Value) -> Result<T, String> {
+    let request = Request::to(peer_address(node))
+        .body(serde_json::to_vec(&body).unwrap())
+        .expects_response(REMOTE_TIMEOUT_SECS);
+
+    match send::<Result<T, String>>(request).await {
+        Ok(Ok(response)) => Ok(response),
+        Ok(Err(e)) => Err(format!("Spider on {} returned an error: {}", node, e)),
+        Err(e) => Err(format!("Failed to reach Spider on {}: {:?}", node, e)),
+    }
+}
+
+/// Tools a remote Spider exposes to us
+pub(crate) async fn list_remote_tools(node: &str) -> Result<Vec<Tool>, String> {
+    call_peer(node, serde_json::json!("RemoteListTools")).await
+}
+
+/// Call a tool on a remote Spider, returning its result in MCP form
+pub(crate) async fn call_remote_tool(
+    node: &str,
+    tool_name: &str,
+    arguments: &Value,
+) -> Result<Value, String> {
+    let request = RemoteCallToolRequest {
+        tool_name: tool_name.to_string(),
+        arguments_json: arguments.to_string(),
+    };
+    let result_json: String =
+        call_peer(node, serde_json::json!({ "RemoteCallTool": request })).await?;
+
+    serde_json::from_str(&result_json)
+        .map_err(|e| format!("Invalid tool result from Spider on {}: {}", node, e))
+}
@@ -0,0 +1,96 @@
+use hyperware_process_lib::{
+    http::server::{send_ws_push, WsMessageType},
+    LazyLoadBlob,
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::types::{ChatJob, JobEvent, JobStatus, WsServerMessage};
+
+/// Finished jobs kept for late subscribers and polling clients; older ones are dropped
+const MAX_FINISHED_JOBS: usize = 20;
+
+/// Events kept per job; a long run drops its oldest progress updates first
+const MAX_EVENTS: usize = 200;
+
+/// Stable id for the Spider key that owns a job: a hash, so the key itself is never stored
+pub(crate) fn owner_id(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub(crate) fn new_job(
+    id: String,
+    owner: String,
+    owner_id: String,
+    conversation_id: Option<String>,
+    now: u64,
+) -> ChatJob {
+    ChatJob {
+        id,
+        owner,
+        owner_id,
+        conversation_id,
+        status: JobStatus::Queued,
+        created_at: now,
+        updated_at: now,
+        events: Vec::new(),
+        result: None,
+        error: None,
+    }
+}
+
+/// Record a serialized event, returning its sequence number
+pub(crate) fn push_event(job: &mut ChatJob, event: String, now: u64) -> u32 {
+    let seq = job.events.last().map_or(1, |e| e.seq + 1);
+    job.events.push(JobEvent {
+        seq,
+        timestamp: now,
+        event,
+    });
+    if job.events.len() > MAX_EVENTS {
+        let excess = job.events.len() - MAX_EVENTS;
+        job.events.drain(..excess);
+    }
+    job.updated_at = now;
+    seq
+}
+
+/// Events a client that has seen up to `after_seq` still needs
+pub(crate) fn events_after(job: &ChatJob, after_seq: Option<u32>) -> Vec<JobEvent> {
+    job.events
+        .iter()
+        .filter(|e| after_seq.is_none_or(|after| e.seq > after))
+        .cloned()
+        .collect()
+}
+
+/// Drop the oldest finished jobs beyond the retention cap; unfinished jobs are always kept
+pub(crate) fn prune_finished(jobs: &mut Vec<ChatJob>) {
+    let finished = jobs.iter().filter(|j| j.status.is_finished()).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+    jobs.retain(|job| {
+        if excess > 0 && job.status.is_finished() {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+/// Push a recorded event to a WebSocket channel, wrapped with its job id and sequence number
+pub(crate) fn send_event(channel_id: u32, job_id: &str, seq: u32, event: &str) {
+    let message = WsServerMessage::JobEvent {
+        job_id: job_id.to_string(),
+        seq,
+        event: serde_json::from_str(event).unwrap_or(Value::Null),
+    };
+    let json = serde_json::to_string(&message).unwrap();
+    send_ws_push(
+        channel_id,
+        WsMessageType::Text,
+        LazyLoadBlob::new(Some("application/json"), json),
+    );
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
@@ -9,39 +10,89 @@ use uuid::Uuid;
 use caller_utils::anthropic_api_key_manager::request_api_key_remote_rpc;
 use hyperprocess_macro::*;
 use hyperware_process_lib::{
+    get_blob,
     homepage::add_to_homepage,
     http::{
         client::{open_ws_connection, send_ws_client_push},
         server::{send_ws_push, WsMessageType},
     },
-    hyperapp::source,
-    our, println, Address, LazyLoadBlob, ProcessId,
+    hyperapp::{add_response_header, get_request_header, set_response_status, source},
+    our, println, Address, LazyLoadBlob, ProcessId, Request,
 };
 
+mod audit;
+
+mod builtin_tools;
+
+mod context;
+
+mod cron;
+
+mod delegation;
+use delegation::{DelegateArgs, RunScope};
+
+mod federation;
+
+mod jobs;
+
+mod mcp_health;
+
+mod mcp_server;
+
+mod oauth;
+
+mod permissions;
+use permissions::{grants, migrate_legacy_permissions, resolve_permissions, Permission};
+
 mod provider;
-use provider::create_llm_provider;
+use provider::{create_llm_provider, provider_family, ProviderErrorKind, KNOWN_PROVIDERS};
+
+mod branches;
+
+mod scheduler;
+
+mod conversations;
+
+mod transcript;
+
+mod triggers;
 
 mod types;
 use types::{
-    AddMcpServerRequest, ApiKey, ApiKeyInfo, ChatClient, ChatRequest, ChatResponse, ConfigResponse,
-    ConnectMcpServerRequest, Conversation, ConversationMetadata, CreateSpiderKeyRequest,
-    DisconnectMcpServerRequest, GetConfigRequest, GetConversationRequest, HypergridConnection,
-    HypergridMessage, HypergridMessageType, JsonRpcNotification, JsonRpcRequest,
-    ListApiKeysRequest, ListConversationsRequest, ListMcpServersRequest, ListSpiderKeysRequest,
-    McpCapabilities, McpClientInfo, McpInitializeParams, McpRequestType, McpServer,
-    McpServerDetails, McpToolCallParams, McpToolInfo, Message, OAuthExchangeRequest,
-    OAuthRefreshRequest, OAuthTokenResponse, PendingMcpRequest, ProcessRequest, ProcessResponse,
-    RemoveApiKeyRequest, RemoveMcpServerRequest, RevokeSpiderKeyRequest, SetApiKeyRequest,
-    SpiderApiKey, SpiderState, Tool, ToolCall, ToolExecutionResult, ToolResult, TrialNotification,
-    UpdateConfigRequest, WsClientMessage, WsConnection, WsServerMessage,
+    AddMcpServerRequest, AgentProfile, ApiKey, ApiKeyInfo, AuditEvent, ChatClient, ChatJob,
+    ChatRequest, ChatResponse, ConfigResponse, ConnectMcpServerRequest, Conversation,
+    ConversationInfo, ConversationMetadata, ConversationSearchHit, CreateSpiderKeyRequest,
+    DeleteAgentProfileRequest, DeleteConversationRequest, DeleteScheduledTaskRequest,
+    DeleteTriggerRequest, DisconnectMcpServerRequest, ExportConversationsRequest,
+    ExportTranscriptsRequest, FederationPeer, ForkConversationRequest, GetConfigRequest,
+    GetConversationRequest, GetJobStatusRequest, HypergridConnection, HypergridMessage,
+    HypergridMessageType, ImportTranscriptsRequest, InvokeToolRequest, InvokeToolResponse,
+    JobStatus, JsonRpcNotification, JsonRpcRequest, LinkOAuthLoginRequest,
+    ListAgentProfilesRequest, ListApiKeysRequest, ListAuditEventsRequest, ListConversationsRequest,
+    ListMcpServersRequest, ListProcessGrantsRequest, ListScheduledTasksRequest,
+    ListSpiderKeysRequest, ListTriggersRequest, McpCallToolResult, McpCapabilities, McpClientInfo,
+    McpConnectionState, McpInitializeParams, McpRequestType, McpServer, McpServerDetails,
+    McpToolCallParams, McpToolInfo, Message, MigrateOAuthTokenRequest, OAuthExchangeRequest,
+    OAuthRefreshRequest, OAuthStatusRequest, OAuthTokenResponse, OAuthTokenStatus,
+    PendingMcpRequest, ProcessCallToolRequest, ProcessGrant, ProcessRequest, ProcessResponse,
+    RegenerateResponseRequest, RemoteCallToolRequest, RemoteChatRequest, RemoveApiKeyRequest,
+    RemoveMcpServerRequest, RestoreConversationRequest, RevokeSpiderKeyRequest,
+    SaveAgentProfileRequest, SaveScheduledTaskRequest, SaveTriggerRequest, ScheduledTask,
+    SearchConversationsRequest, SelectBranchRequest, SetApiKeyRequest, SetProcessGrantRequest,
+    SpiderApiKey, SpiderState, TaskNotification, TaskRun, Tool, ToolCall, ToolExecutionResult,
+    ToolResult, TrialNotification, Trigger, TriggerResult, UnlinkOAuthLoginRequest,
+    UpdateConfigRequest, UpdateConversationRequest, WsClientMessage, WsConnection, WsServerMessage,
 };
 
 mod utils;
 use utils::{
-    decrypt_key, discover_mcp_tools, encrypt_key, is_oauth_token, load_conversation_from_vfs,
-    preview_key, save_conversation_to_vfs,
+    decrypt_key, delete_conversation_from_vfs, discover_mcp_tools, encrypt_key, is_oauth_token,
+    load_conversation_from_vfs, preview_key, redact_mcp_server, save_conversation_to_vfs,
+    save_tool_output_to_vfs, send_to_self_after,
 };
 
+mod validation;
+
 #[cfg(not(feature = "simulation-mode"))]
 const API_KEY_DISPENSER_NODE: &str = "free-key-er.os";
 #[cfg(feature = "simulation-mode")]
@@ -66,6 +117,14 @@ const HYPERGRID: &str = "operator:hypergrid:ware.hypr";
             path: "/api-ssd",
             config: HttpBindingConfig::new(true, false, true, None)
         },
+        Binding::Http {
+            path: "/api/trigger",
+            config: HttpBindingConfig::new(false, false, false, None)
+        },
+        Binding::Http {
+            path: "/mcp",
+            config: HttpBindingConfig::new(false, false, false, None)
+        },
         Binding::Ws {
             path: "/ws",
             config: WsBindingConfig::new(false, false, false),
@@ -83,6 +142,24 @@ impl SpiderState {
         self.max_tokens = 4096;
         self.temperature = 1.0;
         self.next_channel_id = 1000; // Start channel IDs at 1000
+        if self.key_selection.is_empty() {
+            self.key_selection = "round-robin".to_string();
+        }
+
+        // Keys saved before key pools existed have no id; give them one so they can be addressed
+        for (_, key) in self.api_keys.iter_mut().filter(|(_, k)| k.id.is_empty()) {
+            key.id = Uuid::new_v4().to_string();
+        }
+
+        // Spider keys issued before typed permissions carry "read"/"write"; map them onto the new set
+        for key in self.spider_api_keys.iter_mut() {
+            key.permissions = migrate_legacy_permissions(&key.permissions);
+        }
+
+        match audit::open_log() {
+            Ok(log) => self.audit_log = log,
+            Err(e) => println!("Spider: Failed to open audit log: {}", e),
+        }
 
         let our_node = our().node.clone();
         println!("Spider MCP client initialized on node: {}", our_node);
@@ -106,6 +183,7 @@ impl SpiderState {
                     hypergrid_token: None,
                     hypergrid_client_id: None,
                     hypergrid_node: None,
+                    remote_node: None,
                 },
                 tools: vec![
                     Tool {
@@ -113,21 +191,26 @@ impl SpiderState {
                         description: "Configure Hypergrid connection credentials. Use this when you receive hypergrid auth strings.".to_string(),
                         parameters: r#"{"type":"object","properties":{"url":{"type":"string"},"token":{"type":"string"},"client_id":{"type":"string"},"node":{"type":"string"}},"required":["url","token","client_id","node"]}"#.to_string(),
                         input_schema_json: Some(r#"{"type":"object","properties":{"url":{"type":"string","description":"The base URL for the Hypergrid API"},"token":{"type":"string","description":"The authentication token"},"client_id":{"type":"string","description":"The unique client ID"},"node":{"type":"string","description":"The Hyperware node name"}},"required":["url","token","client_id","node"]}"#.to_string()),
+                        output_schema_json: None,
                     },
                     Tool {
                         name: "hypergrid_search".to_string(),
                         description: "Search the Hypergrid provider registry for available data providers.".to_string(),
                         parameters: r#"{"type":"object","properties":{"query":{"type":"string"}},"required":["query"]}"#.to_string(),
                         input_schema_json: Some(r#"{"type":"object","properties":{"query":{"type":"string","description":"Search query for providers"}},"required":["query"]}"#.to_string()),
+                        output_schema_json: None,
                     },
                     Tool {
                         name: "hypergrid_call".to_string(),
                         description: "Call a Hypergrid provider with arguments to retrieve data.".to_string(),
                         parameters: r#"{"type":"object","properties":{"providerId":{"type":"string"},"providerName":{"type":"string"},"callArgs":{"type":"array","items":{"type":"array","items":{"type":"string"}}}},"required":["providerId","providerName","callArgs"]}"#.to_string(),
                         input_schema_json: Some(r#"{"type":"object","properties":{"providerId":{"type":"string","description":"The provider ID"},"providerName":{"type":"string","description":"The provider name"},"callArgs":{"type":"array","items":{"type":"array","items":{"type":"string"}},"description":"Arguments as array of [key, value] pairs"}},"required":["providerId","providerName","callArgs"]}"#.to_string()),
+                        output_schema_json: None,
                     },
                 ],
                 connected: true, // Always mark as connected
+                state: McpConnectionState::Ready,
+                last_error: None,
             };
 
             self.mcp_servers.push(hypergrid_server);
@@ -198,7 +281,7 @@ impl SpiderState {
         let existing_admin_key = self
             .spider_api_keys
             .iter()
-            .find(|k| k.name == "Admin GUI Key" && k.permissions.contains(&"admin".to_string()));
+            .find(|k| k.name == "Admin GUI Key" && grants(&k.permissions, Permission::Admin));
 
         if existing_admin_key.is_none() {
             // Generate a random suffix using UUID (take first 12 chars for a good balance)
@@ -208,13 +291,10 @@ impl SpiderState {
             let admin_key = SpiderApiKey {
                 key: format!("sp_admin_gui_key_{}", random_suffix),
                 name: "Admin GUI Key".to_string(),
-                permissions: vec![
-                    "chat".to_string(),
-                    "read".to_string(),
-                    "write".to_string(),
-                    "admin".to_string(),
-                ],
+                permissions: vec![Permission::Admin.as_str().to_string()],
                 created_at: Utc::now().timestamp() as u64,
+                oauth_key_id: None,
+                tool_scopes: None,
             };
 
             self.spider_api_keys.push(admin_key.clone());
@@ -225,67 +305,56 @@ impl SpiderState {
 
         // VFS directory creation will be handled when actually saving files
 
-        // Auto-reconnect to MCP servers that exist in state with retry logic
-        // Note: Don't filter by server.connected since they won't be connected on startup
+        // Connections don't survive a restart: reconnect every server once now and leave
+        // retries to the health loop. Hypergrid is stateless and always usable.
+        for server in self.mcp_servers.iter_mut() {
+            let always_on = server.transport.transport_type == "hypergrid";
+            server.connected = always_on;
+            server.state = if always_on {
+                McpConnectionState::Ready
+            } else {
+                McpConnectionState::Disconnected
+            };
+        }
         let servers_to_reconnect: Vec<String> =
             self.mcp_servers.iter().map(|s| s.id.clone()).collect();
-
         for server_id in servers_to_reconnect {
             println!("Auto-reconnecting to MCP server: {}", server_id);
-
-            // Retry logic with exponential backoff
-            let max_retries = 3;
-            let mut retry_delay_ms = 1000u64; // Start with 1 second
-            let mut success = false;
-
-            for attempt in 1..=max_retries {
-                // Use admin key for auto-reconnect - find the actual admin key
-                let admin_key = self
-                    .spider_api_keys
-                    .iter()
-                    .find(|k| {
-                        k.name == "Admin GUI Key" && k.permissions.contains(&"admin".to_string())
-                    })
-                    .map(|k| k.key.clone())
-                    .unwrap_or_else(|| {
-                        println!("Warning: No admin key found for auto-reconnect");
-                        String::new()
-                    });
-
-                let connect_request = ConnectMcpServerRequest {
-                    server_id: server_id.clone(),
-                    auth_key: admin_key,
-                };
-                match self.connect_mcp_server(connect_request).await {
-                    Ok(msg) => {
-                        println!("Auto-reconnect successful: {}", msg);
-                        success = true;
-                        break;
-                    }
-                    Err(e) => {
-                        println!(
-                            "Failed to auto-reconnect to MCP server {} (attempt {}/{}): {}",
-                            server_id, attempt, max_retries, e
-                        );
-
-                        if attempt < max_retries {
-                            println!("Retrying in {} ms...", retry_delay_ms);
-                            let _ = hyperware_process_lib::hyperapp::sleep(retry_delay_ms).await;
-
-                            // Exponential backoff with max delay of 10 seconds
-                            retry_delay_ms = (retry_delay_ms * 2).min(10000);
-                        }
-                    }
-                }
-            }
-
-            if !success {
-                println!(
-                    "Failed to reconnect to MCP server {} after {} attempts",
-                    server_id, max_retries
-                );
+            match self.open_mcp_connection(&server_id).await {
+                Ok(msg) => println!("Auto-reconnect successful: {}", msg),
+                Err(e) => println!(
+                    "Failed to auto-reconnect to MCP server {}: {}",
+                    server_id, e
+                ),
             }
         }
+        self.schedule_mcp_health_tick();
+
+        self.purge_expired_conversations().await;
+
+        // Jobs that were in flight when Spider stopped will never finish
+        let now = Utc::now().timestamp() as u64;
+        for job in self
+            .chat_jobs
+            .iter_mut()
+            .filter(|j| !j.status.is_finished())
+        {
+            job.status = JobStatus::Failed;
+            job.error = Some("Interrupted by a restart".to_string());
+            job.updated_at = now;
+        }
+        for run in self
+            .scheduled_tasks
+            .iter_mut()
+            .flat_map(|t| t.runs.iter_mut())
+            .filter(|r| !r.status.is_finished())
+        {
+            run.status = JobStatus::Failed;
+            run.error = Some("Interrupted by a restart".to_string());
+            run.finished_at = Some(now);
+        }
+        // Slots missed while Spider was stopped run once on the first tick
+        self.schedule_scheduler_tick();
 
         // Check if we need to request a free API key
         if self.api_keys.is_empty() {
@@ -303,10 +372,17 @@ impl SpiderState {
                     self.api_keys.push((
                         "anthropic".to_string(),
                         ApiKey {
+                            id: Uuid::new_v4().to_string(),
                             provider: "anthropic".to_string(),
                             key: encrypted_key,
                             created_at: Utc::now().timestamp() as u64,
                             last_used: None,
+                            usage_count: 0,
+                            last_error: None,
+                            refresh_token: None,
+                            expires_at: None,
+                            last_refreshed: None,
+                            delegated: false,
                         },
                     ));
 
@@ -342,24 +418,48 @@ impl SpiderState {
                 let message_str = String::from_utf8(message_bytes).unwrap_or_default();
                 println!("handle_websocket: got {message_str}");
 
+                // MCP clients speak JSON-RPC on the same socket, authenticated either by the auth
+                // frame or by the key their `initialize` request carries
+                if let Ok(message) = serde_json::from_str::<Value>(&message_str) {
+                    if message.get("jsonrpc").is_some() || message.is_array() {
+                        let api_key = match self.chat_clients.get(&channel_id) {
+                            Some(client) => client.api_key.clone(),
+                            None => {
+                                let api_key = mcp_server::initialize_api_key(&message)
+                                    .unwrap_or_default()
+                                    .to_string();
+                                // handle_mcp_rpc reports a bad key; only register a good one
+                                if self.validate_spider_key(&api_key)
+                                    && self.validate_permission(&api_key, Permission::ToolsCall)
+                                {
+                                    self.register_chat_client(channel_id, &api_key);
+                                }
+                                api_key
+                            }
+                        };
+                        if let Some(response) = self.handle_mcp_rpc_batch(&api_key, message).await {
+                            send_ws_push(
+                                channel_id,
+                                WsMessageType::Text,
+                                LazyLoadBlob::new(Some("application/json"), response.to_string()),
+                            );
+                        }
+                        return;
+                    }
+                }
+
                 // Parse the incoming message using typed enum
                 match serde_json::from_str::<WsClientMessage>(&message_str) {
                     Ok(msg) => {
                         match msg {
                             WsClientMessage::Auth { api_key } => {
-                                // Validate API key exists and has write permission (required for chat)
+                                // Validate API key exists and can chat or call tools over MCP
                                 if self.validate_spider_key(&api_key)
-                                    && self.validate_permission(&api_key, "write")
+                                    && (self.validate_permission(&api_key, Permission::Chat)
+                                        || self
+                                            .validate_permission(&api_key, Permission::ToolsCall))
                                 {
-                                    self.chat_clients.insert(
-                                        channel_id,
-                                        ChatClient {
-                                            channel_id,
-                                            api_key: api_key.clone(),
-                                            conversation_id: None,
-                                            connected_at: Utc::now().timestamp() as u64,
-                                        },
-                                    );
+                                    self.register_chat_client(channel_id, &api_key);
 
                                     // Send auth success response
                                     let response = WsServerMessage::AuthSuccess {
@@ -374,9 +474,9 @@ impl SpiderState {
                                 } else {
                                     // Send auth failure and close connection
                                     let error_msg = if !self.validate_spider_key(&api_key) {
-                                        "Invalid API key".to_string()
+                                        self.invalid_key_error(&api_key)
                                     } else {
-                                        "API key lacks write permission required for chat"
+                                        "API key lacks the chat or tools:call permission"
                                             .to_string()
                                     };
 
@@ -397,11 +497,10 @@ impl SpiderState {
                             WsClientMessage::Chat { payload } => {
                                 if let Some(client) = self.chat_clients.get(&channel_id).cloned() {
                                     // Double-check permissions (defense in depth)
-                                    if !self.validate_permission(&client.api_key, "write") {
+                                    if !self.validate_permission(&client.api_key, Permission::Chat)
+                                    {
                                         let response = WsServerMessage::Error {
-                                            error:
-                                                "API key lacks write permission required for chat"
-                                                    .to_string(),
+                                            error: "API key lacks the chat permission".to_string(),
                                         };
                                         let json = serde_json::to_string(&response).unwrap();
                                         send_ws_push(
@@ -420,39 +519,29 @@ impl SpiderState {
                                         model: payload.model,
                                         mcp_servers: payload.mcp_servers,
                                         metadata: payload.metadata,
+                                        conversation_id: payload.conversation_id,
+                                        profile_id: payload.profile_id,
                                     };
 
-                                    // Process the chat request asynchronously
-                                    match self
-                                        .process_chat_request_with_streaming(
-                                            chat_request,
-                                            channel_id,
-                                        )
-                                        .await
-                                    {
-                                        Ok(response) => {
-                                            // Send final response
-                                            let ws_response =
-                                                WsServerMessage::ChatComplete { payload: response };
-                                            let json = serde_json::to_string(&ws_response).unwrap();
-                                            send_ws_push(
-                                                channel_id,
-                                                WsMessageType::Text,
-                                                LazyLoadBlob::new(Some("application/json"), json),
-                                            );
-                                        }
-                                        Err(e) => {
-                                            let error_response =
-                                                WsServerMessage::Error { error: e };
-                                            let json =
-                                                serde_json::to_string(&error_response).unwrap();
-                                            send_ws_push(
-                                                channel_id,
-                                                WsMessageType::Text,
-                                                LazyLoadBlob::new(Some("application/json"), json),
-                                            );
-                                        }
-                                    }
+                                    // Run as a background job so closing this socket does not lose it
+                                    let response =
+                                        match self.queue_chat_job(chat_request, Some(channel_id)) {
+                                            Ok(job_id) => {
+                                                if let Some(client) =
+                                                    self.chat_clients.get_mut(&channel_id)
+                                                {
+                                                    client.job_id = Some(job_id.clone());
+                                                }
+                                                WsServerMessage::JobStarted { job_id }
+                                            }
+                                            Err(e) => WsServerMessage::Error { error: e },
+                                        };
+                                    let json = serde_json::to_string(&response).unwrap();
+                                    send_ws_push(
+                                        channel_id,
+                                        WsMessageType::Text,
+                                        LazyLoadBlob::new(Some("application/json"), json),
+                                    );
                                 } else {
                                     // Not authenticated
                                     let response = WsServerMessage::Error {
@@ -467,21 +556,44 @@ impl SpiderState {
                                     );
                                 }
                             }
-                            WsClientMessage::Cancel => {
-                                // Cancel any active chat request for this channel
-                                if let Some(cancel_flag) =
-                                    self.active_chat_cancellation.get(&channel_id)
-                                {
-                                    cancel_flag.store(true, Ordering::Relaxed);
-                                    println!(
-                                        "Spider: Cancelling chat request for channel {}",
-                                        channel_id
-                                    );
-
-                                    // Send cancellation confirmation
-                                    let response = WsServerMessage::Status {
-                                        status: "cancelled".to_string(),
-                                        message: Some("Request cancelled".to_string()),
+                            WsClientMessage::Cancel { job_id } => {
+                                // Cancel the given job, or the latest one started on this channel
+                                let Some(client) = self.chat_clients.get(&channel_id).cloned()
+                                else {
+                                    return;
+                                };
+                                let Some(job_id) = job_id.or(client.job_id) else {
+                                    return;
+                                };
+                                let response = match self.authorize_job(&client.api_key, &job_id) {
+                                    Ok(_) => match self.active_chat_cancellation.get(&job_id) {
+                                        Some(cancel_flag) => {
+                                            cancel_flag.store(true, Ordering::Relaxed);
+                                            println!("Spider: Cancelling chat job {}", job_id);
+                                            WsServerMessage::Status {
+                                                status: "cancelled".to_string(),
+                                                message: Some("Request cancelled".to_string()),
+                                            }
+                                        }
+                                        None => WsServerMessage::Error {
+                                            error: format!("Chat job {} is not running", job_id),
+                                        },
+                                    },
+                                    Err(e) => WsServerMessage::Error { error: e },
+                                };
+                                let json = serde_json::to_string(&response).unwrap();
+                                send_ws_push(
+                                    channel_id,
+                                    WsMessageType::Text,
+                                    LazyLoadBlob::new(Some("application/json"), json),
+                                );
+                            }
+                            WsClientMessage::SubscribeJob { job_id, after_seq } => {
+                                let Some(client) = self.chat_clients.get(&channel_id).cloned()
+                                else {
+                                    let response = WsServerMessage::Error {
+                                        error: "Not authenticated. Please send auth message first."
+                                            .to_string(),
                                     };
                                     let json = serde_json::to_string(&response).unwrap();
                                     send_ws_push(
@@ -489,6 +601,78 @@ impl SpiderState {
                                         WsMessageType::Text,
                                         LazyLoadBlob::new(Some("application/json"), json),
                                     );
+                                    return;
+                                };
+                                if let Err(e) = self.subscribe_to_job(
+                                    &client.api_key,
+                                    &job_id,
+                                    after_seq,
+                                    channel_id,
+                                ) {
+                                    let response = WsServerMessage::Error { error: e };
+                                    let json = serde_json::to_string(&response).unwrap();
+                                    send_ws_push(
+                                        channel_id,
+                                        WsMessageType::Text,
+                                        LazyLoadBlob::new(Some("application/json"), json),
+                                    );
+                                }
+                            }
+                            WsClientMessage::WatchConversations { conversation_ids } => {
+                                let response = match self.chat_clients.get(&channel_id).cloned() {
+                                    None => WsServerMessage::Error {
+                                        error: "Not authenticated. Please send auth message first."
+                                            .to_string(),
+                                    },
+                                    Some(client)
+                                        if !self.validate_permission(
+                                            &client.api_key,
+                                            Permission::ConversationsRead,
+                                        ) =>
+                                    {
+                                        WsServerMessage::Error {
+                                            error:
+                                                "API key lacks the conversations:read permission"
+                                                    .to_string(),
+                                        }
+                                    }
+                                    Some(_) => {
+                                        let client =
+                                            self.chat_clients.get_mut(&channel_id).unwrap();
+                                        match conversation_ids {
+                                            Some(ids) => {
+                                                for id in ids {
+                                                    if !client.watched_conversations.contains(&id) {
+                                                        client.watched_conversations.push(id);
+                                                    }
+                                                }
+                                            }
+                                            None => client.watch_all_conversations = true,
+                                        }
+                                        WsServerMessage::Status {
+                                            status: "watching".to_string(),
+                                            message: None,
+                                        }
+                                    }
+                                };
+                                let json = serde_json::to_string(&response).unwrap();
+                                send_ws_push(
+                                    channel_id,
+                                    WsMessageType::Text,
+                                    LazyLoadBlob::new(Some("application/json"), json),
+                                );
+                            }
+                            WsClientMessage::UnwatchConversations { conversation_ids } => {
+                                if let Some(client) = self.chat_clients.get_mut(&channel_id) {
+                                    match conversation_ids {
+                                        Some(ids) => client
+                                            .watched_conversations
+                                            .retain(|id| !ids.contains(id)),
+                                        None => {
+                                            client.watched_conversations.clear();
+                                            client.watch_all_conversations = false;
+                                        }
+                                    }
                                 }
                             }
                             WsClientMessage::Ping => {
@@ -521,8 +705,11 @@ impl SpiderState {
                 }
             }
             WsMessageType::Close => {
-                // Clean up client connection
+                // Clean up client connection; its jobs keep running for other subscribers
                 self.chat_clients.remove(&channel_id);
+                for subscribers in self.job_subscribers.values_mut() {
+                    subscribers.retain(|c| *c != channel_id);
+                }
                 println!("Chat client {} disconnected", channel_id);
             }
             WsMessageType::Ping | WsMessageType::Pong => {
@@ -562,25 +749,20 @@ impl SpiderState {
                     channel_id
                 );
 
-                // Find and disconnect the server
-                if let Some(conn) = self.ws_connections.remove(&channel_id) {
-                    // Mark server as disconnected
-                    if let Some(server) =
-                        self.mcp_servers.iter_mut().find(|s| s.id == conn.server_id)
-                    {
-                        server.connected = false;
-                        println!("Spider: MCP server {} disconnected", server.name);
-                    }
+                // Find and disconnect the server, failing whatever was still in flight
+                if let Some(conn) =
+                    self.close_mcp_session(channel_id, "MCP server closed the connection")
+                {
+                    println!("Spider: MCP server {} disconnected", conn.server_name);
+                    // Unexpected drops are retried; deliberate disconnects already closed the
+                    // session, so they don't reach this point
+                    self.set_mcp_server_state(
+                        &conn.server_id,
+                        McpConnectionState::Failed,
+                        Some("Connection closed by server".to_string()),
+                    );
+                    self.schedule_mcp_reconnect(&conn.server_id);
                 }
-
-                // Clean up any pending requests for this connection
-                self.pending_mcp_requests.retain(|_, req| {
-                    if let Some(conn) = self.ws_connections.get(&channel_id) {
-                        req.server_id != conn.server_id
-                    } else {
-                        true
-                    }
-                });
             }
             WsMessageType::Ping | WsMessageType::Pong => {
                 // Ignore ping/pong messages for now
@@ -590,41 +772,30 @@ impl SpiderState {
 
     #[http]
     async fn set_api_key(&mut self, request: SetApiKeyRequest) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
-
-        let encrypted_key = encrypt_key(&request.key);
-
-        let api_key = ApiKey {
-            provider: request.provider.clone(),
-            key: encrypted_key,
-            created_at: Utc::now().timestamp() as u64,
-            last_used: None,
-        };
-
-        self.api_keys.retain(|(p, _)| p != &request.provider);
-        self.api_keys.push((request.provider.clone(), api_key));
-
-        Ok(format!("API key for {} set successfully", request.provider))
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.provider.clone();
+        let result = self.set_api_key_impl(request).await;
+        self.audited(&actor, "api_key.set", &target, result)
     }
 
     #[http]
     async fn list_api_keys(&self, request: ListApiKeysRequest) -> Result<Vec<ApiKeyInfo>, String> {
-        // Validate read permission
-        if !self.validate_permission(&request.auth_key, "read") {
-            return Err("Unauthorized: API key lacks read permission".to_string());
+        // Validate keys:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
         }
 
         let keys: Vec<ApiKeyInfo> = self
             .api_keys
             .iter()
             .map(|(provider, key)| ApiKeyInfo {
+                id: key.id.clone(),
                 provider: provider.clone(),
                 created_at: key.created_at,
                 last_used: key.last_used,
                 key_preview: preview_key(&key.key),
+                usage_count: key.usage_count,
+                last_error: key.last_error.clone(),
             })
             .collect();
 
@@ -633,19 +804,13 @@ impl SpiderState {
 
     #[http]
     async fn remove_api_key(&mut self, request: RemoveApiKeyRequest) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
-
-        let initial_len = self.api_keys.len();
-        self.api_keys.retain(|(p, _)| p != &request.provider);
-
-        if self.api_keys.len() < initial_len {
-            Ok(format!("API key for {} removed", request.provider))
-        } else {
-            Err(format!("No API key found for {}", request.provider))
-        }
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request
+            .key_id
+            .clone()
+            .unwrap_or_else(|| request.provider.clone());
+        let result = self.remove_api_key_impl(request).await;
+        self.audited(&actor, "api_key.remove", &target, result)
     }
 
     #[local]
@@ -654,24 +819,10 @@ impl SpiderState {
         &mut self,
         request: CreateSpiderKeyRequest,
     ) -> Result<SpiderApiKey, String> {
-        // Validate admin key
-        let hypergrid: ProcessId = HYPERGRID.parse().unwrap();
-        if !(self.validate_admin_key(&request.admin_key) || source().process == hypergrid) {
-            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
-        }
-
-        let key = format!("sp_{}", Uuid::new_v4().to_string().replace("-", ""));
-
-        let spider_key = SpiderApiKey {
-            key: key.clone(),
-            name: request.name,
-            permissions: request.permissions,
-            created_at: Utc::now().timestamp() as u64,
-        };
-
-        self.spider_api_keys.push(spider_key.clone());
-
-        Ok(spider_key)
+        let actor = self.audit_actor(&request.admin_key);
+        let target = request.name.clone();
+        let result = self.create_spider_key_impl(request).await;
+        self.audited(&actor, "spider_key.create", &target, result)
     }
 
     #[http]
@@ -692,40 +843,56 @@ impl SpiderState {
         &mut self,
         request: RevokeSpiderKeyRequest,
     ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.admin_key);
+        let target = self.spider_key_name(&request.key_id);
+        let result = self.revoke_spider_key_impl(request).await;
+        self.audited(&actor, "spider_key.revoke", &target, result)
+    }
+
+    #[http]
+    async fn list_audit_events(
+        &self,
+        request: ListAuditEventsRequest,
+    ) -> Result<Vec<AuditEvent>, String> {
         // Validate admin key
         if !self.validate_admin_key(&request.admin_key) {
             return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
         }
 
-        let initial_len = self.spider_api_keys.len();
-        self.spider_api_keys.retain(|k| k.key != request.key_id);
+        audit::query_events(&self.audit_log, &request)
+    }
 
-        if self.spider_api_keys.len() < initial_len {
-            Ok(format!("Spider API key {} revoked", request.key_id))
-        } else {
-            Err(format!("Spider API key {} not found", request.key_id))
-        }
+    // Grant a local process permissions for process_request; an empty list revokes it
+    #[http]
+    async fn set_process_grant(
+        &mut self,
+        request: SetProcessGrantRequest,
+    ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.admin_key);
+        let target = request.process.clone();
+        let result = self.set_process_grant_impl(request).await;
+        self.audited(&actor, "process_grant.set", &target, result)
     }
 
     #[http]
-    async fn add_mcp_server(&mut self, request: AddMcpServerRequest) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
+    async fn list_process_grants(
+        &self,
+        request: ListProcessGrantsRequest,
+    ) -> Result<Vec<ProcessGrant>, String> {
+        // Validate admin key
+        if !self.validate_admin_key(&request.admin_key) {
+            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
         }
 
-        let server = McpServer {
-            id: Uuid::new_v4().to_string(),
-            name: request.name,
-            transport: request.transport,
-            tools: Vec::new(),
-            connected: false,
-        };
-
-        let server_id = server.id.clone();
-        self.mcp_servers.push(server);
+        Ok(self.process_grants.clone())
+    }
 
-        Ok(server_id)
+    #[http]
+    async fn add_mcp_server(&mut self, request: AddMcpServerRequest) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.name.clone();
+        let result = self.add_mcp_server_impl(request).await;
+        self.audited(&actor, "mcp_server.add", &target, result)
     }
 
     #[local]
@@ -734,12 +901,17 @@ impl SpiderState {
         &self,
         request: ListMcpServersRequest,
     ) -> Result<Vec<McpServer>, String> {
-        // Validate read permission
-        if !self.validate_permission(&request.auth_key, "read") {
-            return Err("Unauthorized: API key lacks read permission".to_string());
+        // Validate chat permission
+        if !self.validate_permission(&request.auth_key, Permission::Chat) {
+            return Err("Unauthorized: API key lacks chat permission".to_string());
         }
 
-        Ok(self.mcp_servers.clone())
+        // Transport credentials are only shown to keys that manage servers
+        if self.validate_permission(&request.auth_key, Permission::McpManage) {
+            Ok(self.mcp_servers.clone())
+        } else {
+            Ok(self.mcp_servers.iter().map(redact_mcp_server).collect())
+        }
     }
 
     #[http]
@@ -747,42 +919,10 @@ impl SpiderState {
         &mut self,
         request: DisconnectMcpServerRequest,
     ) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
-
-        // Find the server
-        let server_name = {
-            let server = self
-                .mcp_servers
-                .iter_mut()
-                .find(|s| s.id == request.server_id)
-                .ok_or_else(|| format!("MCP server {} not found", request.server_id))?;
-            server.connected = false;
-            server.name.clone()
-        };
-
-        // Find and close the WebSocket connection
-        let channel_to_close = self
-            .ws_connections
-            .iter()
-            .find(|(_, conn)| conn.server_id == request.server_id)
-            .map(|(id, _)| *id);
-
-        if let Some(channel_id) = channel_to_close {
-            // Send close message
-            send_ws_client_push(channel_id, WsMessageType::Close, LazyLoadBlob::default());
-
-            // Remove the connection
-            self.ws_connections.remove(&channel_id);
-
-            // Clean up any pending requests for this server
-            self.pending_mcp_requests
-                .retain(|_, req| req.server_id != request.server_id);
-        }
-
-        Ok(format!("Disconnected from MCP server {}", server_name))
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.server_id.clone();
+        let result = self.disconnect_mcp_server_impl(request).await;
+        self.audited(&actor, "mcp_server.disconnect", &target, result)
     }
 
     #[http]
@@ -790,27 +930,10 @@ impl SpiderState {
         &mut self,
         request: RemoveMcpServerRequest,
     ) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
-
-        // First disconnect if connected
-        let disconnect_request = DisconnectMcpServerRequest {
-            server_id: request.server_id.clone(),
-            auth_key: request.auth_key.clone(),
-        };
-        let _ = self.disconnect_mcp_server(disconnect_request).await;
-
-        // Remove the server from the list
-        let initial_len = self.mcp_servers.len();
-        self.mcp_servers.retain(|s| s.id != request.server_id);
-
-        if self.mcp_servers.len() < initial_len {
-            Ok(format!("MCP server {} removed", request.server_id))
-        } else {
-            Err(format!("MCP server {} not found", request.server_id))
-        }
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.server_id.clone();
+        let result = self.remove_mcp_server_impl(request).await;
+        self.audited(&actor, "mcp_server.remove", &target, result)
     }
 
     #[http]
@@ -818,18 +941,398 @@ impl SpiderState {
         &mut self,
         request: ConnectMcpServerRequest,
     ) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.server_id.clone();
+        let result = self.connect_mcp_server_impl(request).await;
+        self.audited(&actor, "mcp_server.connect", &target, result)
+    }
 
+    // MCP health loop. Each tick schedules the next one on a timer, so no handler stays busy
+    // between ticks.
+    #[local]
+    async fn mcp_health_tick(&mut self) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: health ticks come from Spider itself".to_string());
+        }
+        self.check_mcp_health().await;
+        self.schedule_mcp_health_tick();
+        Ok(())
+    }
+
+    // Title and summarize a conversation with the configured summary model. Scheduled by
+    // Spider itself once a chat finishes.
+    #[local]
+    async fn summarize_conversation(&mut self, conversation_id: String) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: summaries are scheduled by Spider itself".to_string());
+        }
+        let result = self.refresh_conversation_summary(&conversation_id).await;
+        if let Err(e) = &result {
+            println!(
+                "Spider: Failed to summarize conversation {}: {}",
+                conversation_id, e
+            );
+        }
+        result
+    }
+
+    // Execute a queued chat job. Scheduled by Spider itself so the run is not tied to the
+    // WebSocket or HTTP request that started it.
+    #[local]
+    async fn run_chat_job(&mut self, job_id: String) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: chat jobs are started by Spider itself".to_string());
+        }
+        let request = self
+            .job_requests
+            .remove(&job_id)
+            .ok_or_else(|| format!("Chat job {} has no pending request", job_id))?;
+
+        if let Some(job) = self.chat_jobs.iter_mut().find(|j| j.id == job_id) {
+            job.status = JobStatus::Running;
+            job.updated_at = Utc::now().timestamp() as u64;
+        }
+        self.emit_job_event(
+            &job_id,
+            &WsServerMessage::Status {
+                status: "processing".to_string(),
+                message: Some("Starting chat processing...".to_string()),
+            },
+        );
+
+        let result = self.process_chat_internal(request, Some(&job_id)).await;
+
+        let cancelled = self
+            .active_chat_cancellation
+            .remove(&job_id)
+            .is_some_and(|flag| flag.load(Ordering::Relaxed));
+        let status = match &result {
+            Ok(_) => JobStatus::Completed,
+            Err(_) if cancelled => JobStatus::Cancelled,
+            Err(_) => JobStatus::Failed,
+        };
+
+        self.emit_job_event(
+            &job_id,
+            &WsServerMessage::Status {
+                status: match status {
+                    JobStatus::Cancelled => "cancelled",
+                    JobStatus::Failed => "failed",
+                    _ => "complete",
+                }
+                .to_string(),
+                message: None,
+            },
+        );
+        let final_event = match &result {
+            Ok(response) => WsServerMessage::ChatComplete {
+                payload: response.clone(),
+            },
+            Err(e) => WsServerMessage::Error { error: e.clone() },
+        };
+        self.emit_job_event(&job_id, &final_event);
+
+        if let Some(job) = self.chat_jobs.iter_mut().find(|j| j.id == job_id) {
+            job.status = status;
+            job.updated_at = Utc::now().timestamp() as u64;
+            match &result {
+                Ok(response) => {
+                    job.conversation_id = Some(response.conversation_id.clone());
+                    job.result = Some(response.clone());
+                }
+                Err(e) => job.error = Some(e.clone()),
+            }
+        }
+        self.job_subscribers.remove(&job_id);
+        jobs::prune_finished(&mut self.chat_jobs);
+
+        result.map(|_| ())
+    }
+
+    // Scheduler loop: each tick starts the tasks that are due and schedules the next one on a
+    // timer
+    #[local]
+    async fn scheduler_tick(&mut self) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: scheduler ticks come from Spider itself".to_string());
+        }
+        self.start_due_tasks();
+        self.schedule_scheduler_tick();
+        Ok(())
+    }
+
+    // Run one scheduled task. Each run is its own message so a slow task does not hold up the
+    // scheduler or other tasks.
+    #[local]
+    async fn run_scheduled_task(&mut self, task_id: String) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: scheduled tasks are started by Spider itself".to_string());
+        }
+        self.execute_scheduled_task(&task_id).await
+    }
+
+    // Run a trigger whose result goes to its callback URL, after /api/trigger has answered
+    #[local]
+    async fn run_trigger(&mut self, run_id: String) -> Result<(), String> {
+        if source() != our() {
+            return Err("Forbidden: trigger runs are started by Spider itself".to_string());
+        }
+        let (trigger_id, request) = self
+            .trigger_runs
+            .remove(&run_id)
+            .ok_or_else(|| format!("Trigger run {} has no pending request", run_id))?;
+        let trigger = self
+            .triggers
+            .iter()
+            .find(|t| t.id == trigger_id)
+            .cloned()
+            .ok_or_else(|| format!("Trigger {} was deleted before it ran", trigger_id))?;
+
+        let actor = format!("trigger:{}", trigger.name);
+        let result = self.run_chat(request, None, &actor, None, None).await;
+        self.record_audit(&actor, "trigger.fire", &trigger.id, &result);
+
+        if let Some(callback_url) = trigger.callback_url.as_deref() {
+            let body = triggers::result(&trigger.id, run_id, &result);
+            if let Err(e) = triggers::post_callback(callback_url, &body).await {
+                println!(
+                    "Spider: Failed to deliver trigger {} result to {}: {}",
+                    trigger.name, callback_url, e
+                );
+            }
+        }
+        result.map(|_| ())
+    }
+
+    // Connect (or reconnect) to an MCP server; failures of reconnecting transports are retried
+    // by the health loop
+    async fn open_mcp_connection(&mut self, server_id: &str) -> Result<String, String> {
+        let result = self.connect_transport(server_id).await;
+        if let Err(e) = &result {
+            let reconnects = self
+                .mcp_servers
+                .iter()
+                .find(|s| s.id == server_id)
+                .is_some_and(|s| mcp_health::reconnects(&s.transport.transport_type));
+            if reconnects {
+                self.set_mcp_server_state(server_id, McpConnectionState::Failed, Some(e.clone()));
+                self.schedule_mcp_reconnect(server_id);
+            } else if let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == server_id) {
+                server.last_error = Some(e.clone());
+            }
+        }
+        result
+    }
+
+    fn set_mcp_server_state(
+        &mut self,
+        server_id: &str,
+        state: McpConnectionState,
+        last_error: Option<String>,
+    ) {
+        let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == server_id) else {
+            return;
+        };
+        // Keep the last error around after recovering unless a new one replaces it
+        let last_error = last_error.or_else(|| server.last_error.clone());
+        if server.state == state && server.last_error == last_error {
+            return;
+        }
+        server.state = state;
+        server.connected = state.is_usable();
+        server.last_error = last_error.clone();
+        println!("Spider: MCP server {} is now {:?}", server.name, state);
+
+        // Let connected UIs update their server lists
+        let event = WsServerMessage::McpServerState {
+            server_id: server_id.to_string(),
+            state,
+            last_error,
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        for channel_id in self.chat_clients.keys() {
+            send_ws_push(
+                *channel_id,
+                WsMessageType::Text,
+                LazyLoadBlob::new(Some("application/json"), json.clone()),
+            );
+        }
+    }
+
+    fn schedule_mcp_reconnect(&mut self, server_id: &str) {
+        let health = self.mcp_health.entry(server_id.to_string()).or_default();
+        health.reconnect_attempts += 1;
+        health.connecting_since = None;
+        health.outstanding_ping = None;
+        health.missed_pings = 0;
+        let delay = mcp_health::backoff_delay(health.reconnect_attempts);
+        health.next_retry_at = Some(Instant::now() + delay);
+        println!(
+            "Spider: Reconnecting to MCP server {} in {} ms (attempt {})",
+            server_id,
+            delay.as_millis(),
+            health.reconnect_attempts
+        );
+    }
+
+    // Close a server's WebSocket, if any, without treating it as an unexpected drop
+    fn drop_mcp_connection(&mut self, server_id: &str) {
+        let channels: Vec<u32> = self
+            .ws_connections
+            .iter()
+            .filter(|(_, conn)| conn.server_id == server_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for channel_id in channels {
+            self.close_mcp_session(channel_id, "Connection to the MCP server was closed");
+            send_ws_client_push(channel_id, WsMessageType::Close, LazyLoadBlob::default());
+        }
+    }
+
+    // End a session: tool calls waiting on it fail now instead of running into their timeout
+    fn close_mcp_session(&mut self, channel_id: u32, reason: &str) -> Option<WsConnection> {
+        let session = self.ws_connections.remove(&channel_id)?;
+        for (request_id, pending) in &session.pending {
+            if let McpRequestType::ToolCall { .. } = pending.request_type {
+                self.tool_responses.insert(
+                    request_id.clone(),
+                    Err(format!("{} before responding", reason)),
+                );
+            }
+        }
+        Some(session)
+    }
+
+    fn schedule_mcp_health_tick(&self) {
+        send_to_self_after(mcp_health::HEALTH_TICK_MS, mcp_health::tick_body());
+    }
+
+    // One pass of the health loop: ping usable servers, fail stalled handshakes and silent
+    // servers, and retry failed ones whose backoff has elapsed
+    async fn check_mcp_health(&mut self) {
+        let now = Instant::now();
+        let servers: Vec<(String, McpConnectionState)> = self
+            .mcp_servers
+            .iter()
+            .filter(|s| mcp_health::reconnects(&s.transport.transport_type))
+            .map(|s| (s.id.clone(), s.state))
+            .collect();
+
+        for (server_id, state) in servers {
+            let health = self.mcp_health.entry(server_id.clone()).or_default();
+            match state {
+                McpConnectionState::Ready | McpConnectionState::Degraded => {
+                    if health
+                        .last_ping_at
+                        .is_some_and(|at| now.duration_since(at) < mcp_health::PING_INTERVAL)
+                    {
+                        continue;
+                    }
+                    if let Some(ping_id) = health.outstanding_ping.take() {
+                        health.missed_pings += 1;
+                        for session in self.ws_connections.values_mut() {
+                            session.pending.remove(&ping_id);
+                        }
+                    }
+                    let missed = health.missed_pings;
+                    health.last_ping_at = Some(now);
+
+                    if missed >= mcp_health::MAX_MISSED_PINGS {
+                        self.drop_mcp_connection(&server_id);
+                        self.set_mcp_server_state(
+                            &server_id,
+                            McpConnectionState::Failed,
+                            Some(format!("No response to {} health checks", missed)),
+                        );
+                        self.schedule_mcp_reconnect(&server_id);
+                        continue;
+                    }
+                    if missed > 0 {
+                        self.set_mcp_server_state(
+                            &server_id,
+                            McpConnectionState::Degraded,
+                            Some(format!("Missed {} health check(s)", missed)),
+                        );
+                    }
+                    self.send_mcp_ping(&server_id);
+                }
+                McpConnectionState::Connecting => {
+                    if health.connecting_since.is_some_and(|since| {
+                        now.duration_since(since) > mcp_health::CONNECT_TIMEOUT
+                    }) {
+                        self.drop_mcp_connection(&server_id);
+                        self.set_mcp_server_state(
+                            &server_id,
+                            McpConnectionState::Failed,
+                            Some("Timed out waiting for the server to initialize".to_string()),
+                        );
+                        self.schedule_mcp_reconnect(&server_id);
+                    }
+                }
+                McpConnectionState::Failed => {
+                    if !matches!(health.next_retry_at, Some(at) if now < at) {
+                        health.next_retry_at = None;
+                        if let Err(e) = self.open_mcp_connection(&server_id).await {
+                            println!(
+                                "Spider: Reconnect to MCP server {} failed: {}",
+                                server_id, e
+                            );
+                        }
+                    }
+                }
+                McpConnectionState::Disconnected => {}
+            }
+        }
+    }
+
+    fn send_mcp_ping(&mut self, server_id: &str) {
+        let Some(channel_id) = self
+            .ws_connections
+            .iter()
+            .find(|(_, conn)| conn.server_id == server_id && conn.initialized)
+            .map(|(id, _)| *id)
+        else {
+            return;
+        };
+
+        let request_id = format!("ping_{}_{}", channel_id, Uuid::new_v4());
+        let ping = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "ping".to_string(),
+            params: None,
+            id: request_id.clone(),
+        };
+        if let Some(session) = self.ws_connections.get_mut(&channel_id) {
+            session.pending.insert(
+                request_id.clone(),
+                PendingMcpRequest {
+                    request_id: request_id.clone(),
+                    conversation_id: None,
+                    server_id: server_id.to_string(),
+                    request_type: McpRequestType::Ping,
+                },
+            );
+        }
+        self.mcp_health
+            .entry(server_id.to_string())
+            .or_default()
+            .outstanding_ping = Some(request_id);
+
+        let blob = LazyLoadBlob::new(
+            Some("application/json"),
+            serde_json::to_string(&ping).unwrap().into_bytes(),
+        );
+        send_ws_client_push(channel_id, WsMessageType::Text, blob);
+    }
+
+    async fn connect_transport(&mut self, server_id: &str) -> Result<String, String> {
         // Find the server and get its transport config
         let (server_name, transport) = {
             let server = self
                 .mcp_servers
                 .iter()
-                .find(|s| s.id == request.server_id)
-                .ok_or_else(|| format!("MCP server {} not found", request.server_id))?;
+                .find(|s| s.id == server_id)
+                .ok_or_else(|| format!("MCP server {} not found", server_id))?;
             (server.name.clone(), server.transport.clone())
         };
 
@@ -841,6 +1344,9 @@ impl SpiderState {
                 .clone()
                 .unwrap_or_else(|| "ws://localhost:10125".to_string());
 
+            // Reconnects replace any connection still open for this server
+            self.drop_mcp_connection(server_id);
+
             // Allocate a channel ID for this connection
             let channel_id = self.next_channel_id;
             self.next_channel_id += 1;
@@ -854,11 +1360,12 @@ impl SpiderState {
             self.ws_connections.insert(
                 channel_id,
                 WsConnection {
-                    server_id: request.server_id.clone(),
+                    server_id: server_id.to_string(),
                     server_name: server_name.clone(),
                     channel_id,
                     tools: Vec::new(),
                     initialized: false,
+                    pending: HashMap::new(),
                 },
             );
 
@@ -881,15 +1388,17 @@ impl SpiderState {
             };
 
             // Store pending request
-            self.pending_mcp_requests.insert(
-                format!("init_{}", channel_id),
-                PendingMcpRequest {
-                    request_id: format!("init_{}", channel_id),
-                    conversation_id: None,
-                    server_id: request.server_id.clone(),
-                    request_type: McpRequestType::Initialize,
-                },
-            );
+            if let Some(session) = self.ws_connections.get_mut(&channel_id) {
+                session.pending.insert(
+                    format!("init_{}", channel_id),
+                    PendingMcpRequest {
+                        request_id: format!("init_{}", channel_id),
+                        conversation_id: None,
+                        server_id: server_id.to_string(),
+                        request_type: McpRequestType::Initialize,
+                    },
+                );
+            }
 
             // Send the initialize message
             let blob = LazyLoadBlob::new(
@@ -898,14 +1407,12 @@ impl SpiderState {
             );
             send_ws_client_push(channel_id, WsMessageType::Text, blob);
 
-            // Mark server as connecting (will be marked connected when initialized)
-            if let Some(server) = self
-                .mcp_servers
-                .iter_mut()
-                .find(|s| s.id == request.server_id)
-            {
-                server.connected = false; // Will be set to true when initialization completes
-            }
+            // Ready once initialize and tools/list complete; the health loop times out the handshake
+            self.set_mcp_server_state(server_id, McpConnectionState::Connecting, None);
+            self.mcp_health
+                .entry(server_id.to_string())
+                .or_default()
+                .connecting_since = Some(Instant::now());
 
             Ok(format!(
                 "Connecting to MCP server {} via WebSocket...",
@@ -937,7 +1444,7 @@ impl SpiderState {
 
             // Create the hypergrid connection
             let hypergrid_conn = HypergridConnection {
-                server_id: request.server_id.clone(),
+                server_id: server_id.to_string(),
                 url: url.clone(),
                 token: token.clone(),
                 client_id: client_id.clone(),
@@ -952,7 +1459,7 @@ impl SpiderState {
 
             // Store the connection
             self.hypergrid_connections
-                .insert(request.server_id.clone(), hypergrid_conn);
+                .insert(server_id.to_string(), hypergrid_conn);
 
             // Define the hypergrid tools
             let hypergrid_tools = vec![
@@ -961,49 +1468,61 @@ impl SpiderState {
                     description: "Configure the hypergrid connection credentials".to_string(),
                     parameters: r#"{"type":"object","properties":{"url":{"type":"string"},"token":{"type":"string"},"client_id":{"type":"string"},"node":{"type":"string"}},"required":["url","token","client_id","node"]}"#.to_string(),
                     input_schema_json: Some(r#"{"type":"object","properties":{"url":{"type":"string","description":"The base URL for the Hypergrid API"},"token":{"type":"string","description":"The authentication token"},"client_id":{"type":"string","description":"The unique client ID"},"node":{"type":"string","description":"The Hyperware node name"}},"required":["url","token","client_id","node"]}"#.to_string()),
+                    output_schema_json: None,
                 },
                 Tool {
                     name: "search-registry".to_string(),
                     description: "Search through hypergrid provider registry".to_string(),
                     parameters: r#"{"type":"object","properties":{"query":{"type":"string"}},"required":["query"]}"#.to_string(),
                     input_schema_json: Some(r#"{"type":"object","properties":{"query":{"type":"string","description":"Search query for providers"}},"required":["query"]}"#.to_string()),
+                    output_schema_json: None,
                 },
                 Tool {
                     name: "call-provider".to_string(),
                     description: "Call a hypergrid provider with arguments".to_string(),
                     parameters: r#"{"type":"object","properties":{"providerId":{"type":"string"},"providerName":{"type":"string"},"callArgs":{"type":"array","items":{"type":"array","items":{"type":"string"}}}},"required":["providerId","providerName","callArgs"]}"#.to_string(),
                     input_schema_json: Some(r#"{"type":"object","properties":{"providerId":{"type":"string","description":"The provider ID"},"providerName":{"type":"string","description":"The provider name"},"callArgs":{"type":"array","items":{"type":"array","items":{"type":"string"}},"description":"Arguments as array of [key, value] pairs"}},"required":["providerId","providerName","callArgs"]}"#.to_string()),
+                    output_schema_json: None,
                 },
             ];
 
             // Update the server with hypergrid tools and mark as connected
-            if let Some(server) = self
-                .mcp_servers
-                .iter_mut()
-                .find(|s| s.id == request.server_id)
-            {
+            if let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == server_id) {
                 server.tools = hypergrid_tools;
-                server.connected = true;
             }
+            self.set_mcp_server_state(server_id, McpConnectionState::Ready, None);
 
             Ok(format!(
                 "Connected to Hypergrid MCP server {} (Node: {}, Client ID: {})",
                 server_name, node, conn_client_id
             ))
+        } else if transport.transport_type == "spider-remote" {
+            let node = transport
+                .remote_node
+                .clone()
+                .ok_or_else(|| "Spider-remote requires a remote node".to_string())?;
+            let tools = federation::list_remote_tools(&node).await?;
+            let tool_count = tools.len();
+
+            if let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == server_id) {
+                server.tools = tools;
+            }
+            self.set_mcp_server_state(server_id, McpConnectionState::Ready, None);
+
+            Ok(format!(
+                "Connected to Spider on {} with {} tools",
+                node, tool_count
+            ))
         } else {
             // For other transport types, use the old method for now
             let tools = discover_mcp_tools(&transport).await?;
             let tool_count = tools.len();
 
             // Update the server with discovered tools
-            if let Some(server) = self
-                .mcp_servers
-                .iter_mut()
-                .find(|s| s.id == request.server_id)
-            {
+            if let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == server_id) {
                 server.tools = tools;
-                server.connected = true;
             }
+            self.set_mcp_server_state(server_id, McpConnectionState::Ready, None);
 
             Ok(format!(
                 "Connected to MCP server {} with {} tools",
@@ -1016,27 +1535,23 @@ impl SpiderState {
     async fn list_conversations(
         &self,
         request: ListConversationsRequest,
-    ) -> Result<Vec<Conversation>, String> {
-        // Validate read permission
-        if !self.validate_permission(&request.auth_key, "read") {
-            return Err("Unauthorized: API key lacks read permission".to_string());
+    ) -> Result<Vec<ConversationInfo>, String> {
+        // Validate conversations:read permission
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
         }
 
-        let conversations: Vec<Conversation> = self
-            .active_conversations
+        Ok(self
+            .query_conversations(
+                request.limit,
+                request.offset,
+                request.client.as_deref(),
+                request.tag.as_deref(),
+                request.include_deleted.unwrap_or(false),
+            )
             .iter()
-            .filter(|(_, conv)| {
-                request
-                    .client
-                    .as_ref()
-                    .map_or(true, |c| &conv.metadata.client == c)
-            })
-            .map(|(_, conv)| conv.clone())
-            .skip(request.offset.unwrap_or(0) as usize)
-            .take(request.limit.unwrap_or(50) as usize)
-            .collect();
-
-        Ok(conversations)
+            .map(conversations::info)
+            .collect())
     }
 
     #[http]
@@ -1044,320 +1559,2570 @@ impl SpiderState {
         &self,
         request: GetConversationRequest,
     ) -> Result<Conversation, String> {
-        // Validate read permission
-        if !self.validate_permission(&request.auth_key, "read") {
-            return Err("Unauthorized: API key lacks read permission".to_string());
-        }
-
-        // First check in-memory conversations
-        for (id, conv) in &self.active_conversations {
-            if id == &request.conversation_id {
-                return Ok(conv.clone());
-            }
+        // Validate conversations:read permission
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
         }
 
-        // Try to load from VFS
-        load_conversation_from_vfs(&request.conversation_id).await
+        self.find_conversation(&request.conversation_id).await
     }
 
     #[http]
-    async fn get_config(&self, request: GetConfigRequest) -> Result<ConfigResponse, String> {
-        // Validate read permission
-        if !self.validate_permission(&request.auth_key, "read") {
-            return Err("Unauthorized: API key lacks read permission".to_string());
+    async fn search_conversations(
+        &self,
+        request: SearchConversationsRequest,
+    ) -> Result<Vec<ConversationSearchHit>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
         }
 
-        Ok(ConfigResponse {
-            default_llm_provider: self.default_llm_provider.clone(),
-            max_tokens: self.max_tokens,
-            temperature: self.temperature,
-        })
+        let include_deleted = request.include_deleted.unwrap_or(false);
+        Ok(self
+            .active_conversations
+            .iter()
+            .filter(|(_, conv)| {
+                conversations::is_visible(conv, None, request.tag.as_deref(), include_deleted)
+            })
+            .flat_map(|(_, conv)| conversations::search(conv, &request.query))
+            .take(request.limit.unwrap_or(50) as usize)
+            .collect())
     }
 
     #[http]
-    async fn update_config(&mut self, request: UpdateConfigRequest) -> Result<String, String> {
-        // Validate write permission
-        if !self.validate_permission(&request.auth_key, "write") {
-            return Err("Unauthorized: API key lacks write permission".to_string());
-        }
-
-        if let Some(provider) = request.default_llm_provider {
-            self.default_llm_provider = provider;
-        }
-
-        if let Some(tokens) = request.max_tokens {
-            self.max_tokens = tokens;
-        }
-
-        if let Some(temp) = request.temperature {
-            self.temperature = temp;
-        }
-
-        Ok("Configuration updated".to_string())
+    async fn update_conversation(
+        &mut self,
+        request: UpdateConversationRequest,
+    ) -> Result<Conversation, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.conversation_id.clone();
+        let result = self.update_conversation_impl(request).await;
+        self.audited(&actor, "conversation.update", &target, result)
     }
 
-    #[http(method = "GET", path = "/api-ssd")]
-    async fn get_admin_key(&self) -> Result<String, String> {
-        // Return the admin key for the GUI - specifically look for the GUI admin key
-        self.spider_api_keys
-            .iter()
-            .find(|k| k.name == "Admin GUI Key" && k.permissions.contains(&"admin".to_string()))
-            .map(|k| k.key.clone())
-            .ok_or_else(|| "No admin GUI key found".to_string())
+    #[http]
+    async fn delete_conversation(
+        &mut self,
+        request: DeleteConversationRequest,
+    ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.conversation_id.clone();
+        let result = self.delete_conversation_impl(request).await;
+        self.audited(&actor, "conversation.delete", &target, result)
     }
 
     #[http]
-    async fn get_trial_notification(&self) -> Result<TrialNotification, String> {
-        // Return trial notification data
-        Ok(TrialNotification {
-            show: self.show_trial_key_notification,
-            title: "Trial API Key Active".to_string(),
-            message: "Spider is using a limited trial API key from the Anthropic API Key Manager. This key has usage limitations and may stop working unexpectedly. Please add your own API key in Settings for uninterrupted service.".to_string(),
-            allow_dismiss: true,
-            allow_do_not_show_again: true,
-        })
+    async fn export_transcripts(
+        &mut self,
+        request: ExportTranscriptsRequest,
+    ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request
+            .conversation_ids
+            .as_ref()
+            .map_or_else(|| "all".to_string(), |ids| ids.join(","));
+        let result = self.export_transcripts_impl(request).await;
+        self.audited(&actor, "conversation.export", &target, result)
     }
 
+    // Store transcripts produced by Spider or another tool as new conversations, ready to be
+    // read or continued
     #[http]
-    async fn dismiss_trial_notification(&mut self, permanent: bool) -> Result<String, String> {
-        // Clear the trial notification flag
-        self.show_trial_key_notification = false;
-
-        // If permanent dismissal requested, we could store a flag in state
-        // For now, just clear the current flag
-        if permanent {
-            // Could add a permanent_dismiss_trial_notification field to state
-            Ok("Trial notification permanently dismissed".to_string())
-        } else {
-            Ok("Trial notification dismissed".to_string())
-        }
+    async fn import_transcripts(
+        &mut self,
+        request: ImportTranscriptsRequest,
+    ) -> Result<Vec<ConversationInfo>, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.format.clone();
+        let result = self.import_transcripts_impl(request).await;
+        self.audited(&actor, "conversation.import", &target, result)
     }
 
-    #[local]
     #[http]
-    async fn chat(&mut self, request: ChatRequest) -> Result<ChatResponse, String> {
-        // Use the shared internal chat processing logic (without WebSocket streaming)
-        self.process_chat_internal(request, None).await
+    async fn fork_conversation(
+        &mut self,
+        request: ForkConversationRequest,
+    ) -> Result<Conversation, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.conversation_id.clone();
+        let result = self.fork_conversation_impl(request).await;
+        self.audited(&actor, "conversation.fork", &target, result)
     }
 
-    #[local]
-    async fn ping(&self) -> String {
+    #[http]
+    async fn select_branch(
+        &mut self,
+        request: SelectBranchRequest,
+    ) -> Result<Conversation, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.conversation_id.clone();
+        let result = self.select_branch_impl(request).await;
+        self.audited(&actor, "conversation.select_branch", &target, result)
+    }
+
+    // Answer the last user message of the active branch again; the previous answer stays
+    // reachable as a sibling branch
+    #[http]
+    async fn regenerate_response(
+        &mut self,
+        request: RegenerateResponseRequest,
+    ) -> Result<ChatResponse, String> {
+        let actor = self.audit_actor(&request.api_key);
+        let target = request.conversation_id.clone();
+        let result = self.regenerate_response_impl(request).await;
+        self.audited(&actor, "conversation.regenerate", &target, result)
+    }
+
+    #[http]
+    async fn restore_conversation(
+        &mut self,
+        request: RestoreConversationRequest,
+    ) -> Result<Conversation, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.conversation_id.clone();
+        let result = self.restore_conversation_impl(request).await;
+        self.audited(&actor, "conversation.restore", &target, result)
+    }
+
+    #[http]
+    async fn export_conversations(
+        &mut self,
+        request: ExportConversationsRequest,
+    ) -> Result<Vec<Conversation>, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request
+            .conversation_ids
+            .as_ref()
+            .map_or_else(|| "all".to_string(), |ids| ids.join(","));
+        let result = self.export_conversations_impl(request).await;
+        self.audited(&actor, "conversation.export", &target, result)
+    }
+
+    #[http]
+    async fn list_agent_profiles(
+        &self,
+        request: ListAgentProfilesRequest,
+    ) -> Result<Vec<AgentProfile>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        Ok(self.agent_profiles.clone())
+    }
+
+    #[http]
+    async fn save_agent_profile(
+        &mut self,
+        request: SaveAgentProfileRequest,
+    ) -> Result<AgentProfile, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.id.clone().unwrap_or_else(|| request.name.clone());
+        let result = self.save_agent_profile_impl(request).await;
+        self.audited(&actor, "profile.save", &target, result)
+    }
+
+    #[http]
+    async fn delete_agent_profile(
+        &mut self,
+        request: DeleteAgentProfileRequest,
+    ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.profile_id.clone();
+        let result = self.delete_agent_profile_impl(request).await;
+        self.audited(&actor, "profile.delete", &target, result)
+    }
+
+    #[http]
+    async fn list_scheduled_tasks(
+        &self,
+        request: ListScheduledTasksRequest,
+    ) -> Result<Vec<ScheduledTask>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        Ok(self.scheduled_tasks.clone())
+    }
+
+    #[http]
+    async fn save_scheduled_task(
+        &mut self,
+        request: SaveScheduledTaskRequest,
+    ) -> Result<ScheduledTask, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request
+            .task_id
+            .clone()
+            .unwrap_or_else(|| request.name.clone());
+        let result = self.save_scheduled_task_impl(request, &actor).await;
+        self.audited(&actor, "task.save", &target, result)
+    }
+
+    #[http]
+    async fn delete_scheduled_task(
+        &mut self,
+        request: DeleteScheduledTaskRequest,
+    ) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.task_id.clone();
+        let result = self.delete_scheduled_task_impl(request).await;
+        self.audited(&actor, "task.delete", &target, result)
+    }
+
+    #[http]
+    async fn list_triggers(&self, request: ListTriggersRequest) -> Result<Vec<Trigger>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        Ok(self
+            .triggers
+            .iter()
+            .map(|t| Trigger {
+                secret: triggers::mask_secret(&t.secret),
+                ..t.clone()
+            })
+            .collect())
+    }
+
+    // Returns the trigger with its full secret, which is only shown here
+    #[http]
+    async fn save_trigger(&mut self, request: SaveTriggerRequest) -> Result<Trigger, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request
+            .trigger_id
+            .clone()
+            .unwrap_or_else(|| request.name.clone());
+        let result = self.save_trigger_impl(request, &actor).await;
+        self.audited(&actor, "trigger.save", &target, result)
+    }
+
+    #[http]
+    async fn delete_trigger(&mut self, request: DeleteTriggerRequest) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = request.trigger_id.clone();
+        let result = self.delete_trigger_impl(request).await;
+        self.audited(&actor, "trigger.delete", &target, result)
+    }
+
+    // Webhook entry point: any JSON body, authorized by `Authorization: Bearer <trigger secret>`.
+    // The secret also identifies the trigger, so all triggers share this path; HTTP bindings are
+    // fixed when the process starts. Answers with a TriggerResult, or an `error` object with a
+    // 4xx/5xx status when the trigger could not run.
+    #[http(method = "POST", path = "/api/trigger")]
+    async fn fire_trigger(&mut self) -> String {
+        add_response_header("Content-Type".to_string(), "application/json".to_string());
+
+        let secret = get_request_header("authorization")
+            .as_deref()
+            .and_then(mcp_server::bearer_token)
+            .unwrap_or_default()
+            .to_string();
+        let body = get_blob().map(|b| b.bytes).unwrap_or_default();
+
+        match self.handle_trigger(&secret, &body).await {
+            Ok(result) => serde_json::to_string(&result).unwrap(),
+            Err((status, e)) => {
+                set_response_status(status);
+                serde_json::json!({ "error": e }).to_string()
+            }
+        }
+    }
+
+    #[http]
+    async fn get_config(&self, request: GetConfigRequest) -> Result<ConfigResponse, String> {
+        // Validate chat permission
+        if !self.validate_permission(&request.auth_key, Permission::Chat) {
+            return Err("Unauthorized: API key lacks chat permission".to_string());
+        }
+
+        // Fallback chains and federation peers describe the deployment rather than chat
+        // settings, so only keys that may change them see them
+        let config_writer = self.validate_permission(&request.auth_key, Permission::ConfigWrite);
+
+        Ok(ConfigResponse {
+            default_llm_provider: self.default_llm_provider.clone(),
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            context: self.context_config.clone(),
+            fallback_chains: if config_writer {
+                self.fallback_chains.clone()
+            } else {
+                Vec::new()
+            },
+            key_selection: self.key_selection.clone(),
+            federation_peers: if config_writer {
+                self.federation_peers.clone()
+            } else {
+                Vec::new()
+            },
+            conversation_retention_days: self.conversation_retention_days,
+            summaries: self.summary_config.clone(),
+            builtin_tools: self.builtin_tools_config.clone(),
+        })
+    }
+
+    #[http]
+    async fn update_config(&mut self, request: UpdateConfigRequest) -> Result<String, String> {
+        let actor = self.audit_actor(&request.auth_key);
+        let target = "config".to_string();
+        let result = self.update_config_impl(request).await;
+        self.audited(&actor, "config.update", &target, result)
+    }
+
+    #[http(method = "GET", path = "/api-ssd")]
+    async fn get_admin_key(&self) -> Result<String, String> {
+        // Return the admin key for the GUI - specifically look for the GUI admin key
+        self.spider_api_keys
+            .iter()
+            .find(|k| k.name == "Admin GUI Key" && grants(&k.permissions, Permission::Admin))
+            .map(|k| k.key.clone())
+            .ok_or_else(|| "No admin GUI key found".to_string())
+    }
+
+    #[http]
+    async fn get_trial_notification(&self) -> Result<TrialNotification, String> {
+        // Return trial notification data
+        Ok(TrialNotification {
+            show: self.show_trial_key_notification,
+            title: "Trial API Key Active".to_string(),
+            message: "Spider is using a limited trial API key from the Anthropic API Key Manager. This key has usage limitations and may stop working unexpectedly. Please add your own API key in Settings for uninterrupted service.".to_string(),
+            allow_dismiss: true,
+            allow_do_not_show_again: true,
+        })
+    }
+
+    #[http]
+    async fn dismiss_trial_notification(&mut self, permanent: bool) -> Result<String, String> {
+        // Clear the trial notification flag
+        self.show_trial_key_notification = false;
+
+        // If permanent dismissal requested, we could store a flag in state
+        // For now, just clear the current flag
+        if permanent {
+            // Could add a permanent_dismiss_trial_notification field to state
+            Ok("Trial notification permanently dismissed".to_string())
+        } else {
+            Ok("Trial notification dismissed".to_string())
+        }
+    }
+
+    #[local]
+    #[http]
+    async fn chat(&mut self, request: ChatRequest) -> Result<ChatResponse, String> {
+        // Use the shared internal chat processing logic (without WebSocket streaming)
+        self.process_chat_internal(request, None).await
+    }
+
+    // Start a chat in the background and return its job id; poll get_job_status or subscribe
+    // over the WebSocket for progress
+    #[http]
+    async fn start_chat_job(&mut self, request: ChatRequest) -> Result<String, String> {
+        if !self.validate_spider_key(&request.api_key) {
+            return Err(self.invalid_key_error(&request.api_key));
+        }
+        if !self.validate_permission(&request.api_key, Permission::Chat) {
+            return Err("Forbidden: API key lacks chat permission".to_string());
+        }
+        self.queue_chat_job(request, None)
+    }
+
+    #[http]
+    async fn get_job_status(&self, request: GetJobStatusRequest) -> Result<ChatJob, String> {
+        let job = self.authorize_job(&request.auth_key, &request.job_id)?;
+        Ok(ChatJob {
+            events: jobs::events_after(job, request.after_seq),
+            ..job.clone()
+        })
+    }
+
+    #[local]
+    async fn ping(&self) -> String {
         "Pong".to_string()
     }
 
-    #[local]
-    async fn process_request(
-        &mut self,
-        request: ProcessRequest,
-    ) -> Result<ProcessResponse, String> {
-        match request.action.as_str() {
-            "chat" => {
-                let chat_request: ChatRequest = serde_json::from_str(&request.payload)
-                    .map_err(|e| format!("Invalid chat request: {}", e))?;
-                let result = self.chat(chat_request).await?;
-                let serialized = serde_json::to_string(&result)
-                    .map_err(|e| format!("Failed to serialize chat response: {}", e))?;
-                Ok(ProcessResponse {
-                    success: true,
-                    data: serialized,
-                })
-            }
-            _ => Ok(ProcessResponse {
-                success: false,
-                data: format!("Unknown action: {}", request.action),
-            }),
+    // Typed RPC for other processes on this node, authorized by the grants held by the caller
+    #[local]
+    async fn process_request(
+        &mut self,
+        request: ProcessRequest,
+    ) -> Result<ProcessResponse, String> {
+        match request {
+            ProcessRequest::Chat(chat) => {
+                let actor = self.authorize_process(Permission::Chat)?;
+                let scope = RunScope {
+                    read_conversations: self
+                        .authorize_process(Permission::ConversationsRead)
+                        .is_ok(),
+                    ..RunScope::default()
+                };
+                let chat_request = ChatRequest {
+                    api_key: String::new(),
+                    messages: chat.messages,
+                    llm_provider: chat.llm_provider,
+                    model: chat.model,
+                    mcp_servers: chat.mcp_servers,
+                    metadata: chat.metadata,
+                    conversation_id: None,
+                    profile_id: None,
+                };
+                let response = self
+                    .run_chat(chat_request, None, &actor, None, Some(scope))
+                    .await?;
+                Ok(ProcessResponse::Chat(response))
+            }
+            ProcessRequest::ListConversations(list) => {
+                self.authorize_process(Permission::ConversationsRead)?;
+                Ok(ProcessResponse::Conversations(self.query_conversations(
+                    list.limit,
+                    list.offset,
+                    list.client.as_deref(),
+                    None,
+                    false,
+                )))
+            }
+            ProcessRequest::GetConversation(conversation_id) => {
+                self.authorize_process(Permission::ConversationsRead)?;
+                let conversation = self.find_conversation(&conversation_id).await?;
+                Ok(ProcessResponse::Conversation(Box::new(conversation)))
+            }
+            ProcessRequest::ListMcpTools => {
+                self.authorize_process(Permission::ToolsCall)?;
+                Ok(ProcessResponse::McpTools(
+                    mcp_server::exported_tools(&self.mcp_servers, None)
+                        .into_iter()
+                        .map(|exported| Tool {
+                            name: exported.name,
+                            ..exported.tool
+                        })
+                        .collect(),
+                ))
+            }
+            ProcessRequest::CallTool(ProcessCallToolRequest {
+                tool_name,
+                arguments_json,
+            }) => {
+                let actor = self.authorize_process(Permission::ToolsCall)?;
+                let exported = mcp_server::exported_tools(&self.mcp_servers, None)
+                    .into_iter()
+                    .find(|t| t.name == tool_name)
+                    .ok_or_else(|| format!("Unknown tool: {}", tool_name))?;
+                let arguments: Value = serde_json::from_str(&arguments_json)
+                    .map_err(|e| format!("Invalid tool arguments: {}", e))?;
+
+                let result = self
+                    .execute_mcp_tool(
+                        &actor,
+                        &exported.server_id,
+                        &exported.tool.name,
+                        &arguments,
+                        None,
+                    )
+                    .await;
+                Ok(ProcessResponse::ToolResult(
+                    mcp_server::call_tool_result(result).to_string(),
+                ))
+            }
+        }
+    }
+
+    // Run a single MCP tool without involving a model, e.g. to debug a server or for automations
+    #[local]
+    #[http]
+    async fn invoke_tool(
+        &mut self,
+        request: InvokeToolRequest,
+    ) -> Result<InvokeToolResponse, String> {
+        let actor = if self.validate_permission(&request.auth_key, Permission::ToolsCall) {
+            self.audit_actor(&request.auth_key)
+        } else {
+            self.authorize_process(Permission::ToolsCall)
+                .map_err(|_| "Unauthorized: API key lacks tools:call permission".to_string())?
+        };
+
+        let tool = self
+            .mcp_servers
+            .iter()
+            .find(|s| s.id == request.server_id)
+            .ok_or_else(|| format!("MCP server {} not found", request.server_id))?
+            .tools
+            .iter()
+            .find(|t| t.name == request.tool_name)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Tool {} not found on server {}",
+                    request.tool_name, request.server_id
+                )
+            })?;
+
+        let arguments: Value = if request.arguments_json.trim().is_empty() {
+            Value::Object(serde_json::Map::new())
+        } else {
+            serde_json::from_str(&request.arguments_json)
+                .map_err(|e| format!("Invalid tool arguments: {}", e))?
+        };
+        validation::validate_tool_arguments(&tool, &arguments)?;
+
+        let result = self
+            .execute_mcp_tool(&actor, &request.server_id, &tool.name, &arguments, None)
+            .await;
+        let result = mcp_server::call_tool_result(result);
+
+        Ok(InvokeToolResponse {
+            is_error: result
+                .get("isError")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false),
+            structured_content_json: result.get("structuredContent").map(|c| c.to_string()),
+            result_json: result.to_string(),
+        })
+    }
+
+    // OAuth endpoints - proxy requests to Anthropic to avoid CORS.
+    // Tokens stay server-side so Spider can refresh them; clients only see token health.
+    #[http]
+    async fn exchange_oauth_token(
+        &mut self,
+        req: OAuthExchangeRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        let actor = self.audit_actor(&req.auth_key);
+        let target = "claude-login".to_string();
+        let result = self.exchange_oauth_token_impl(req).await;
+        self.audited(&actor, "oauth.exchange", &target, result)
+    }
+
+    #[http]
+    async fn refresh_oauth_token(
+        &mut self,
+        req: OAuthRefreshRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        let actor = self.audit_actor(&req.auth_key);
+        let target = "claude-login".to_string();
+        let result = self.refresh_oauth_token_impl(req).await;
+        self.audited(&actor, "oauth.refresh", &target, result)
+    }
+
+    // Delegate a Claude login to the calling Spider key: its chats use that login exclusively
+    #[http]
+    async fn link_oauth_login(
+        &mut self,
+        req: LinkOAuthLoginRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        let actor = self.audit_actor(&req.auth_key);
+        let target = self.spider_key_name(&req.auth_key);
+        let result = self.link_oauth_login_impl(req).await;
+        self.audited(&actor, "oauth.link", &target, result)
+    }
+
+    #[http]
+    async fn unlink_oauth_login(&mut self, req: UnlinkOAuthLoginRequest) -> Result<String, String> {
+        let actor = self.audit_actor(&req.auth_key);
+        let target = self.spider_key_name(&req.auth_key);
+        let result = self.unlink_oauth_login_impl(req).await;
+        self.audited(&actor, "oauth.unlink", &target, result)
+    }
+
+    // Migration path for clients that used OAuth tokens directly as Spider keys: the token is
+    // verified with Anthropic once, then delegated to a newly issued Spider key. Issuing a key
+    // takes an admin key, as with create_spider_key; a valid OAuth token alone is not enough.
+    #[http]
+    async fn migrate_oauth_token(
+        &mut self,
+        req: MigrateOAuthTokenRequest,
+    ) -> Result<SpiderApiKey, String> {
+        let actor = self.audit_actor(&req.admin_key);
+        let target = req
+            .name
+            .clone()
+            .unwrap_or_else(|| "Migrated Claude login".to_string());
+        let result = self.migrate_oauth_token_impl(req).await;
+        self.audited(&actor, "oauth.migrate", &target, result)
+    }
+
+    #[http]
+    async fn get_oauth_token_status(
+        &self,
+        req: OAuthStatusRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        // Validate keys:manage permission
+        if !self.validate_permission(&req.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
+        }
+
+        Ok(self.oauth_token_status())
+    }
+
+    // Spider as an MCP server (Streamable HTTP): re-exports every connected server's tools.
+    // Clients authenticate with `Authorization: Bearer <Spider API key>`.
+    #[http(method = "POST", path = "/mcp")]
+    async fn serve_mcp(&mut self) -> String {
+        add_response_header("Content-Type".to_string(), "application/json".to_string());
+
+        let api_key = get_request_header("authorization")
+            .as_deref()
+            .and_then(mcp_server::bearer_token)
+            .unwrap_or_default()
+            .to_string();
+        let body = get_blob().map(|b| b.bytes).unwrap_or_default();
+
+        let message = match serde_json::from_slice::<Value>(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                return mcp_server::error(
+                    Value::Null,
+                    mcp_server::PARSE_ERROR,
+                    &format!("Parse error: {}", e),
+                )
+                .to_string()
+            }
+        };
+
+        // Notifications have no response body
+        self.handle_mcp_rpc_batch(&api_key, message)
+            .await
+            .map(|response| response.to_string())
+            .unwrap_or_default()
+    }
+
+    // Spider keeps no MCP sessions and never sends server-initiated messages, so it offers no
+    // GET stream; Streamable HTTP servers without one answer GET with 405
+    #[http(method = "GET", path = "/mcp")]
+    async fn serve_mcp_stream(&mut self) -> String {
+        set_response_status(http::StatusCode::METHOD_NOT_ALLOWED);
+        add_response_header("Allow".to_string(), "POST".to_string());
+        String::new()
+    }
+
+    // Federation: Spiders on allow-listed nodes can use our tools and LLM providers.
+    // Tools we reach through other Spiders are not re-exported, so peers can't form loops.
+    #[remote]
+    async fn remote_list_tools(&self) -> Result<Vec<Tool>, String> {
+        self.authorize_peer(|p| p.allow_tools)?;
+
+        Ok(mcp_server::exported_tools(&self.federated_servers(), None)
+            .into_iter()
+            .map(|exported| Tool {
+                name: exported.name,
+                ..exported.tool
+            })
+            .collect())
+    }
+
+    #[remote]
+    async fn remote_call_tool(&mut self, request: RemoteCallToolRequest) -> Result<String, String> {
+        let node = self.authorize_peer(|p| p.allow_tools)?;
+
+        let exported = mcp_server::exported_tools(&self.federated_servers(), None)
+            .into_iter()
+            .find(|t| t.name == request.tool_name)
+            .ok_or_else(|| format!("Unknown tool: {}", request.tool_name))?;
+        let arguments: Value = serde_json::from_str(&request.arguments_json)
+            .map_err(|e| format!("Invalid tool arguments: {}", e))?;
+
+        let result = self
+            .execute_mcp_tool(
+                &format!("node:{}", node),
+                &exported.server_id,
+                &exported.tool.name,
+                &arguments,
+                None,
+            )
+            .await;
+
+        Ok(mcp_server::call_tool_result(result).to_string())
+    }
+
+    #[remote]
+    async fn remote_chat(&mut self, request: RemoteChatRequest) -> Result<ChatResponse, String> {
+        let node = self.authorize_peer(|p| p.allow_chat)?;
+        let allow_tools = self.authorize_peer(|p| p.allow_tools).is_ok();
+
+        // Peers without tool access chat without tools. Others get the servers we federate,
+        // never those we reach through other Spiders, so peers can't form loops.
+        let mcp_servers = if allow_tools {
+            let federated: Vec<String> =
+                self.federated_servers().into_iter().map(|s| s.id).collect();
+            match request.mcp_servers {
+                Some(requested) => requested
+                    .into_iter()
+                    .filter(|id| federated.contains(id))
+                    .collect(),
+                None => federated,
+            }
+        } else {
+            Vec::new()
+        };
+
+        let chat_request = ChatRequest {
+            api_key: String::new(),
+            messages: request.messages,
+            llm_provider: request.llm_provider,
+            model: request.model,
+            mcp_servers: Some(mcp_servers),
+            metadata: Some(ConversationMetadata {
+                start_time: Utc::now().to_rfc3339(),
+                client: format!("spider@{}", node),
+                from_stt: false,
+            }),
+            conversation_id: None,
+            profile_id: None,
+        };
+
+        self.run_chat(chat_request, None, &format!("node:{}", node), None, None)
+            .await
+    }
+}
+
+impl SpiderState {
+    fn validate_spider_key(&self, key: &str) -> bool {
+        // Only keys Spider issued grant access; OAuth logins must be linked to one first
+        self.spider_api_keys.iter().any(|k| k.key == key)
+    }
+
+    fn validate_admin_key(&self, key: &str) -> bool {
+        self.validate_permission(key, Permission::Admin)
+    }
+
+    fn validate_permission(&self, key: &str, permission: Permission) -> bool {
+        self.spider_api_keys
+            .iter()
+            .any(|k| k.key == key && grants(&k.permissions, permission))
+    }
+
+    // Audit actor for a request: the Spider key's name, falling back to whoever sent it
+    fn audit_actor(&self, key: &str) -> String {
+        self.spider_api_keys
+            .iter()
+            .find(|k| k.key == key)
+            .map(|k| k.name.clone())
+            .unwrap_or_else(|| source().to_string())
+    }
+
+    // Spider keys are secrets, so the audit log refers to them by name
+    fn spider_key_name(&self, key: &str) -> String {
+        self.spider_api_keys
+            .iter()
+            .find(|k| k.key == key)
+            .map(|k| k.name.clone())
+            .unwrap_or_else(|| "unknown key".to_string())
+    }
+
+    fn record_audit<T>(
+        &mut self,
+        actor: &str,
+        action: &str,
+        target: &str,
+        result: &Result<T, String>,
+    ) {
+        let (outcome, detail) = audit::outcome_of(result);
+        let event = AuditEvent {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().timestamp() as u64,
+            actor: actor.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            outcome: outcome.to_string(),
+            detail,
+        };
+        // Auditing must never block the action itself
+        if let Err(e) = audit::append_event(&mut self.audit_log, &event) {
+            println!("Spider: Failed to record audit event {}: {}", action, e);
+        }
+    }
+
+    fn audited<T>(
+        &mut self,
+        actor: &str,
+        action: &str,
+        target: &str,
+        result: Result<T, String>,
+    ) -> Result<T, String> {
+        self.record_audit(actor, action, target, &result);
+        result
+    }
+
+    // Bodies of the audited handlers; each handler records its outcome with `audited`
+    async fn set_api_key_impl(&mut self, request: SetApiKeyRequest) -> Result<String, String> {
+        // Validate keys:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
+        }
+
+        if !KNOWN_PROVIDERS.contains(&request.provider.as_str()) {
+            return Err(format!(
+                "Unknown LLM provider: {} (expected one of: {})",
+                request.provider,
+                KNOWN_PROVIDERS.join(", ")
+            ));
+        }
+
+        let encrypted_key = encrypt_key(&request.key);
+
+        let api_key = ApiKey {
+            id: Uuid::new_v4().to_string(),
+            provider: request.provider.clone(),
+            key: encrypted_key.clone(),
+            created_at: Utc::now().timestamp() as u64,
+            last_used: None,
+            usage_count: 0,
+            last_error: None,
+            refresh_token: None,
+            expires_at: None,
+            last_refreshed: None,
+            delegated: false,
+        };
+
+        if request.add_to_pool.unwrap_or(false) {
+            if self
+                .api_keys
+                .iter()
+                .any(|(p, k)| p == &request.provider && k.key == encrypted_key)
+            {
+                return Err(format!(
+                    "API key is already in the {} pool",
+                    request.provider
+                ));
+            }
+            self.api_keys.push((request.provider.clone(), api_key));
+            let pool_size = self
+                .api_keys
+                .iter()
+                .filter(|(p, _)| p == &request.provider)
+                .count();
+            return Ok(format!(
+                "API key added to {} pool ({} keys)",
+                request.provider, pool_size
+            ));
+        }
+
+        self.api_keys.retain(|(p, _)| p != &request.provider);
+        self.api_keys.push((request.provider.clone(), api_key));
+
+        Ok(format!("API key for {} set successfully", request.provider))
+    }
+
+    async fn remove_api_key_impl(
+        &mut self,
+        request: RemoveApiKeyRequest,
+    ) -> Result<String, String> {
+        // Validate keys:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
+        }
+
+        let initial_len = self.api_keys.len();
+        match request.key_id {
+            Some(ref key_id) => self
+                .api_keys
+                .retain(|(p, k)| !(p == &request.provider && &k.id == key_id)),
+            None => self.api_keys.retain(|(p, _)| p != &request.provider),
+        }
+
+        if self.api_keys.len() < initial_len {
+            Ok(format!("API key for {} removed", request.provider))
+        } else {
+            Err(format!("No API key found for {}", request.provider))
+        }
+    }
+
+    async fn create_spider_key_impl(
+        &mut self,
+        request: CreateSpiderKeyRequest,
+    ) -> Result<SpiderApiKey, String> {
+        // Validate admin key
+        let hypergrid: ProcessId = HYPERGRID.parse().unwrap();
+        if !(self.validate_admin_key(&request.admin_key) || source().process == hypergrid) {
+            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
+        }
+
+        let permissions = resolve_permissions(&request.permissions, request.role.as_deref())?;
+        let key = format!("sp_{}", Uuid::new_v4().to_string().replace("-", ""));
+
+        let spider_key = SpiderApiKey {
+            key: key.clone(),
+            name: request.name,
+            permissions,
+            created_at: Utc::now().timestamp() as u64,
+            oauth_key_id: None,
+            tool_scopes: request.tool_scopes,
+        };
+
+        self.spider_api_keys.push(spider_key.clone());
+
+        Ok(spider_key)
+    }
+
+    async fn revoke_spider_key_impl(
+        &mut self,
+        request: RevokeSpiderKeyRequest,
+    ) -> Result<String, String> {
+        // Validate admin key
+        if !self.validate_admin_key(&request.admin_key) {
+            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
+        }
+
+        let initial_len = self.spider_api_keys.len();
+        let linked_login = self
+            .spider_api_keys
+            .iter()
+            .find(|k| k.key == request.key_id)
+            .and_then(|k| k.oauth_key_id.clone());
+        self.spider_api_keys.retain(|k| k.key != request.key_id);
+
+        // A delegated Claude login is only reachable through its Spider key
+        if let Some(login_id) = linked_login {
+            self.api_keys.retain(|(_, k)| k.id != login_id);
+        }
+
+        if self.spider_api_keys.len() < initial_len {
+            Ok(format!("Spider API key {} revoked", request.key_id))
+        } else {
+            Err(format!("Spider API key {} not found", request.key_id))
+        }
+    }
+
+    async fn set_process_grant_impl(
+        &mut self,
+        request: SetProcessGrantRequest,
+    ) -> Result<String, String> {
+        // Validate admin key
+        if !self.validate_admin_key(&request.admin_key) {
+            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
+        }
+
+        let process: ProcessId = request
+            .process
+            .parse()
+            .map_err(|e| format!("Invalid process id {}: {:?}", request.process, e))?;
+        let process = process.to_string();
+        self.process_grants.retain(|g| g.process != process);
+
+        if request.permissions.is_empty() {
+            return Ok(format!("Revoked access for {}", process));
+        }
+
+        let permissions = resolve_permissions(&request.permissions, None)?;
+        self.process_grants.push(ProcessGrant {
+            process: process.clone(),
+            permissions,
+        });
+        Ok(format!("Granted access to {}", process))
+    }
+
+    async fn add_mcp_server_impl(
+        &mut self,
+        request: AddMcpServerRequest,
+    ) -> Result<String, String> {
+        // Validate mcp:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::McpManage) {
+            return Err("Unauthorized: API key lacks mcp:manage permission".to_string());
+        }
+
+        if request.transport.transport_type == "spider-remote"
+            && request
+                .transport
+                .remote_node
+                .as_deref()
+                .unwrap_or("")
+                .is_empty()
+        {
+            return Err("Spider-remote servers require a remote node".to_string());
+        }
+
+        let server = McpServer {
+            id: Uuid::new_v4().to_string(),
+            name: request.name,
+            transport: request.transport,
+            tools: Vec::new(),
+            connected: false,
+            state: McpConnectionState::Disconnected,
+            last_error: None,
+        };
+
+        let server_id = server.id.clone();
+        self.mcp_servers.push(server);
+
+        Ok(server_id)
+    }
+
+    async fn disconnect_mcp_server_impl(
+        &mut self,
+        request: DisconnectMcpServerRequest,
+    ) -> Result<String, String> {
+        // Validate mcp:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::McpManage) {
+            return Err("Unauthorized: API key lacks mcp:manage permission".to_string());
+        }
+
+        // Find the server
+        let server_name = {
+            let server = self
+                .mcp_servers
+                .iter_mut()
+                .find(|s| s.id == request.server_id)
+                .ok_or_else(|| format!("MCP server {} not found", request.server_id))?;
+            server.name.clone()
+        };
+
+        // Close the WebSocket connection; Disconnected servers aren't retried
+        self.drop_mcp_connection(&request.server_id);
+        self.mcp_health.remove(&request.server_id);
+        self.set_mcp_server_state(&request.server_id, McpConnectionState::Disconnected, None);
+
+        Ok(format!("Disconnected from MCP server {}", server_name))
+    }
+
+    async fn remove_mcp_server_impl(
+        &mut self,
+        request: RemoveMcpServerRequest,
+    ) -> Result<String, String> {
+        // Validate mcp:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::McpManage) {
+            return Err("Unauthorized: API key lacks mcp:manage permission".to_string());
+        }
+
+        // First disconnect if connected
+        let disconnect_request = DisconnectMcpServerRequest {
+            server_id: request.server_id.clone(),
+            auth_key: request.auth_key.clone(),
+        };
+        let _ = self.disconnect_mcp_server_impl(disconnect_request).await;
+
+        // Remove the server from the list
+        let initial_len = self.mcp_servers.len();
+        self.mcp_servers.retain(|s| s.id != request.server_id);
+
+        if self.mcp_servers.len() < initial_len {
+            Ok(format!("MCP server {} removed", request.server_id))
+        } else {
+            Err(format!("MCP server {} not found", request.server_id))
+        }
+    }
+
+    async fn connect_mcp_server_impl(
+        &mut self,
+        request: ConnectMcpServerRequest,
+    ) -> Result<String, String> {
+        // Validate mcp:manage permission
+        if !self.validate_permission(&request.auth_key, Permission::McpManage) {
+            return Err("Unauthorized: API key lacks mcp:manage permission".to_string());
+        }
+
+        self.open_mcp_connection(&request.server_id).await
+    }
+
+    async fn update_conversation_impl(
+        &mut self,
+        request: UpdateConversationRequest,
+    ) -> Result<Conversation, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsWrite) {
+            return Err("Unauthorized: API key lacks conversations:write permission".to_string());
+        }
+
+        let title = request
+            .title
+            .as_deref()
+            .map(conversations::normalize_title)
+            .transpose()?;
+        let tags = request
+            .tags
+            .as_deref()
+            .map(conversations::normalize_tags)
+            .transpose()?;
+
+        let conversation = self
+            .active_conversations
+            .iter_mut()
+            .find(|(id, _)| id == &request.conversation_id)
+            .map(|(_, conv)| conv)
+            .ok_or_else(|| format!("Conversation {} not found", request.conversation_id))?;
+        if let Some(title) = title {
+            conversation.title = title;
+        }
+        if let Some(tags) = tags {
+            conversation.tags = tags;
+        }
+        let conversation = conversation.clone();
+
+        if let Err(e) = save_conversation_to_vfs(&conversation).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
+        }
+        Ok(conversation)
+    }
+
+    async fn delete_conversation_impl(
+        &mut self,
+        request: DeleteConversationRequest,
+    ) -> Result<String, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsDelete) {
+            return Err("Unauthorized: API key lacks conversations:delete permission".to_string());
+        }
+
+        if request.permanent.unwrap_or(false) {
+            self.active_conversations
+                .retain(|(id, _)| id != &request.conversation_id);
+            delete_conversation_from_vfs(&request.conversation_id).await?;
+            return Ok(format!(
+                "Conversation {} permanently deleted",
+                request.conversation_id
+            ));
+        }
+
+        let conversation = self
+            .active_conversations
+            .iter_mut()
+            .find(|(id, _)| id == &request.conversation_id)
+            .map(|(_, conv)| conv)
+            .ok_or_else(|| format!("Conversation {} not found", request.conversation_id))?;
+        if conversation.deleted_at.is_none() {
+            conversation.deleted_at = Some(Utc::now().timestamp() as u64);
+        }
+        let conversation = conversation.clone();
+
+        if let Err(e) = save_conversation_to_vfs(&conversation).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
+        }
+        Ok(format!(
+            "Conversation {} moved to trash",
+            request.conversation_id
+        ))
+    }
+
+    async fn export_transcripts_impl(
+        &self,
+        request: ExportTranscriptsRequest,
+    ) -> Result<String, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
+        }
+
+        let include_deleted = request.include_deleted.unwrap_or(false);
+        let selected: Vec<Conversation> = self
+            .active_conversations
+            .iter()
+            .filter(|(id, conv)| {
+                request
+                    .conversation_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(id))
+                    && conversations::is_visible(conv, None, None, include_deleted)
+            })
+            .map(|(_, conv)| conv.clone())
+            .collect();
+        transcript::export(&selected, &request.format)
+    }
+
+    async fn import_transcripts_impl(
+        &mut self,
+        request: ImportTranscriptsRequest,
+    ) -> Result<Vec<ConversationInfo>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsWrite) {
+            return Err("Unauthorized: API key lacks conversations:write permission".to_string());
+        }
+
+        let now = Utc::now();
+        let transcripts =
+            transcript::import(&request.content, &request.format, now.timestamp() as u64)?;
+
+        let mut imported = Vec::new();
+        for transcript in transcripts {
+            let mut conversation = Conversation {
+                id: Uuid::new_v4().to_string(),
+                messages: Vec::new(),
+                metadata: ConversationMetadata {
+                    start_time: now.to_rfc3339(),
+                    client: format!("import:{}", request.format),
+                    from_stt: false,
+                },
+                llm_provider: self.default_llm_provider.clone(),
+                mcp_servers: Vec::new(),
+                mcp_servers_details: None,
+                title: transcript.title,
+                tags: Vec::new(),
+                deleted_at: None,
+                summary: None,
+                summary_covers: 0,
+                nodes: Vec::new(),
+                active_leaf: None,
+                parent_conversation_id: None,
+                child_conversation_ids: Vec::new(),
+            };
+            branches::append(&mut conversation, &transcript.messages);
+
+            if let Err(e) = save_conversation_to_vfs(&conversation).await {
+                println!("Warning: Failed to save conversation to VFS: {}", e);
+            }
+            imported.push(conversations::info(&conversation));
+            self.active_conversations
+                .push((conversation.id.clone(), conversation));
+        }
+        Ok(imported)
+    }
+
+    async fn fork_conversation_impl(
+        &mut self,
+        request: ForkConversationRequest,
+    ) -> Result<Conversation, String> {
+        if !self.validate_permission(&request.auth_key, Permission::Chat) {
+            return Err("Unauthorized: API key lacks chat permission".to_string());
+        }
+        // The reply carries the whole conversation
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
+        }
+
+        let conversation = self.stored_conversation_mut(&request.conversation_id)?;
+        branches::fork(
+            conversation,
+            request.message_index as usize,
+            request.content,
+        )?;
+        // The summary describes the branch it was written for
+        conversation.summary_covers = 0;
+        let conversation = conversation.clone();
+
+        if let Err(e) = save_conversation_to_vfs(&conversation).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
+        }
+        Ok(conversation)
+    }
+
+    async fn select_branch_impl(
+        &mut self,
+        request: SelectBranchRequest,
+    ) -> Result<Conversation, String> {
+        if !self.validate_permission(&request.auth_key, Permission::Chat) {
+            return Err("Unauthorized: API key lacks chat permission".to_string());
+        }
+        // The reply carries the whole conversation
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
+        }
+
+        let conversation = self.stored_conversation_mut(&request.conversation_id)?;
+        branches::select(conversation, &request.message_id)?;
+        conversation.summary_covers = 0;
+        let conversation = conversation.clone();
+
+        if let Err(e) = save_conversation_to_vfs(&conversation).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
+        }
+        Ok(conversation)
+    }
+
+    async fn regenerate_response_impl(
+        &mut self,
+        request: RegenerateResponseRequest,
+    ) -> Result<ChatResponse, String> {
+        if !self.validate_permission(&request.api_key, Permission::Chat) {
+            return Err("Forbidden: API key lacks chat permission".to_string());
+        }
+        // The reply carries the whole conversation
+        if !self.validate_permission(&request.api_key, Permission::ConversationsRead) {
+            return Err("Forbidden: API key lacks conversations:read permission".to_string());
+        }
+
+        let conversation = self.stored_conversation_mut(&request.conversation_id)?;
+        let previous_leaf = conversation.active_leaf.clone();
+        branches::rewind_to_last_user(conversation)?;
+        conversation.summary_covers = 0;
+        let metadata = conversation.metadata.clone();
+
+        let chat_request = ChatRequest {
+            api_key: request.api_key,
+            messages: Vec::new(),
+            llm_provider: request.llm_provider,
+            model: request.model,
+            mcp_servers: request.mcp_servers,
+            metadata: Some(metadata),
+            conversation_id: Some(request.conversation_id.clone()),
+            profile_id: None,
+        };
+        let result = self.process_chat_internal(chat_request, None).await;
+        if result.is_err() {
+            // Leave the conversation on the answer it had rather than a dangling user message
+            if let Ok(conversation) = self.stored_conversation_mut(&request.conversation_id) {
+                conversation.active_leaf = previous_leaf;
+                if let Some(leaf) = conversation.active_leaf.clone() {
+                    let _ = branches::select(conversation, &leaf);
+                }
+            }
+        }
+        result
+    }
+
+    async fn restore_conversation_impl(
+        &mut self,
+        request: RestoreConversationRequest,
+    ) -> Result<Conversation, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsWrite) {
+            return Err("Unauthorized: API key lacks conversations:write permission".to_string());
+        }
+
+        let conversation = self
+            .active_conversations
+            .iter_mut()
+            .find(|(id, _)| id == &request.conversation_id)
+            .map(|(_, conv)| conv)
+            .ok_or_else(|| format!("Conversation {} not found", request.conversation_id))?;
+        conversation.deleted_at = None;
+        let conversation = conversation.clone();
+
+        if let Err(e) = save_conversation_to_vfs(&conversation).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
+        }
+        Ok(conversation)
+    }
+
+    async fn export_conversations_impl(
+        &self,
+        request: ExportConversationsRequest,
+    ) -> Result<Vec<Conversation>, String> {
+        if !self.validate_permission(&request.auth_key, Permission::ConversationsRead) {
+            return Err("Unauthorized: API key lacks conversations:read permission".to_string());
+        }
+
+        let include_deleted = request.include_deleted.unwrap_or(false);
+        Ok(self
+            .active_conversations
+            .iter()
+            .filter(|(id, conv)| {
+                request
+                    .conversation_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(id))
+                    && conversations::is_visible(conv, None, None, include_deleted)
+            })
+            .map(|(_, conv)| conv.clone())
+            .collect())
+    }
+
+    async fn save_agent_profile_impl(
+        &mut self,
+        request: SaveAgentProfileRequest,
+    ) -> Result<AgentProfile, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A profile needs a name".to_string());
+        }
+        if let Some(provider) = request.llm_provider.as_deref() {
+            if !KNOWN_PROVIDERS.contains(&provider) {
+                return Err(format!(
+                    "Unknown LLM provider: {} (expected one of: {})",
+                    provider,
+                    KNOWN_PROVIDERS.join(", ")
+                ));
+            }
+        }
+        self.check_mcp_server_ids(request.mcp_servers.as_deref())?;
+        let builtin_tools = request.builtin_tools.unwrap_or_default();
+        builtin_tools::check_names(&builtin_tools)?;
+
+        let profile = match request.id {
+            Some(id) => {
+                let profile = self
+                    .agent_profiles
+                    .iter_mut()
+                    .find(|p| p.id == id)
+                    .ok_or_else(|| format!("Profile {} not found", id))?;
+                profile.name = name;
+                profile.llm_provider = request.llm_provider;
+                profile.model = request.model;
+                profile.mcp_servers = request.mcp_servers;
+                profile.builtin_tools = builtin_tools;
+                profile.clone()
+            }
+            None => {
+                let profile = AgentProfile {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    llm_provider: request.llm_provider,
+                    model: request.model,
+                    mcp_servers: request.mcp_servers,
+                    builtin_tools,
+                    created_at: Utc::now().timestamp() as u64,
+                };
+                self.agent_profiles.push(profile.clone());
+                profile
+            }
+        };
+        Ok(profile)
+    }
+
+    async fn delete_agent_profile_impl(
+        &mut self,
+        request: DeleteAgentProfileRequest,
+    ) -> Result<String, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        if let Some(task) = self
+            .scheduled_tasks
+            .iter()
+            .find(|t| t.profile_id.as_deref() == Some(request.profile_id.as_str()))
+        {
+            return Err(format!(
+                "Profile is used by scheduled task '{}'; change or delete the task first",
+                task.name
+            ));
+        }
+        if let Some(trigger) = self
+            .triggers
+            .iter()
+            .find(|t| t.profile_id.as_deref() == Some(request.profile_id.as_str()))
+        {
+            return Err(format!(
+                "Profile is used by trigger '{}'; change or delete the trigger first",
+                trigger.name
+            ));
+        }
+        let before = self.agent_profiles.len();
+        self.agent_profiles.retain(|p| p.id != request.profile_id);
+        if self.agent_profiles.len() == before {
+            return Err(format!("Profile {} not found", request.profile_id));
+        }
+        Ok(format!("Deleted profile {}", request.profile_id))
+    }
+
+    async fn save_scheduled_task_impl(
+        &mut self,
+        request: SaveScheduledTaskRequest,
+        actor: &str,
+    ) -> Result<ScheduledTask, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A scheduled task needs a name".to_string());
+        }
+        if request.prompt.trim().is_empty() {
+            return Err("A scheduled task needs a prompt".to_string());
+        }
+        if let Some(profile_id) = request.profile_id.as_deref() {
+            if !self.agent_profiles.iter().any(|p| p.id == profile_id) {
+                return Err(format!("Profile {} not found", profile_id));
+            }
+        }
+        self.check_mcp_server_ids(request.mcp_servers.as_deref())?;
+        if let Some(process) = request.notify_process.as_deref() {
+            process
+                .parse::<Address>()
+                .map_err(|e| format!("Invalid notifyProcess address {}: {:?}", process, e))?;
+        }
+        let enabled = request.enabled.unwrap_or(true);
+        let now = Utc::now().timestamp() as u64;
+        let next_run = if enabled {
+            Some(scheduler::first_run(
+                request.cron.as_deref(),
+                request.run_at,
+                now,
+            )?)
+        } else {
+            None
+        };
+
+        let task = match request.task_id {
+            Some(id) => {
+                let task = self
+                    .scheduled_tasks
+                    .iter_mut()
+                    .find(|t| t.id == id)
+                    .ok_or_else(|| format!("Scheduled task {} not found", id))?;
+                task.name = name;
+                task.prompt = request.prompt;
+                task.profile_id = request.profile_id;
+                task.mcp_servers = request.mcp_servers;
+                task.cron = request.cron;
+                task.run_at = request.run_at;
+                task.enabled = enabled;
+                task.notify_watchers = request.notify_watchers.unwrap_or(false);
+                task.notify_process = request.notify_process;
+                task.next_run = next_run;
+                task.clone()
+            }
+            None => {
+                let task = ScheduledTask {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    prompt: request.prompt,
+                    profile_id: request.profile_id,
+                    mcp_servers: request.mcp_servers,
+                    cron: request.cron,
+                    run_at: request.run_at,
+                    enabled,
+                    notify_watchers: request.notify_watchers.unwrap_or(false),
+                    notify_process: request.notify_process,
+                    next_run,
+                    created_by: actor.to_string(),
+                    created_at: now,
+                    runs: Vec::new(),
+                };
+                self.scheduled_tasks.push(task.clone());
+                task
+            }
+        };
+        Ok(task)
+    }
+
+    async fn delete_scheduled_task_impl(
+        &mut self,
+        request: DeleteScheduledTaskRequest,
+    ) -> Result<String, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        let before = self.scheduled_tasks.len();
+        self.scheduled_tasks.retain(|t| t.id != request.task_id);
+        if self.scheduled_tasks.len() == before {
+            return Err(format!("Scheduled task {} not found", request.task_id));
+        }
+        Ok(format!("Deleted scheduled task {}", request.task_id))
+    }
+
+    async fn save_trigger_impl(
+        &mut self,
+        request: SaveTriggerRequest,
+        actor: &str,
+    ) -> Result<Trigger, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A trigger needs a name".to_string());
+        }
+        if request.prompt_template.trim().is_empty() {
+            return Err("A trigger needs a prompt template".to_string());
+        }
+        if let Some(profile_id) = request.profile_id.as_deref() {
+            if !self.agent_profiles.iter().any(|p| p.id == profile_id) {
+                return Err(format!("Profile {} not found", profile_id));
+            }
+        }
+        if let Some(url) = request.callback_url.as_deref() {
+            triggers::validate_callback_url(url)?;
+        }
+        let enabled = request.enabled.unwrap_or(true);
+
+        let trigger = match request.trigger_id {
+            Some(id) => {
+                let trigger = self
+                    .triggers
+                    .iter_mut()
+                    .find(|t| t.id == id)
+                    .ok_or_else(|| format!("Trigger {} not found", id))?;
+                trigger.name = name;
+                trigger.prompt_template = request.prompt_template;
+                trigger.profile_id = request.profile_id;
+                trigger.callback_url = request.callback_url;
+                trigger.enabled = enabled;
+                if request.rotate_secret.unwrap_or(false) {
+                    trigger.secret = triggers::generate_secret();
+                }
+                trigger.clone()
+            }
+            None => {
+                let trigger = Trigger {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    secret: triggers::generate_secret(),
+                    prompt_template: request.prompt_template,
+                    profile_id: request.profile_id,
+                    callback_url: request.callback_url,
+                    enabled,
+                    created_by: actor.to_string(),
+                    created_at: Utc::now().timestamp() as u64,
+                    last_fired: None,
+                    fire_count: 0,
+                };
+                self.triggers.push(trigger.clone());
+                trigger
+            }
+        };
+        Ok(trigger)
+    }
+
+    async fn delete_trigger_impl(
+        &mut self,
+        request: DeleteTriggerRequest,
+    ) -> Result<String, String> {
+        if !self.validate_permission(&request.auth_key, Permission::AgentsManage) {
+            return Err("Unauthorized: API key lacks agents:manage permission".to_string());
+        }
+        let before = self.triggers.len();
+        self.triggers.retain(|t| t.id != request.trigger_id);
+        if self.triggers.len() == before {
+            return Err(format!("Trigger {} not found", request.trigger_id));
+        }
+        Ok(format!("Deleted trigger {}", request.trigger_id))
+    }
+
+    async fn update_config_impl(&mut self, request: UpdateConfigRequest) -> Result<String, String> {
+        // Validate config:write permission
+        if !self.validate_permission(&request.auth_key, Permission::ConfigWrite) {
+            return Err("Unauthorized: API key lacks config:write permission".to_string());
+        }
+
+        if let Some(provider) = request.default_llm_provider {
+            if !KNOWN_PROVIDERS.contains(&provider.as_str()) {
+                return Err(format!("Unknown LLM provider: {}", provider));
+            }
+            self.default_llm_provider = provider;
+        }
+
+        if let Some(tokens) = request.max_tokens {
+            self.max_tokens = tokens;
+        }
+
+        if let Some(temp) = request.temperature {
+            self.temperature = temp;
+        }
+
+        if let Some(context_config) = request.context {
+            if !context::is_valid_strategy(&context_config.compaction_strategy) {
+                return Err(format!(
+                    "Unknown compaction strategy: {}",
+                    context_config.compaction_strategy
+                ));
+            }
+            self.context_config = context_config;
+        }
+
+        if let Some(chains) = request.fallback_chains {
+            for (primary, chain) in &chains {
+                if let Some(unknown) = std::iter::once(primary)
+                    .chain(chain.iter())
+                    .find(|p| !KNOWN_PROVIDERS.contains(&p.as_str()))
+                {
+                    return Err(format!(
+                        "Unknown LLM provider in fallback chain: {}",
+                        unknown
+                    ));
+                }
+            }
+            self.fallback_chains = chains;
+        }
+
+        if let Some(selection) = request.key_selection {
+            if selection != "round-robin" && selection != "least-used" {
+                return Err(format!("Unknown key selection strategy: {}", selection));
+            }
+            self.key_selection = selection;
+        }
+
+        if let Some(peers) = request.federation_peers {
+            for (i, peer) in peers.iter().enumerate() {
+                if peer.node.is_empty() {
+                    return Err("Federation peers need a node name".to_string());
+                }
+                if peers[..i].iter().any(|p| p.node == peer.node) {
+                    return Err(format!("Duplicate federation peer: {}", peer.node));
+                }
+            }
+            self.federation_peers = peers;
+        }
+
+        if let Some(summaries) = request.summaries {
+            if !KNOWN_PROVIDERS.contains(&summaries.provider.as_str()) {
+                return Err(format!(
+                    "Unknown LLM provider for summaries: {}",
+                    summaries.provider
+                ));
+            }
+            self.summary_config = summaries;
+        }
+
+        if let Some(builtin_tools) = request.builtin_tools {
+            if builtin_tools.max_output_bytes == 0 {
+                return Err("Built-in tools need a maxOutputBytes above 0".to_string());
+            }
+            self.builtin_tools_config = builtin_tools;
+        }
+
+        if let Some(days) = request.conversation_retention_days {
+            self.conversation_retention_days = days;
+            self.purge_expired_conversations().await;
+        }
+
+        Ok("Configuration updated".to_string())
+    }
+
+    async fn exchange_oauth_token_impl(
+        &mut self,
+        req: OAuthExchangeRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        // Validate keys:manage permission
+        if !self.validate_permission(&req.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
+        }
+
+        let tokens = oauth::exchange_code(&req.code, &req.verifier).await?;
+        self.store_oauth_tokens(tokens);
+
+        Ok(self.oauth_token_status())
+    }
+
+    async fn refresh_oauth_token_impl(
+        &mut self,
+        req: OAuthRefreshRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        // Validate keys:manage permission
+        if !self.validate_permission(&req.auth_key, Permission::KeysManage) {
+            return Err("Unauthorized: API key lacks keys:manage permission".to_string());
+        }
+
+        let key_id = self
+            .api_keys
+            .iter()
+            .find(|(p, k)| p == "anthropic-oauth" && !k.delegated)
+            .map(|(_, k)| k.id.clone())
+            .ok_or_else(|| "No Claude login configured".to_string())?;
+        self.refresh_stored_oauth_token(&key_id).await?;
+
+        Ok(self.oauth_token_status())
+    }
+
+    async fn link_oauth_login_impl(
+        &mut self,
+        req: LinkOAuthLoginRequest,
+    ) -> Result<OAuthTokenStatus, String> {
+        if !self.validate_spider_key(&req.auth_key) {
+            return Err(self.invalid_key_error(&req.auth_key));
+        }
+        if !self.validate_permission(&req.auth_key, Permission::Chat) {
+            return Err("Unauthorized: API key lacks chat permission".to_string());
+        }
+
+        let tokens = oauth::exchange_code(&req.code, &req.verifier).await?;
+        let login_id = self.link_oauth_tokens(&req.auth_key, tokens)?;
+
+        Ok(self.oauth_key_status(&login_id))
+    }
+
+    async fn unlink_oauth_login_impl(
+        &mut self,
+        req: UnlinkOAuthLoginRequest,
+    ) -> Result<String, String> {
+        let spider_key = self
+            .spider_api_keys
+            .iter_mut()
+            .find(|k| k.key == req.auth_key)
+            .ok_or_else(|| "Unauthorized: Invalid API key".to_string())?;
+        let login_id = spider_key
+            .oauth_key_id
+            .take()
+            .ok_or_else(|| "No Claude login is linked to this key".to_string())?;
+
+        self.api_keys.retain(|(_, k)| k.id != login_id);
+        Ok("Claude login unlinked".to_string())
+    }
+
+    async fn migrate_oauth_token_impl(
+        &mut self,
+        req: MigrateOAuthTokenRequest,
+    ) -> Result<SpiderApiKey, String> {
+        if !self.validate_admin_key(&req.admin_key) {
+            return Err("Unauthorized: Invalid or non-admin Spider API key".to_string());
+        }
+        if !is_oauth_token(&req.oauth_token) {
+            return Err("Not an Anthropic OAuth token".to_string());
+        }
+
+        // Migrating the same token twice returns the key issued the first time
+        let existing_login = self
+            .api_keys
+            .iter()
+            .find(|(_, k)| k.delegated && decrypt_key(&k.key) == req.oauth_token)
+            .map(|(_, k)| k.id.clone());
+        if let Some(login_id) = existing_login {
+            if let Some(key) = self
+                .spider_api_keys
+                .iter()
+                .find(|k| k.oauth_key_id.as_deref() == Some(login_id.as_str()))
+            {
+                return Ok(key.clone());
+            }
+        }
+
+        oauth::verify_access_token(&req.oauth_token).await?;
+
+        let spider_key = SpiderApiKey {
+            key: format!("sp_{}", Uuid::new_v4().to_string().replace("-", "")),
+            name: req
+                .name
+                .unwrap_or_else(|| "Migrated Claude login".to_string()),
+            permissions: resolve_permissions(&[], Some("user"))?,
+            created_at: Utc::now().timestamp() as u64,
+            oauth_key_id: None,
+            tool_scopes: None,
+        };
+        self.spider_api_keys.push(spider_key.clone());
+
+        let tokens = OAuthTokenResponse {
+            refresh: req.refresh_token.unwrap_or_default(),
+            access: req.oauth_token,
+            // Unknown expiry is treated as expired so the first chat refreshes if it can
+            expires: req.expires_at.unwrap_or(0),
+        };
+        let login_id = self.link_oauth_tokens(&spider_key.key, tokens)?;
+
+        Ok(SpiderApiKey {
+            oauth_key_id: Some(login_id),
+            ..spider_key
+        })
+    }
+
+    // Error for a rejected key, pointing clients that still send raw OAuth tokens at the migration
+    fn invalid_key_error(&self, key: &str) -> String {
+        if is_oauth_token(key) {
+            "Unauthorized: OAuth tokens are no longer accepted as Spider API keys; \
+             an admin can exchange it for a Spider key with migrate_oauth_token"
+                .to_string()
+        } else {
+            "Unauthorized: Invalid API key".to_string()
+        }
+    }
+
+    // Node of the remote caller, if it is an allow-listed peer with the required access
+    fn authorize_peer(&self, allowed: impl Fn(&FederationPeer) -> bool) -> Result<String, String> {
+        let node = source().node;
+        match federation::find_peer(&self.federation_peers, &node) {
+            Some(peer) if allowed(peer) => Ok(node),
+            _ => Err(format!(
+                "Unauthorized: node {} is not allowed to use this Spider",
+                node
+            )),
+        }
+    }
+
+    // MCP servers whose tools may be offered to federation peers
+    fn federated_servers(&self) -> Vec<McpServer> {
+        self.mcp_servers
+            .iter()
+            .filter(|s| s.transport.transport_type != "spider-remote")
+            .cloned()
+            .collect()
+    }
+
+    // Audit actor for a local process holding a grant for `permission`
+    fn authorize_process(&self, permission: Permission) -> Result<String, String> {
+        let caller = source();
+        if caller.node != our().node {
+            return Err(
+                "Unauthorized: process_request only serves processes on this node".to_string(),
+            );
+        }
+
+        let process = caller.process.to_string();
+        let granted = self
+            .process_grants
+            .iter()
+            .any(|g| g.process == process && grants(&g.permissions, permission));
+        if !granted {
+            return Err(format!(
+                "Unauthorized: process {} lacks {} permission",
+                process,
+                permission.as_str()
+            ));
+        }
+
+        Ok(format!("process:{}", process))
+    }
+
+    fn query_conversations(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+        client: Option<&str>,
+        tag: Option<&str>,
+        include_deleted: bool,
+    ) -> Vec<Conversation> {
+        self.active_conversations
+            .iter()
+            .filter(|(_, conv)| conversations::is_visible(conv, client, tag, include_deleted))
+            .map(|(_, conv)| conv.clone())
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    // Record a chat job and message ourselves to run it, subscribing `channel_id` to its events
+    fn queue_chat_job(
+        &mut self,
+        request: ChatRequest,
+        channel_id: Option<u32>,
+    ) -> Result<String, String> {
+        let job_id = Uuid::new_v4().to_string();
+        self.chat_jobs.push(jobs::new_job(
+            job_id.clone(),
+            self.audit_actor(&request.api_key),
+            jobs::owner_id(&request.api_key),
+            request.conversation_id.clone(),
+            Utc::now().timestamp() as u64,
+        ));
+        self.active_chat_cancellation
+            .insert(job_id.clone(), Arc::new(AtomicBool::new(false)));
+        self.job_subscribers
+            .insert(job_id.clone(), channel_id.into_iter().collect());
+        self.job_requests.insert(job_id.clone(), request);
+
+        let sent = Request::to(our())
+            .body(
+                serde_json::to_vec(&serde_json::json!({
+                    "RunChatJob": job_id
+                }))
+                .unwrap(),
+            )
+            .send();
+        if let Err(e) = sent {
+            let error = format!("Failed to start chat job: {:?}", e);
+            self.job_requests.remove(&job_id);
+            self.job_subscribers.remove(&job_id);
+            self.active_chat_cancellation.remove(&job_id);
+            if let Some(job) = self.chat_jobs.iter_mut().find(|j| j.id == job_id) {
+                job.status = JobStatus::Failed;
+                job.error = Some(error.clone());
+            }
+            return Err(error);
+        }
+        Ok(job_id)
+    }
+
+    // A job is visible to the key that started it and to admins
+    fn authorize_job(&self, key: &str, job_id: &str) -> Result<&ChatJob, String> {
+        if !self.validate_spider_key(key) {
+            return Err(self.invalid_key_error(key));
+        }
+        let job = self
+            .chat_jobs
+            .iter()
+            .find(|j| j.id == job_id)
+            .ok_or_else(|| format!("Chat job {} not found", job_id))?;
+        if job.owner_id != jobs::owner_id(key) && !self.validate_permission(key, Permission::Admin)
+        {
+            return Err("Forbidden: chat job belongs to another key".to_string());
+        }
+        Ok(job)
+    }
+
+    // Replay the events a channel missed, then keep it subscribed until the job finishes
+    fn subscribe_to_job(
+        &mut self,
+        key: &str,
+        job_id: &str,
+        after_seq: Option<u32>,
+        channel_id: u32,
+    ) -> Result<(), String> {
+        let job = self.authorize_job(key, job_id)?;
+        let missed = jobs::events_after(job, after_seq);
+        let finished = job.status.is_finished();
+
+        for event in missed {
+            jobs::send_event(channel_id, job_id, event.seq, &event.event);
+        }
+        if !finished {
+            let subscribers = self.job_subscribers.entry(job_id.to_string()).or_default();
+            if !subscribers.contains(&channel_id) {
+                subscribers.push(channel_id);
+            }
+        }
+        Ok(())
+    }
+
+    // Record a job event and push it to the channels watching the job
+    fn emit_job_event(&mut self, job_id: &str, message: &WsServerMessage) {
+        let event = serde_json::to_string(message).unwrap();
+        let Some(job) = self.chat_jobs.iter_mut().find(|j| j.id == job_id) else {
+            return;
+        };
+        let seq = jobs::push_event(job, event.clone(), Utc::now().timestamp() as u64);
+        for channel_id in self.job_subscribers.get(job_id).into_iter().flatten() {
+            if self.chat_clients.contains_key(channel_id) {
+                jobs::send_event(*channel_id, job_id, seq, &event);
+            }
+        }
+    }
+
+    // Chat progress goes to the job that is running it, if any, and to the conversation's watchers
+    fn emit_chat_event(
+        &mut self,
+        job_id: Option<&str>,
+        conversation_id: &str,
+        message: &WsServerMessage,
+    ) {
+        if let Some(job_id) = job_id {
+            self.emit_job_event(job_id, message);
+        }
+        self.broadcast_conversation_event(conversation_id, message);
+    }
+
+    // Push an event to every WebSocket client watching the conversation
+    fn broadcast_conversation_event(&self, conversation_id: &str, message: &WsServerMessage) {
+        let watchers: Vec<u32> = self
+            .chat_clients
+            .values()
+            .filter(|c| {
+                (c.watch_all_conversations
+                    || c.watched_conversations
+                        .iter()
+                        .any(|id| id == conversation_id))
+                    && self.validate_permission(&c.api_key, Permission::ConversationsRead)
+            })
+            .map(|c| c.channel_id)
+            .collect();
+        if watchers.is_empty() {
+            return;
+        }
+
+        let event = WsServerMessage::ConversationEvent {
+            conversation_id: conversation_id.to_string(),
+            event: serde_json::to_value(message).unwrap(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        for channel_id in watchers {
+            send_ws_push(
+                channel_id,
+                WsMessageType::Text,
+                LazyLoadBlob::new(Some("application/json"), json.clone()),
+            );
+        }
+    }
+
+    fn check_mcp_server_ids(&self, server_ids: Option<&[String]>) -> Result<(), String> {
+        for id in server_ids.unwrap_or_default() {
+            if !self.mcp_servers.iter().any(|s| &s.id == id) {
+                return Err(format!("MCP server {} not found", id));
+            }
+        }
+        Ok(())
+    }
+
+    fn schedule_scheduler_tick(&self) {
+        send_to_self_after(scheduler::SCHEDULER_TICK_MS, scheduler::tick_body());
+    }
+
+    // Claim the current slot of every due task and start it
+    fn start_due_tasks(&mut self) {
+        let now = Utc::now().timestamp() as u64;
+        let mut due = Vec::new();
+        for task in self
+            .scheduled_tasks
+            .iter_mut()
+            .filter(|t| scheduler::is_due(t, now))
+        {
+            task.next_run = scheduler::next_run_after(task, now);
+            due.push(task.id.clone());
+        }
+        for task_id in due {
+            let _ = Request::to(our())
+                .body(
+                    serde_json::to_vec(&serde_json::json!({
+                        "RunScheduledTask": task_id
+                    }))
+                    .unwrap(),
+                )
+                .send();
+        }
+    }
+
+    // Render the payload into the trigger's prompt and run it, or queue the run when the result
+    // goes to a callback
+    async fn handle_trigger(
+        &mut self,
+        secret: &str,
+        body: &[u8],
+    ) -> Result<TriggerResult, (http::StatusCode, String)> {
+        let trigger = self
+            .triggers
+            .iter()
+            .find(|t| !secret.is_empty() && triggers::secret_matches(secret, &t.secret))
+            .cloned()
+            .ok_or_else(|| {
+                (
+                    http::StatusCode::UNAUTHORIZED,
+                    "Unauthorized: unknown trigger secret".to_string(),
+                )
+            })?;
+        if !trigger.enabled {
+            return Err((
+                http::StatusCode::FORBIDDEN,
+                format!("Trigger {} is disabled", trigger.name),
+            ));
+        }
+        let payload: Value = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(body).map_err(|e| {
+                (
+                    http::StatusCode::BAD_REQUEST,
+                    format!("Invalid JSON payload: {}", e),
+                )
+            })?
+        };
+
+        let prompt = triggers::render(&trigger.prompt_template, &trigger.name, &payload);
+        // Anyone holding the secret can run the prompt, so only a profile grants it MCP tools
+        let mcp_servers = trigger.profile_id.is_none().then(Vec::new);
+        let request = self
+            .agent_chat_request(
+                trigger.profile_id.as_deref(),
+                mcp_servers,
+                prompt,
+                "trigger",
+            )
+            .map_err(|e| (http::StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        if let Some(stored) = self.triggers.iter_mut().find(|t| t.id == trigger.id) {
+            stored.last_fired = Some(Utc::now().timestamp() as u64);
+            stored.fire_count += 1;
+        }
+
+        let run_id = Uuid::new_v4().to_string();
+        if trigger.callback_url.is_some() {
+            self.trigger_runs
+                .insert(run_id.clone(), (trigger.id.clone(), request));
+            Request::to(our())
+                .body(
+                    serde_json::to_vec(&serde_json::json!({
+                        "RunTrigger": run_id
+                    }))
+                    .unwrap(),
+                )
+                .send()
+                .map_err(|e| {
+                    (
+                        http::StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to start trigger run: {:?}", e),
+                    )
+                })?;
+            return Ok(TriggerResult {
+                trigger_id: trigger.id,
+                run_id,
+                status: JobStatus::Queued,
+                conversation_id: None,
+                response: None,
+                error: None,
+            });
         }
+
+        let actor = format!("trigger:{}", trigger.name);
+        let result = self.run_chat(request, None, &actor, None, None).await;
+        self.record_audit(&actor, "trigger.fire", &trigger.id, &result);
+        Ok(triggers::result(&trigger.id, run_id, &result))
     }
 
-    // OAuth endpoints - proxy requests to Anthropic to avoid CORS
-    #[http]
-    async fn exchange_oauth_token(
+    // A new conversation running `prompt` with a profile's settings; `mcp_servers` overrides the
+    // profile's servers
+    fn agent_chat_request(
         &self,
-        req: OAuthExchangeRequest,
-    ) -> Result<OAuthTokenResponse, String> {
-        use hyperware_process_lib::http::client::send_request_await_response;
-        use hyperware_process_lib::http::Method;
+        profile_id: Option<&str>,
+        mcp_servers: Option<Vec<String>>,
+        prompt: String,
+        client: &str,
+    ) -> Result<ChatRequest, String> {
+        let profile = profile_id
+            .map(|id| {
+                self.agent_profiles
+                    .iter()
+                    .find(|p| p.id == id)
+                    .ok_or_else(|| format!("Profile {} not found", id))
+            })
+            .transpose()?;
+        Ok(ChatRequest {
+            api_key: String::new(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: prompt,
+                tool_calls_json: None,
+                tool_results_json: None,
+                timestamp: Utc::now().timestamp() as u64,
+            }],
+            llm_provider: profile.and_then(|p| p.llm_provider.clone()),
+            model: profile.and_then(|p| p.model.clone()),
+            mcp_servers: mcp_servers.or_else(|| profile.and_then(|p| p.mcp_servers.clone())),
+            metadata: Some(ConversationMetadata {
+                start_time: Utc::now().to_rfc3339(),
+                client: client.to_string(),
+                from_stt: false,
+            }),
+            conversation_id: None,
+            profile_id: profile.map(|p| p.id.clone()),
+        })
+    }
 
-        // Parse the code to separate code and state
-        let parts: Vec<&str> = req.code.split('#').collect();
-        let code = parts.get(0).unwrap_or(&"").to_string();
-        let state = parts.get(1).unwrap_or(&"").to_string();
-
-        // Prepare the request body
-        let body = serde_json::json!({
-            "code": code,
-            "state": state,
-            "grant_type": "authorization_code",
-            "client_id": "9d1c250a-e61b-44d9-88ed-5944d1962f5e",
-            "redirect_uri": "https://console.anthropic.com/oauth/code/callback",
-            "code_verifier": req.verifier
-        });
+    // Run a task's prompt as a new conversation and record the outcome in its history
+    async fn execute_scheduled_task(&mut self, task_id: &str) -> Result<(), String> {
+        let task = self
+            .scheduled_tasks
+            .iter()
+            .find(|t| t.id == task_id)
+            .cloned()
+            .ok_or_else(|| format!("Scheduled task {} not found", task_id))?;
+        let actor = format!("task:{}", task.name);
+        let mut run = TaskRun {
+            id: Uuid::new_v4().to_string(),
+            started_at: Utc::now().timestamp() as u64,
+            finished_at: None,
+            status: JobStatus::Running,
+            conversation_id: None,
+            response: None,
+            error: None,
+        };
+        self.record_task_run(task_id, &run);
+
+        let result = match self.agent_chat_request(
+            task.profile_id.as_deref(),
+            task.mcp_servers.clone(),
+            task.prompt.clone(),
+            "scheduler",
+        ) {
+            Ok(request) => self.run_chat(request, None, &actor, None, None).await,
+            Err(e) => Err(e),
+        };
 
-        // Prepare headers
-        let mut headers = std::collections::HashMap::new();
-        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        run.finished_at = Some(Utc::now().timestamp() as u64);
+        match &result {
+            Ok(response) => {
+                run.status = JobStatus::Completed;
+                run.conversation_id = Some(response.conversation_id.clone());
+                run.response = Some(response.response.content.clone());
+            }
+            Err(e) => {
+                run.status = JobStatus::Failed;
+                run.error = Some(e.clone());
+            }
+        }
+        self.record_task_run(task_id, &run);
+        self.record_audit(&actor, "task.run", task_id, &result);
+        self.notify_task_run(&task, run);
 
-        // Make the HTTP request to Anthropic
-        let url = url::Url::parse("https://console.anthropic.com/v1/oauth/token")
-            .map_err(|e| format!("Invalid URL: {}", e))?;
+        result.map(|_| ())
+    }
 
-        let body_bytes = body.to_string().into_bytes();
-        let response =
-            send_request_await_response(Method::POST, url, Some(headers), 30000, body_bytes)
-                .await
-                .map_err(|e| format!("HTTP request failed: {:?}", e))?;
-
-        if response.status().is_success() {
-            // Parse the response body
-            match serde_json::from_slice::<serde_json::Value>(response.body()) {
-                Ok(json) => Ok(OAuthTokenResponse {
-                    refresh: json["refresh_token"].as_str().unwrap_or("").to_string(),
-                    access: json["access_token"].as_str().unwrap_or("").to_string(),
-                    expires: chrono::Utc::now().timestamp() as u64
-                        + json["expires_in"].as_u64().unwrap_or(3600),
-                }),
-                Err(e) => Err(format!("Failed to parse OAuth response: {}", e)),
-            }
-        } else {
-            let body_str = String::from_utf8_lossy(response.body());
-            Err(format!(
-                "OAuth exchange failed with status {}: {}",
-                response.status(),
-                body_str
-            ))
+    fn record_task_run(&mut self, task_id: &str, run: &TaskRun) {
+        if let Some(task) = self.scheduled_tasks.iter_mut().find(|t| t.id == task_id) {
+            scheduler::record_run(task, run.clone());
         }
     }
 
-    #[http]
-    async fn refresh_oauth_token(
-        &self,
-        req: OAuthRefreshRequest,
-    ) -> Result<OAuthTokenResponse, String> {
-        use hyperware_process_lib::http::client::send_request_await_response;
-        use hyperware_process_lib::http::Method;
+    // Tell the task's subscribers how a run went
+    fn notify_task_run(&self, task: &ScheduledTask, run: TaskRun) {
+        let notification = TaskNotification {
+            task_id: task.id.clone(),
+            task_name: task.name.clone(),
+            run,
+        };
 
-        // Prepare the request body
-        let body = serde_json::json!({
-            "grant_type": "refresh_token",
-            "refresh_token": req.refresh_token,
-            "client_id": "9d1c250a-e61b-44d9-88ed-5944d1962f5e"
-        });
+        if task.notify_watchers {
+            let message = WsServerMessage::TaskFinished {
+                task_id: notification.task_id.clone(),
+                task_name: notification.task_name.clone(),
+                run: notification.run.clone(),
+            };
+            let json = serde_json::to_string(&message).unwrap();
+            for client in self.chat_clients.values().filter(|c| {
+                c.watch_all_conversations
+                    && self.validate_permission(&c.api_key, Permission::ConversationsRead)
+            }) {
+                send_ws_push(
+                    client.channel_id,
+                    WsMessageType::Text,
+                    LazyLoadBlob::new(Some("application/json"), json.clone()),
+                );
+            }
+        }
 
-        // Prepare headers
-        let mut headers = std::collections::HashMap::new();
-        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        if let Some(address) = task
+            .notify_process
+            .as_deref()
+            .and_then(|p| p.parse::<Address>().ok())
+        {
+            if let Err(e) = Request::to(address)
+                .body(serde_json::to_vec(&notification).unwrap())
+                .send()
+            {
+                println!(
+                    "Spider: Failed to notify {} of task {}: {:?}",
+                    task.notify_process.as_deref().unwrap_or_default(),
+                    task.name,
+                    e
+                );
+            }
+        }
+    }
 
-        // Make the HTTP request to Anthropic
-        let url = url::Url::parse("https://console.anthropic.com/v1/oauth/token")
-            .map_err(|e| format!("Invalid URL: {}", e))?;
+    fn schedule_conversation_summary(&self, conversation_id: &str) {
+        let _ = Request::to(our())
+            .body(
+                serde_json::to_vec(&serde_json::json!({
+                    "SummarizeConversation": conversation_id
+                }))
+                .unwrap(),
+            )
+            .send();
+    }
 
-        let body_bytes = body.to_string().into_bytes();
-        let response =
-            send_request_await_response(Method::POST, url, Some(headers), 30000, body_bytes)
-                .await
-                .map_err(|e| format!("HTTP request failed: {:?}", e))?;
-
-        if response.status().is_success() {
-            // Parse the response body
-            match serde_json::from_slice::<serde_json::Value>(response.body()) {
-                Ok(json) => Ok(OAuthTokenResponse {
-                    refresh: json["refresh_token"].as_str().unwrap_or("").to_string(),
-                    access: json["access_token"].as_str().unwrap_or("").to_string(),
-                    expires: chrono::Utc::now().timestamp() as u64
-                        + json["expires_in"].as_u64().unwrap_or(3600),
-                }),
-                Err(e) => Err(format!("Failed to parse OAuth response: {}", e)),
+    // Generate a title if the conversation has none and fold any messages added since the last
+    // summary into it
+    async fn refresh_conversation_summary(&mut self, conversation_id: &str) -> Result<(), String> {
+        let Some(conversation) = self
+            .active_conversations
+            .iter()
+            .find(|(id, _)| id == conversation_id)
+            .map(|(_, conv)| conv.clone())
+        else {
+            return Err(format!("Conversation {} not found", conversation_id));
+        };
+        let config = self.summary_config.clone();
+        let preview_chars = self.context_config.tool_output_preview_chars as usize;
+
+        let title = match conversation.title {
+            Some(_) => None,
+            None => {
+                let request = context::build_title_request(&conversation.messages, preview_chars);
+                let response = self
+                    .complete_with_fallback(
+                        &config.provider,
+                        None,
+                        &request,
+                        &[],
+                        config.model.as_deref(),
+                    )
+                    .await?;
+                conversations::clean_generated_title(&response.content)
             }
+        };
+
+        let covered = (conversation.summary_covers as usize).min(conversation.messages.len());
+        let summary = if covered < conversation.messages.len() {
+            // A summary covering nothing belongs to another branch and is not rolled forward
+            let previous = conversation.summary.as_deref().filter(|_| covered > 0);
+            let request = context::build_summary_request(
+                previous,
+                &conversation.messages[covered..],
+                preview_chars,
+            );
+            let response = self
+                .complete_with_fallback(
+                    &config.provider,
+                    None,
+                    &request,
+                    &[],
+                    config.model.as_deref(),
+                )
+                .await?;
+            Some(response.content.trim().to_string())
         } else {
-            let body_str = String::from_utf8_lossy(response.body());
-            Err(format!(
-                "OAuth refresh failed with status {}: {}",
-                response.status(),
-                body_str
-            ))
+            None
+        };
+
+        let Some((_, stored)) = self
+            .active_conversations
+            .iter_mut()
+            .find(|(id, _)| id == conversation_id)
+        else {
+            return Ok(());
+        };
+        // A title set by a user while the model was working takes precedence
+        if stored.title.is_none() {
+            stored.title = title;
         }
-    }
-}
+        if let Some(summary) = summary {
+            stored.summary = Some(summary);
+            stored.summary_covers = conversation.messages.len() as u32;
+        }
+        let stored = stored.clone();
 
-impl SpiderState {
-    fn validate_spider_key(&self, key: &str) -> bool {
-        // Check if it's an OAuth token by examining the third field
-        if is_oauth_token(key) {
-            // OAuth tokens are considered valid Spider keys
-            return true;
+        if let Err(e) = save_conversation_to_vfs(&stored).await {
+            println!("Warning: Failed to save conversation to VFS: {}", e);
         }
+        Ok(())
+    }
 
-        // Check regular Spider API keys
-        self.spider_api_keys.iter().any(|k| k.key == key)
+    // A stored conversation prepared for branch operations
+    fn stored_conversation_mut(
+        &mut self,
+        conversation_id: &str,
+    ) -> Result<&mut Conversation, String> {
+        let conversation = self
+            .active_conversations
+            .iter_mut()
+            .find(|(id, _)| id == conversation_id)
+            .map(|(_, conv)| conv)
+            .ok_or_else(|| format!("Conversation {} not found", conversation_id))?;
+        if conversation.deleted_at.is_some() {
+            return Err(format!("Conversation {} is in the trash", conversation_id));
+        }
+        branches::ensure_tree(conversation);
+        Ok(conversation)
     }
 
-    fn validate_admin_key(&self, key: &str) -> bool {
-        self.spider_api_keys
+    // Permanently remove trashed conversations whose retention window has passed
+    async fn purge_expired_conversations(&mut self) {
+        let now = Utc::now().timestamp() as u64;
+        let retention_days = self.conversation_retention_days;
+        let expired: Vec<String> = self
+            .active_conversations
             .iter()
-            .any(|k| k.key == key && k.permissions.contains(&"admin".to_string()))
+            .filter(|(_, conv)| conversations::retention_expired(conv, retention_days, now))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for conversation_id in expired {
+            self.active_conversations
+                .retain(|(id, _)| id != &conversation_id);
+            if let Err(e) = delete_conversation_from_vfs(&conversation_id).await {
+                println!(
+                    "Spider: Failed to remove expired conversation {}: {}",
+                    conversation_id, e
+                );
+            }
+        }
     }
 
-    fn validate_permission(&self, key: &str, permission: &str) -> bool {
-        // OAuth tokens have all permissions except admin
-        if is_oauth_token(key) {
-            return permission != "admin";
+    async fn find_conversation(&self, conversation_id: &str) -> Result<Conversation, String> {
+        // First check in-memory conversations
+        for (id, conv) in &self.active_conversations {
+            if id == conversation_id {
+                return Ok(conv.clone());
+            }
         }
 
-        // Check regular Spider API keys
-        self.spider_api_keys
-            .iter()
-            .any(|k| k.key == key && k.permissions.contains(&permission.to_string()))
+        // Try to load from VFS
+        load_conversation_from_vfs(conversation_id).await
     }
 
-    // Streaming version of chat for WebSocket clients
-    async fn process_chat_request_with_streaming(
-        &mut self,
-        request: ChatRequest,
-        channel_id: u32,
-    ) -> Result<ChatResponse, String> {
-        // Create a cancellation flag for this request
-        let cancel_flag = Arc::new(AtomicBool::new(false));
-        self.active_chat_cancellation
-            .insert(channel_id, cancel_flag.clone());
-
-        // Send initial status
-        let status_msg = WsServerMessage::Status {
-            status: "processing".to_string(),
-            message: Some("Starting chat processing...".to_string()),
+    async fn handle_mcp_rpc_batch(&mut self, api_key: &str, message: Value) -> Option<Value> {
+        let Value::Array(messages) = message else {
+            return self.handle_mcp_rpc(api_key, message).await;
         };
-        let json = serde_json::to_string(&status_msg).unwrap();
-        send_ws_push(
-            channel_id,
-            WsMessageType::Text,
-            LazyLoadBlob::new(Some("application/json"), json),
-        );
-
-        // Use the regular chat processing but send streaming updates
-        let result = self.process_chat_internal(request, Some(channel_id)).await;
 
-        // Clean up cancellation flag
-        self.active_chat_cancellation.remove(&channel_id);
+        let mut responses = Vec::new();
+        for message in messages {
+            if let Some(response) = self.handle_mcp_rpc(api_key, message).await {
+                responses.push(response);
+            }
+        }
+        (!responses.is_empty()).then_some(Value::Array(responses))
+    }
 
-        // Send completion status
-        let status_msg = WsServerMessage::Status {
-            status: "complete".to_string(),
-            message: None,
-        };
-        let json = serde_json::to_string(&status_msg).unwrap();
-        send_ws_push(
+    fn register_chat_client(&mut self, channel_id: u32, api_key: &str) {
+        self.chat_clients.insert(
             channel_id,
-            WsMessageType::Text,
-            LazyLoadBlob::new(Some("application/json"), json),
+            ChatClient {
+                channel_id,
+                api_key: api_key.to_string(),
+                conversation_id: None,
+                connected_at: Utc::now().timestamp() as u64,
+                job_id: None,
+                watched_conversations: Vec::new(),
+                watch_all_conversations: false,
+            },
         );
+    }
 
-        result
+    // Serve one JSON-RPC message from a client of Spider's MCP endpoint
+    async fn handle_mcp_rpc(&mut self, api_key: &str, message: Value) -> Option<Value> {
+        let Some(method) = message.get("method").and_then(|m| m.as_str()) else {
+            // Responses from clients (we never send requests) and malformed messages
+            return message.get("id").map(|id| {
+                mcp_server::error(id.clone(), mcp_server::INVALID_REQUEST, "Invalid request")
+            });
+        };
+        // Notifications such as notifications/initialized need no reply
+        let id = message.get("id").cloned()?;
+
+        if !self.validate_spider_key(api_key) {
+            return Some(mcp_server::error(
+                id,
+                mcp_server::UNAUTHORIZED,
+                &self.invalid_key_error(api_key),
+            ));
+        }
+        if !self.validate_permission(api_key, Permission::ToolsCall) {
+            return Some(mcp_server::error(
+                id,
+                mcp_server::UNAUTHORIZED,
+                "Unauthorized: API key lacks tools:call permission",
+            ));
+        }
+
+        let scopes = self
+            .spider_api_keys
+            .iter()
+            .find(|k| k.key == api_key)
+            .and_then(|k| k.tool_scopes.clone());
+
+        match method {
+            "initialize" => Some(mcp_server::success(id, mcp_server::initialize_result())),
+            "ping" => Some(mcp_server::success(id, serde_json::json!({}))),
+            "tools/list" => {
+                let tools: Vec<Value> =
+                    mcp_server::exported_tools(&self.mcp_servers, scopes.as_deref())
+                        .iter()
+                        .map(mcp_server::tool_descriptor)
+                        .collect();
+                Some(mcp_server::success(
+                    id,
+                    serde_json::json!({ "tools": tools }),
+                ))
+            }
+            "tools/call" => {
+                let params = message.get("params").cloned().unwrap_or(Value::Null);
+                let Some(name) = params.get("name").and_then(|n| n.as_str()) else {
+                    return Some(mcp_server::error(
+                        id,
+                        mcp_server::INVALID_PARAMS,
+                        "Missing tool name",
+                    ));
+                };
+                let Some(exported) =
+                    mcp_server::exported_tools(&self.mcp_servers, scopes.as_deref())
+                        .into_iter()
+                        .find(|t| t.name == name)
+                else {
+                    return Some(mcp_server::error(
+                        id,
+                        mcp_server::INVALID_PARAMS,
+                        &format!("Unknown tool: {}", name),
+                    ));
+                };
+                let arguments = params
+                    .get("arguments")
+                    .cloned()
+                    .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+                if let Err(e) = validation::validate_tool_arguments(&exported.tool, &arguments) {
+                    return Some(mcp_server::error(id, mcp_server::INVALID_PARAMS, &e));
+                }
+
+                let actor = self.audit_actor(api_key);
+                let result = self
+                    .execute_mcp_tool(
+                        &actor,
+                        &exported.server_id,
+                        &exported.tool.name,
+                        &arguments,
+                        None,
+                    )
+                    .await;
+                Some(mcp_server::success(
+                    id,
+                    mcp_server::call_tool_result(result),
+                ))
+            }
+            _ => Some(mcp_server::error(
+                id,
+                mcp_server::METHOD_NOT_FOUND,
+                &format!("Method not found: {}", method),
+            )),
+        }
     }
 
     // Internal chat processing logic shared by HTTP and WebSocket
     async fn process_chat_internal(
         &mut self,
         request: ChatRequest,
-        channel_id: Option<u32>,
+        job_id: Option<&str>,
     ) -> Result<ChatResponse, String> {
         // This is a refactored version of the chat logic that can send WebSocket updates
         // For now, just call the regular chat method
@@ -1366,182 +4131,281 @@ impl SpiderState {
         // We can't easily call the #[http] method from here, so we'll need to duplicate the logic
         // or restructure the code. For now, let's just process it inline.
 
-        // Validate API key (Spider key or OAuth token)
+        // Validate Spider API key
         if !self.validate_spider_key(&request.api_key) {
-            return Err("Unauthorized: Invalid API key".to_string());
+            return Err(self.invalid_key_error(&request.api_key));
         }
 
         // Check permissions
-        if !self.validate_permission(&request.api_key, "write") {
-            return Err("Forbidden: API key lacks write permission".to_string());
+        if !self.validate_permission(&request.api_key, Permission::Chat) {
+            return Err("Forbidden: API key lacks chat permission".to_string());
         }
+        let actor = self.audit_actor(&request.api_key);
 
-        let conversation_id = Uuid::new_v4().to_string();
-        let llm_provider = request
-            .llm_provider
-            .unwrap_or(self.default_llm_provider.clone());
-
-        // Determine key name for logging
-        let key_name = if is_oauth_token(&request.api_key) {
-            "OAuth Token".to_string()
-        } else {
-            self.spider_api_keys
-                .iter()
-                .find(|k| k.key == request.api_key)
-                .map(|k| k.name.clone())
-                .unwrap_or("Unknown Key".to_string())
+        // Any Claude login delegated to this key
+        let pinned_key_id = self
+            .spider_api_keys
+            .iter()
+            .find(|k| k.key == request.api_key)
+            .and_then(|k| k.oauth_key_id.clone());
+        let scope = RunScope {
+            read_conversations: self
+                .validate_permission(&request.api_key, Permission::ConversationsRead),
+            ..RunScope::default()
         };
 
-        println!(
-            "Spider: Starting new conversation {} with provider {} (key: {})",
-            conversation_id, llm_provider, key_name
+        self.run_chat(request, job_id, &actor, pinned_key_id, Some(scope))
+            .await
+    }
+
+    // Agentic chat loop for an already-authorized caller; `actor` is used for logs and the audit trail.
+    // `scope` is set for runs started by the delegate tool.
+    async fn run_chat(
+        &mut self,
+        request: ChatRequest,
+        job_id: Option<&str>,
+        actor: &str,
+        pinned_key_id: Option<String>,
+        scope: Option<RunScope>,
+    ) -> Result<ChatResponse, String> {
+        // New conversations get their id up front so watchers can follow the run from the start
+        let conversation_id = request
+            .conversation_id
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        self.broadcast_conversation_event(
+            &conversation_id,
+            &WsServerMessage::Status {
+                status: "processing".to_string(),
+                message: None,
+            },
         );
+        // Watchers did not see the prompt being sent, so they get the new messages too
+        for message in &request.messages {
+            self.broadcast_conversation_event(
+                &conversation_id,
+                &WsServerMessage::Message {
+                    message: message.clone(),
+                },
+            );
+        }
 
-        // Get the API key for the selected provider
-        let api_key = if is_oauth_token(&request.api_key) {
-            // OAuth token - use it directly as the API key
-            if llm_provider != "anthropic" && llm_provider != "anthropic-oauth" {
-                return Err(format!(
-                    "OAuth token can only be used with Anthropic provider, not {}",
-                    llm_provider
-                ));
-            }
-            request.api_key.clone()
-        } else {
-            // Regular Spider key - look up the provider's API key
-            // For Anthropic, prefer OAuth token if available
-            if llm_provider == "anthropic" {
-                // First check for anthropic-oauth key (OAuth tokens stored as API keys)
-                if let Some((_, oauth_key)) =
-                    self.api_keys.iter().find(|(p, _)| p == "anthropic-oauth")
-                {
-                    let decrypted = decrypt_key(&oauth_key.key);
-                    // If it's an OAuth token, use it
-                    if is_oauth_token(&decrypted) {
-                        decrypted
-                    } else {
-                        // Fall back to regular anthropic key if exists
-                        self.api_keys
-                            .iter()
-                            .find(|(p, _)| p == "anthropic")
-                            .map(|(_, k)| decrypt_key(&k.key))
-                            .ok_or_else(|| {
-                                format!("No API key found for provider: {}", llm_provider)
-                            })?
-                    }
-                } else {
-                    // No OAuth, try regular anthropic key
-                    self.api_keys
-                        .iter()
-                        .find(|(p, _)| p == "anthropic")
-                        .map(|(_, k)| decrypt_key(&k.key))
-                        .ok_or_else(|| format!("No API key found for provider: {}", llm_provider))?
-                }
-            } else {
-                // Non-Anthropic provider, use regular lookup
-                let encrypted_key = self
-                    .api_keys
+        let result = self
+            .run_agent_loop(
+                request,
+                conversation_id.clone(),
+                job_id,
+                actor,
+                pinned_key_id,
+                scope,
+            )
+            .await;
+
+        let final_event = match &result {
+            Ok(response) => WsServerMessage::ChatComplete {
+                payload: response.clone(),
+            },
+            Err(e) => WsServerMessage::Error { error: e.clone() },
+        };
+        self.broadcast_conversation_event(&conversation_id, &final_event);
+        result
+    }
+
+    async fn run_agent_loop(
+        &mut self,
+        request: ChatRequest,
+        conversation_id: String,
+        job_id: Option<&str>,
+        actor: &str,
+        pinned_key_id: Option<String>,
+        scope: Option<RunScope>,
+    ) -> Result<ChatResponse, String> {
+        let scope = RunScope {
+            cancel_job_id: job_id
+                .map(str::to_string)
+                .or(scope.as_ref().and_then(|s| s.cancel_job_id.clone())),
+            pinned_key_id: pinned_key_id.clone(),
+            ..scope.unwrap_or_default()
+        };
+
+        // A profile fills in whatever the request leaves unset
+        let profile = request
+            .profile_id
+            .as_deref()
+            .map(|id| {
+                self.agent_profiles
+                    .iter()
+                    .find(|p| p.id == id)
+                    .cloned()
+                    .ok_or_else(|| format!("Profile {} not found", id))
+            })
+            .transpose()?;
+        let mut request = request;
+        if let Some(profile) = &profile {
+            request.llm_provider = request
+                .llm_provider
+                .or_else(|| profile.llm_provider.clone());
+            request.model = request.model.or_else(|| profile.model.clone());
+            request.mcp_servers = request.mcp_servers.or_else(|| profile.mcp_servers.clone());
+        }
+
+        // A continued conversation resumes from the end of its active branch
+        let existing = match request.conversation_id.as_deref() {
+            Some(id) => {
+                let mut conversation = self
+                    .active_conversations
                     .iter()
-                    .find(|(p, _)| p == &llm_provider)
-                    .map(|(_, k)| k.key.clone())
-                    .ok_or_else(|| format!("No API key found for provider: {}", llm_provider))?;
-                decrypt_key(&encrypted_key)
+                    .find(|(conv_id, _)| conv_id == id)
+                    .map(|(_, conv)| conv.clone())
+                    .ok_or_else(|| format!("Conversation {} not found", id))?;
+                if conversation.deleted_at.is_some() {
+                    return Err(format!("Conversation {} is in the trash", id));
+                }
+                branches::ensure_tree(&mut conversation);
+                Some(conversation)
             }
+            None => None,
         };
+        let history: Vec<Message> = existing
+            .as_ref()
+            .map(|conv| conv.messages.clone())
+            .unwrap_or_default();
+        let llm_provider = request
+            .llm_provider
+            .unwrap_or(self.default_llm_provider.clone());
+        if !KNOWN_PROVIDERS.contains(&llm_provider.as_str()) {
+            return Err(format!(
+                "Unknown LLM provider: {} (expected one of: {})",
+                llm_provider,
+                KNOWN_PROVIDERS.join(", ")
+            ));
+        }
 
-        // Collect available tools from connected MCP servers
-        let available_tools: Vec<Tool> = if let Some(ref mcp_server_ids) = request.mcp_servers {
-            self.mcp_servers
-                .iter()
-                .filter(|s| s.connected && mcp_server_ids.contains(&s.id))
-                .flat_map(|s| s.tools.clone())
-                .collect()
-        } else {
-            // Use all connected servers if none specified
-            self.mcp_servers
-                .iter()
-                .filter(|s| s.connected)
-                .flat_map(|s| s.tools.clone())
-                .collect()
+        println!(
+            "Spider: {} conversation {} with provider {} (actor: {})",
+            if existing.is_some() {
+                "Continuing"
+            } else {
+                "Starting new"
+            },
+            conversation_id,
+            llm_provider,
+            actor
+        );
+
+        if pinned_key_id.is_some() && provider_family(&llm_provider) != "anthropic" {
+            return Err(format!(
+                "Keys linked to a Claude login can only be used with Anthropic, not {}",
+                llm_provider
+            ));
+        }
+
+        // Collect available tools from the requested connected MCP servers (all of them if none
+        // were named), noting which server offered each; where names clash, calls go to the first
+        let mut available_tools: Vec<Tool> = Vec::new();
+        let mut tool_servers: HashMap<String, String> = HashMap::new(); // tool name -> server id
+        for server in self.mcp_servers.iter().filter(|s| {
+            s.connected
+                && request
+                    .mcp_servers
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&s.id))
+        }) {
+            for tool in &server.tools {
+                tool_servers
+                    .entry(tool.name.clone())
+                    .or_insert_with(|| server.id.clone());
+                available_tools.push(tool.clone());
+            }
+        }
+        // Built-in tools are enabled by the profile. A delegated run without one keeps its
+        // parent's, as its scope only allows the parent's tools.
+        let mut builtins: Vec<String> = match (&profile, &scope.tools) {
+            (Some(profile), _) => profile.builtin_tools.clone(),
+            (None, Some(_)) => builtin_tools::NAMES.map(str::to_string).to_vec(),
+            (None, None) => Vec::new(),
         };
+        // Searching conversations reads them, so only callers who may read them get the tool
+        if !scope.read_conversations {
+            builtins.retain(|name| name != builtin_tools::SEARCH_CONVERSATIONS);
+        }
+        builtin_tools::add_enabled(&mut available_tools, &builtins);
+        delegation::scope_tools(&mut available_tools, &scope, &self.agent_profiles);
 
         // Start the agentic loop - runs indefinitely until the agent stops making tool calls
-        let mut working_messages = request.messages.clone();
+        let mut working_messages = history.clone();
+        working_messages.extend(request.messages.iter().cloned());
+        let initial_message_count = working_messages.len();
         let mut iteration_count = 0;
+        // Rolling summary of compacted history: (messages covered, summary text)
+        let mut compaction_summary: Option<(usize, String)> = None;
 
         let response = loop {
             iteration_count += 1;
 
             // Check for cancellation
-            if let Some(ch_id) = channel_id {
-                if let Some(cancel_flag) = self.active_chat_cancellation.get(&ch_id) {
-                    let is_cancelled = cancel_flag.load(Ordering::Relaxed);
-                    if is_cancelled {
-                        println!(
-                            "Spider: Chat request cancelled at iteration {}",
-                            iteration_count
-                        );
-                        return Err("Request cancelled by user".to_string());
-                    }
+            if let Some(cancel_flag) = scope
+                .cancel_job_id
+                .as_ref()
+                .and_then(|id| self.active_chat_cancellation.get(id))
+            {
+                let is_cancelled = cancel_flag.load(Ordering::Relaxed);
+                if is_cancelled {
+                    println!(
+                        "Spider: Chat request cancelled at iteration {}",
+                        iteration_count
+                    );
+                    return Err("Request cancelled by user".to_string());
                 }
-
-                // Send streaming update
-                let stream_msg = WsServerMessage::Stream {
-                    iteration: iteration_count,
-                    message: format!("Processing iteration {}...", iteration_count),
-                    tool_calls: None,
-                };
-                let json = serde_json::to_string(&stream_msg).unwrap();
-                send_ws_push(
-                    ch_id,
-                    WsMessageType::Text,
-                    LazyLoadBlob::new(Some("application/json"), json),
-                );
             }
 
-            // Call the LLM with available tools using the provider abstraction
-            let provider = create_llm_provider(&llm_provider, &api_key);
-            let llm_response = match provider
-                .complete(
+            // Send streaming update
+            let stream_msg = WsServerMessage::Stream {
+                iteration: iteration_count,
+                message: format!("Processing iteration {}...", iteration_count),
+                tool_calls: None,
+            };
+            self.emit_chat_event(job_id, &conversation_id, &stream_msg);
+
+            // A run out of budget gets no tools on its last iteration, so it has to answer
+            let final_iteration = scope
+                .max_iterations
+                .is_some_and(|max| iteration_count >= max);
+            let tools: &[Tool] = if final_iteration {
+                &[]
+            } else {
+                &available_tools
+            };
+
+            // Fit the history into the context window; working_messages keeps the full record
+            let context_messages = self
+                .prepare_context(
                     &working_messages,
-                    &available_tools,
+                    tools,
+                    &llm_provider,
+                    pinned_key_id.as_deref(),
                     request.model.as_deref(),
-                    self.max_tokens,
-                    self.temperature,
-                )
-                .await
-            {
-                Ok(response) => response,
-                Err(e) => {
-                    // Log the error for debugging
-                    println!("Spider: Error calling LLM provider {}: {}", llm_provider, e);
-
-                    // Check if it's an API key error
-                    if e.contains("401") || e.contains("unauthorized") || e.contains("api key") {
-                        return Err(format!(
-                            "Authentication failed for {}: Please check your API key",
-                            llm_provider
-                        ));
-                    }
-
-                    // Check if it's a rate limit error
-                    if e.contains("429") || e.contains("rate limit") {
-                        return Err(format!(
-                            "Rate limited by {}: Please try again later",
-                            llm_provider
-                        ));
-                    }
-
-                    // Return user-friendly error message
-                    return Err(format!(
-                        "Failed to get response from {}: {}",
-                        llm_provider, e
-                    ));
-                }
-            };
+                    &mut compaction_summary,
+                )
+                .await;
+
+            // Call the LLM with available tools, falling back across keys and providers
+            let llm_response = self
+                .complete_with_fallback(
+                    &llm_provider,
+                    pinned_key_id.as_deref(),
+                    &context_messages,
+                    tools,
+                    request.model.as_deref(),
+                )
+                .await?;
 
             // Check if the response contains tool calls
-            if let Some(ref tool_calls_json) = llm_response.tool_calls_json {
+            if let Some(tool_calls_json) = llm_response
+                .tool_calls_json
+                .as_ref()
+                .filter(|_| !final_iteration)
+            {
                 // The agent wants to use tools - execute them
                 println!(
                     "Spider: Iteration {} - Agent requested tool calls",
@@ -1549,39 +4413,32 @@ impl SpiderState {
                 );
 
                 // Send streaming update for tool calls
-                if let Some(ch_id) = channel_id {
-                    let stream_msg = WsServerMessage::Stream {
-                        iteration: iteration_count,
-                        message: "Executing tool calls...".to_string(),
-                        tool_calls: Some(tool_calls_json.clone()),
-                    };
-                    let json = serde_json::to_string(&stream_msg).unwrap();
-                    send_ws_push(
-                        ch_id,
-                        WsMessageType::Text,
-                        LazyLoadBlob::new(Some("application/json"), json),
-                    );
-                }
+                let stream_msg = WsServerMessage::Stream {
+                    iteration: iteration_count,
+                    message: "Executing tool calls...".to_string(),
+                    tool_calls: Some(tool_calls_json.clone()),
+                };
+                self.emit_chat_event(job_id, &conversation_id, &stream_msg);
 
                 let tool_results = self
-                    .process_tool_calls(tool_calls_json, Some(conversation_id.clone()))
+                    .process_tool_calls(
+                        actor,
+                        tool_calls_json,
+                        Some(conversation_id.clone()),
+                        &available_tools,
+                        &tool_servers,
+                        &scope,
+                    )
                     .await?;
 
                 // Add the assistant's message with tool calls
                 working_messages.push(llm_response.clone());
 
                 // Send the assistant message with tool calls to the client
-                if let Some(ch_id) = channel_id {
-                    let msg_update = WsServerMessage::Message {
-                        message: llm_response.clone(),
-                    };
-                    let json = serde_json::to_string(&msg_update).unwrap();
-                    send_ws_push(
-                        ch_id,
-                        WsMessageType::Text,
-                        LazyLoadBlob::new(Some("application/json"), json),
-                    );
-                }
+                let msg_update = WsServerMessage::Message {
+                    message: llm_response.clone(),
+                };
+                self.emit_chat_event(job_id, &conversation_id, &msg_update);
 
                 // Add tool results as a new message for the LLM to see
                 let tool_message = Message {
@@ -1594,17 +4451,10 @@ impl SpiderState {
                 working_messages.push(tool_message.clone());
 
                 // Send the tool results message to the client
-                if let Some(ch_id) = channel_id {
-                    let msg_update = WsServerMessage::Message {
-                        message: tool_message.clone(),
-                    };
-                    let json = serde_json::to_string(&msg_update).unwrap();
-                    send_ws_push(
-                        ch_id,
-                        WsMessageType::Text,
-                        LazyLoadBlob::new(Some("application/json"), json),
-                    );
-                }
+                let msg_update = WsServerMessage::Message {
+                    message: tool_message.clone(),
+                };
+                self.emit_chat_event(job_id, &conversation_id, &msg_update);
 
                 // Continue the loop - the agent will decide what to do next
                 continue;
@@ -1617,17 +4467,10 @@ impl SpiderState {
                 );
 
                 // Send the final assistant message to the client
-                if let Some(ch_id) = channel_id {
-                    let msg_update = WsServerMessage::Message {
-                        message: llm_response.clone(),
-                    };
-                    let json = serde_json::to_string(&msg_update).unwrap();
-                    send_ws_push(
-                        ch_id,
-                        WsMessageType::Text,
-                        LazyLoadBlob::new(Some("application/json"), json),
-                    );
-                }
+                let msg_update = WsServerMessage::Message {
+                    message: llm_response.clone(),
+                };
+                self.emit_chat_event(job_id, &conversation_id, &msg_update);
 
                 break llm_response;
             }
@@ -1644,7 +4487,6 @@ impl SpiderState {
 
         // Get only the new messages that were added during this chat session
         // (everything after the initial user messages)
-        let initial_message_count = request.messages.len();
         let new_messages = working_messages[initial_message_count..].to_vec();
 
         // Gather MCP server details for the conversation
@@ -1670,9 +4512,9 @@ impl SpiderState {
             })
             .collect();
 
-        let conversation = Conversation {
+        let mut conversation = existing.unwrap_or(Conversation {
             id: conversation_id.clone(),
-            messages: working_messages,
+            messages: Vec::new(),
             metadata,
             llm_provider,
             mcp_servers: mcp_server_ids,
@@ -1681,7 +4523,20 @@ impl SpiderState {
             } else {
                 Some(mcp_servers_details)
             },
-        };
+            title: None,
+            tags: Vec::new(),
+            deleted_at: None,
+            summary: None,
+            summary_covers: 0,
+            nodes: Vec::new(),
+            active_leaf: None,
+            parent_conversation_id: scope.parent_conversation_id.clone(),
+            child_conversation_ids: Vec::new(),
+        });
+        branches::append(&mut conversation, &working_messages[history.len()..]);
+        if let Some(children) = self.delegated_conversations.remove(&conversation_id) {
+            conversation.child_conversation_ids.extend(children);
+        }
 
         // Save to VFS
         if let Err(e) = save_conversation_to_vfs(&conversation).await {
@@ -1689,8 +4544,16 @@ impl SpiderState {
         }
 
         // Keep in memory for quick access
+        self.active_conversations
+            .retain(|(id, _)| id != &conversation_id);
         self.active_conversations
             .push((conversation_id.clone(), conversation));
+        self.purge_expired_conversations().await;
+
+        // Titling runs after the response so it never delays the chat
+        if self.summary_config.enabled {
+            self.schedule_conversation_summary(&conversation_id);
+        }
 
         Ok(ChatResponse {
             conversation_id,
@@ -1699,6 +4562,467 @@ impl SpiderState {
         })
     }
 
+    // Replace the stored Claude login with freshly issued tokens
+    fn store_oauth_tokens(&mut self, tokens: OAuthTokenResponse) {
+        let now = Utc::now().timestamp() as u64;
+        let api_key = ApiKey {
+            id: Uuid::new_v4().to_string(),
+            provider: "anthropic-oauth".to_string(),
+            key: encrypt_key(&tokens.access),
+            created_at: now,
+            last_used: None,
+            usage_count: 0,
+            last_error: None,
+            refresh_token: if tokens.refresh.is_empty() {
+                None
+            } else {
+                Some(encrypt_key(&tokens.refresh))
+            },
+            expires_at: Some(tokens.expires),
+            last_refreshed: Some(now),
+            delegated: false,
+        };
+
+        self.api_keys
+            .retain(|(p, k)| p != "anthropic-oauth" || k.delegated);
+        self.api_keys.push(("anthropic-oauth".to_string(), api_key));
+    }
+
+    // Store tokens as a delegated login and link it to a Spider key, replacing any previous link
+    fn link_oauth_tokens(
+        &mut self,
+        spider_key: &str,
+        tokens: OAuthTokenResponse,
+    ) -> Result<String, String> {
+        let now = Utc::now().timestamp() as u64;
+        let login_id = Uuid::new_v4().to_string();
+
+        let key = self
+            .spider_api_keys
+            .iter_mut()
+            .find(|k| k.key == spider_key)
+            .ok_or_else(|| "Unauthorized: Invalid API key".to_string())?;
+        let previous = key.oauth_key_id.replace(login_id.clone());
+        if let Some(previous) = previous {
+            self.api_keys.retain(|(_, k)| k.id != previous);
+        }
+
+        self.api_keys.push((
+            "anthropic-oauth".to_string(),
+            ApiKey {
+                id: login_id.clone(),
+                provider: "anthropic-oauth".to_string(),
+                key: encrypt_key(&tokens.access),
+                created_at: now,
+                last_used: None,
+                usage_count: 0,
+                last_error: None,
+                refresh_token: if tokens.refresh.is_empty() {
+                    None
+                } else {
+                    Some(encrypt_key(&tokens.refresh))
+                },
+                expires_at: Some(tokens.expires),
+                last_refreshed: Some(now),
+                delegated: true,
+            },
+        ));
+
+        Ok(login_id)
+    }
+
+    // Refresh a stored OAuth key in place, returning the new access token
+    async fn refresh_stored_oauth_token(&mut self, key_id: &str) -> Result<String, String> {
+        let refresh_token = self
+            .api_keys
+            .iter()
+            .find(|(_, k)| k.id == key_id)
+            .and_then(|(_, k)| k.refresh_token.as_ref())
+            .map(|t| decrypt_key(t))
+            .ok_or_else(|| "Claude login has no refresh token; please log in again".to_string())?;
+
+        println!("Spider: Refreshing Claude OAuth token {}", key_id);
+        let result = oauth::refresh_tokens(&refresh_token).await;
+
+        // The key may have been removed while the refresh was in flight
+        let (_, key) = self
+            .api_keys
+            .iter_mut()
+            .find(|(_, k)| k.id == key_id)
+            .ok_or_else(|| "Claude login was removed during refresh".to_string())?;
+
+        match result {
+            Ok(tokens) => {
+                key.key = encrypt_key(&tokens.access);
+                if !tokens.refresh.is_empty() {
+                    key.refresh_token = Some(encrypt_key(&tokens.refresh));
+                }
+                key.expires_at = Some(tokens.expires);
+                key.last_refreshed = Some(Utc::now().timestamp() as u64);
+                key.last_error = None;
+                Ok(tokens.access)
+            }
+            Err(e) => {
+                println!("Spider: Failed to refresh Claude OAuth token: {}", e);
+                key.last_error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    // Proactively refresh OAuth keys that are about to expire
+    async fn ensure_oauth_tokens_fresh(&mut self) {
+        let now = Utc::now().timestamp() as u64;
+        let expiring: Vec<String> = self
+            .api_keys
+            .iter()
+            .filter(|(p, k)| {
+                p == "anthropic-oauth"
+                    && k.refresh_token.is_some()
+                    && k.expires_at
+                        .is_some_and(|exp| exp <= now + oauth::REFRESH_MARGIN_SECS)
+            })
+            .map(|(_, k)| k.id.clone())
+            .collect();
+
+        for key_id in expiring {
+            let _ = self.refresh_stored_oauth_token(&key_id).await;
+        }
+    }
+
+    // Health of the shared (non-delegated) Claude login
+    fn oauth_token_status(&self) -> OAuthTokenStatus {
+        let login_id = self
+            .api_keys
+            .iter()
+            .find(|(p, k)| p == "anthropic-oauth" && !k.delegated)
+            .map(|(_, k)| k.id.clone())
+            .unwrap_or_default();
+        self.oauth_key_status(&login_id)
+    }
+
+    fn oauth_key_status(&self, key_id: &str) -> OAuthTokenStatus {
+        let Some((_, key)) = self.api_keys.iter().find(|(_, k)| k.id == key_id) else {
+            return OAuthTokenStatus {
+                configured: false,
+                healthy: false,
+                expires_at: None,
+                expires_in_secs: None,
+                has_refresh_token: false,
+                last_refreshed: None,
+                last_error: None,
+            };
+        };
+
+        let now = Utc::now().timestamp();
+        let expires_in_secs = key.expires_at.map(|exp| exp as i64 - now);
+        let has_refresh_token = key.refresh_token.is_some();
+        let unexpired = expires_in_secs.is_none_or(|secs| secs > 0);
+
+        OAuthTokenStatus {
+            configured: true,
+            healthy: (unexpired || has_refresh_token) && key.last_error.is_none(),
+            expires_at: key.expires_at,
+            expires_in_secs,
+            has_refresh_token,
+            last_refreshed: key.last_refreshed,
+            last_error: key.last_error.clone(),
+        }
+    }
+
+    // Providers tried for a chat, primary first. Without a configured chain, Anthropic prefers a
+    // stored OAuth login and falls back to a regular API key.
+    fn fallback_chain_for(&self, llm_provider: &str) -> Vec<String> {
+        let mut chain = vec![llm_provider.to_string()];
+        let configured = self
+            .fallback_chains
+            .iter()
+            .find(|(primary, _)| primary == llm_provider)
+            .map(|(_, chain)| chain.clone());
+
+        match configured {
+            Some(fallbacks) => chain.extend(fallbacks),
+            None if llm_provider == "anthropic" => {
+                chain.insert(0, "anthropic-oauth".to_string());
+            }
+            None => {}
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        chain.retain(|p| seen.insert(p.clone()));
+        chain
+    }
+
+    // Pooled keys for a provider as (key id, decrypted key), ordered by the selection strategy
+    fn provider_key_candidates(&mut self, llm_provider: &str) -> Vec<(String, String)> {
+        let mut pool: Vec<&ApiKey> = self
+            .api_keys
+            .iter()
+            .filter(|(p, k)| p == llm_provider && !k.delegated)
+            .map(|(_, k)| k)
+            .collect();
+
+        if self.key_selection == "least-used" {
+            pool.sort_by_key(|k| (k.usage_count, k.last_used.unwrap_or(0)));
+        } else if !pool.is_empty() {
+            // Round-robin: rotate the pool so each request starts at the next key
+            let cursor = self
+                .key_cursors
+                .entry(llm_provider.to_string())
+                .or_insert(0);
+            let start = *cursor % pool.len();
+            *cursor = start + 1;
+            pool.rotate_left(start);
+        }
+
+        pool.into_iter()
+            .map(|k| (k.id.clone(), decrypt_key(&k.key)))
+            .collect()
+    }
+
+    fn record_key_usage(&mut self, key_id: &str, error: Option<&str>) {
+        if let Some((_, key)) = self.api_keys.iter_mut().find(|(_, k)| k.id == key_id) {
+            key.usage_count += 1;
+            key.last_used = Some(Utc::now().timestamp() as u64);
+            key.last_error = error.map(|e| e.to_string());
+        }
+    }
+
+    // Call the LLM, trying each pooled key of each provider in the fallback chain.
+    // Only rate-limit, overload and auth errors move on to the next candidate.
+    async fn complete_with_fallback(
+        &mut self,
+        llm_provider: &str,
+        pinned_key_id: Option<&str>,
+        messages: &[Message],
+        tools: &[Tool],
+        model: Option<&str>,
+    ) -> Result<Message, String> {
+        if pinned_key_id.is_some()
+            || self
+                .fallback_chain_for(llm_provider)
+                .iter()
+                .any(|p| p == "anthropic-oauth")
+        {
+            self.ensure_oauth_tokens_fresh().await;
+        }
+
+        // A delegated Claude login is used exclusively, never mixed with the shared pool
+        let candidates: Vec<(String, Option<String>, String)> = match pinned_key_id {
+            Some(key_id) => self
+                .api_keys
+                .iter()
+                .find(|(_, k)| k.id == key_id)
+                .map(|(_, k)| {
+                    vec![(
+                        "anthropic-oauth".to_string(),
+                        Some(key_id.to_string()),
+                        decrypt_key(&k.key),
+                    )]
+                })
+                .ok_or_else(|| {
+                    "The Claude login linked to this key was removed; please link it again"
+                        .to_string()
+                })?,
+            None => {
+                let mut candidates = Vec::new();
+                for provider_name in self.fallback_chain_for(llm_provider) {
+                    for (key_id, key) in self.provider_key_candidates(&provider_name) {
+                        candidates.push((provider_name.clone(), Some(key_id), key));
+                    }
+                }
+                candidates
+            }
+        };
+
+        if candidates.is_empty() {
+            return Err(format!("No API key found for provider: {}", llm_provider));
+        }
+
+        let mut last_failure: Option<(String, String, ProviderErrorKind)> = None;
+        'candidates: for (provider_name, key_id, mut key) in candidates {
+            let mut refreshed = false;
+            loop {
+                let provider = create_llm_provider(&provider_name, &key)?;
+
+                // A requested model only makes sense within the same provider family
+                let candidate_model =
+                    if provider_family(&provider_name) == provider_family(llm_provider) {
+                        model
+                    } else {
+                        None
+                    };
+
+                let error = match provider
+                    .complete(
+                        messages,
+                        tools,
+                        candidate_model,
+                        self.max_tokens,
+                        self.temperature,
+                    )
+                    .await
+                {
+                    Ok(response) => {
+                        if let Some(ref key_id) = key_id {
+                            self.record_key_usage(key_id, None);
+                        }
+                        return Ok(response);
+                    }
+                    Err(e) => e,
+                };
+
+                println!(
+                    "Spider: Error calling LLM provider {}: {}",
+                    provider_name, error
+                );
+                let kind = error.kind();
+                let error = error.to_string();
+                if let Some(ref key_id) = key_id {
+                    self.record_key_usage(key_id, Some(&error));
+                }
+
+                // An expired OAuth access token is recoverable: refresh once and retry
+                if kind == ProviderErrorKind::Auth
+                    && provider_name == "anthropic-oauth"
+                    && !refreshed
+                {
+                    if let Some(ref key_id) = key_id {
+                        if let Ok(new_key) = self.refresh_stored_oauth_token(key_id).await {
+                            key = new_key;
+                            refreshed = true;
+                            continue;
+                        }
+                    }
+                }
+
+                last_failure = Some((provider_name.clone(), error, kind));
+                if !kind.should_fall_back() {
+                    break 'candidates;
+                }
+                println!("Spider: Falling back to next key/provider ({:?})", kind);
+                break;
+            }
+        }
+
+        // Report the last failure in user-friendly terms
+        let (provider_name, e, kind) = last_failure.unwrap();
+        Err(match kind {
+            ProviderErrorKind::Auth => format!(
+                "Authentication failed for {}: Please check your API key",
+                provider_name
+            ),
+            ProviderErrorKind::RateLimited => {
+                format!("Rate limited by {}: Please try again later", provider_name)
+            }
+            ProviderErrorKind::Overloaded => {
+                format!("{} is overloaded: Please try again later", provider_name)
+            }
+            ProviderErrorKind::Other => {
+                format!("Failed to get response from {}: {}", provider_name, e)
+            }
+        })
+    }
+
+    // Fit the history into the context window according to the configured compaction strategy.
+    // Only the copy sent to the model is compacted; the saved conversation keeps every message.
+    async fn prepare_context(
+        &mut self,
+        messages: &[Message],
+        tools: &[Tool],
+        llm_provider: &str,
+        pinned_key_id: Option<&str>,
+        model: Option<&str>,
+        summary_cache: &mut Option<(usize, String)>,
+    ) -> Vec<Message> {
+        let config = self.context_config.clone();
+        let budget = context::history_budget(&config, self.max_tokens, tools);
+        let total = context::estimate_total_tokens(messages);
+        if total <= budget || config.compaction_strategy == context::STRATEGY_NONE {
+            return messages.to_vec();
+        }
+
+        println!(
+            "Spider: History is ~{} tokens, over the {} token budget; compacting ({})",
+            total, budget, config.compaction_strategy
+        );
+
+        let keep_recent = config.keep_recent_messages as usize;
+        let split = context::recent_window_start(messages, keep_recent);
+
+        let mut compacted =
+            if config.compaction_strategy == context::STRATEGY_SUMMARIZE && split > 0 {
+                match self
+                    .summarize_history(
+                        messages,
+                        split,
+                        llm_provider,
+                        pinned_key_id,
+                        model,
+                        summary_cache,
+                    )
+                    .await
+                {
+                    Ok(summary) => context::with_summary(&summary, &messages[split..]),
+                    Err(e) => {
+                        println!(
+                            "Spider: Failed to summarize history, falling back to truncation: {}",
+                            e
+                        );
+                        messages.to_vec()
+                    }
+                }
+            } else {
+                messages.to_vec()
+            };
+
+        // Truncation also backs up summarization when the summary alone isn't enough
+        let protect_from = context::recent_window_start(&compacted, keep_recent);
+        if !context::truncate_old_tool_results(
+            &mut compacted,
+            protect_from,
+            budget,
+            config.tool_output_preview_chars as usize,
+        ) {
+            context::drop_oldest_messages(&mut compacted, keep_recent, budget);
+        }
+
+        compacted
+    }
+
+    // Summarize messages[..split] with the chat's own provider, rolling forward any earlier summary
+    async fn summarize_history(
+        &mut self,
+        messages: &[Message],
+        split: usize,
+        llm_provider: &str,
+        pinned_key_id: Option<&str>,
+        model: Option<&str>,
+        summary_cache: &mut Option<(usize, String)>,
+    ) -> Result<String, String> {
+        let (previous, from) = match summary_cache.as_ref() {
+            Some((covered, summary)) if *covered <= split => (Some(summary.clone()), *covered),
+            _ => (None, 0),
+        };
+        if from == split {
+            if let Some(summary) = previous {
+                return Ok(summary);
+            }
+        }
+
+        let summary_request = context::build_summary_request(
+            previous.as_deref(),
+            &messages[from..split],
+            self.context_config.tool_output_preview_chars as usize,
+        );
+        let response = self
+            .complete_with_fallback(llm_provider, pinned_key_id, &summary_request, &[], model)
+            .await?;
+
+        *summary_cache = Some((split, response.content.clone()));
+        Ok(response.content)
+    }
+
     fn handle_mcp_message(&mut self, channel_id: u32, message: Value) {
         // Find the connection for this channel
         let conn = match self.ws_connections.get(&channel_id) {
@@ -1714,7 +5038,11 @@ impl SpiderState {
 
         // Check if this is a response to a pending request
         if let Some(id) = message.get("id").and_then(|v| v.as_str()) {
-            if let Some(pending) = self.pending_mcp_requests.remove(id) {
+            let pending = self
+                .ws_connections
+                .get_mut(&channel_id)
+                .and_then(|session| session.pending.remove(id));
+            if let Some(pending) = pending {
                 match pending.request_type {
                     McpRequestType::Initialize => {
                         self.handle_initialize_response(channel_id, &conn, &message);
@@ -1725,6 +5053,21 @@ impl SpiderState {
                     McpRequestType::ToolCall { tool_name: _ } => {
                         self.handle_tool_call_response(&pending, &message);
                     }
+                    McpRequestType::Ping => {
+                        // Any answer, even an error from servers without ping, proves liveness
+                        let health = self.mcp_health.entry(conn.server_id.clone()).or_default();
+                        health.outstanding_ping = None;
+                        health.missed_pings = 0;
+                        if self.mcp_servers.iter().any(|s| {
+                            s.id == conn.server_id && s.state == McpConnectionState::Degraded
+                        }) {
+                            self.set_mcp_server_state(
+                                &conn.server_id,
+                                McpConnectionState::Ready,
+                                None,
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -1779,6 +5122,13 @@ impl SpiderState {
                 "Spider: Failed to initialize MCP server {}: {:?}",
                 conn.server_name, error
             );
+            self.drop_mcp_connection(&conn.server_id);
+            self.set_mcp_server_state(
+                &conn.server_id,
+                McpConnectionState::Failed,
+                Some(format!("Initialization failed: {}", error)),
+            );
+            self.schedule_mcp_reconnect(&conn.server_id);
         }
     }
 
@@ -1792,13 +5142,14 @@ impl SpiderState {
         };
 
         // Store pending request
-        if let Some(conn) = self.ws_connections.get(&channel_id) {
-            self.pending_mcp_requests.insert(
+        if let Some(session) = self.ws_connections.get_mut(&channel_id) {
+            let server_id = session.server_id.clone();
+            session.pending.insert(
                 request_id.clone(),
                 PendingMcpRequest {
                     request_id,
                     conversation_id: None,
-                    server_id: conn.server_id.clone(),
+                    server_id,
                     request_type: McpRequestType::ToolsList,
                 },
             );
@@ -1836,12 +5187,16 @@ impl SpiderState {
                         let input_schema_json = tool_json
                             .get("inputSchema")
                             .map(|schema| schema.to_string());
+                        let output_schema_json = tool_json
+                            .get("outputSchema")
+                            .map(|schema| schema.to_string());
 
                         tools.push(Tool {
                             name: name.to_string(),
                             description: description.to_string(),
                             parameters,
                             input_schema_json,
+                            output_schema_json,
                         });
                     }
                 }
@@ -1860,8 +5215,12 @@ impl SpiderState {
                 // Update server with tools and mark as connected
                 if let Some(server) = self.mcp_servers.iter_mut().find(|s| s.id == conn.server_id) {
                     server.tools = tools;
-                    server.connected = true;
                 }
+                if let Some(health) = self.mcp_health.get_mut(&conn.server_id) {
+                    health.reconnect_attempts = 0;
+                    health.connecting_since = None;
+                }
+                self.set_mcp_server_state(&conn.server_id, McpConnectionState::Ready, None);
             }
         } else if let Some(error) = message.get("error") {
             println!(
@@ -1877,24 +5236,65 @@ impl SpiderState {
             pending.request_id, message
         );
 
-        // Store the response so execute_mcp_tool can retrieve it
+        // Store the response so execute_mcp_tool can retrieve it. JSON-RPC errors mean the
+        // call itself failed; tool failures arrive as results with isError set.
         let result = if let Some(result_value) = message.get("result") {
-            result_value.clone()
+            Ok(result_value.clone())
         } else if let Some(error) = message.get("error") {
-            serde_json::json!({
-                "error": error
-            })
+            Err(error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| error.to_string()))
         } else {
-            serde_json::json!({
-                "error": "Invalid MCP response format"
-            })
+            Err("Invalid MCP response format".to_string())
         };
 
         self.tool_responses
             .insert(pending.request_id.clone(), result);
     }
 
+    // Every tool invocation is audited against the Spider key (or process) that triggered it
     async fn execute_mcp_tool(
+        &mut self,
+        actor: &str,
+        server_id: &str,
+        tool_name: &str,
+        parameters: &Value,
+        conversation_id: Option<String>,
+    ) -> Result<McpCallToolResult, String> {
+        let result = self
+            .run_mcp_tool(server_id, tool_name, parameters, conversation_id)
+            .await
+            .map(mcp_server::parse_call_tool_result)
+            .and_then(|result| {
+                if let Some(tool) = self
+                    .mcp_servers
+                    .iter()
+                    .find(|s| s.id == server_id)
+                    .and_then(|s| s.tools.iter().find(|t| t.name == tool_name))
+                {
+                    validation::validate_tool_output(tool, &result)?;
+                }
+                Ok(result)
+            });
+
+        // Tool-reported errors count as failures too
+        let outcome = match &result {
+            Ok(result) if result.is_error => Err(mcp_server::result_text(result)),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.clone()),
+        };
+        self.record_audit(
+            actor,
+            "mcp_tool.call",
+            &format!("{}/{}", server_id, tool_name),
+            &outcome,
+        );
+        result
+    }
+
+    async fn run_mcp_tool(
         &mut self,
         server_id: &str,
         tool_name: &str,
@@ -2110,17 +5510,19 @@ impl SpiderState {
                 };
 
                 // Store pending request
-                self.pending_mcp_requests.insert(
-                    request_id.clone(),
-                    PendingMcpRequest {
-                        request_id: request_id.clone(),
-                        conversation_id: conversation_id.clone(),
-                        server_id: server_id.to_string(),
-                        request_type: McpRequestType::ToolCall {
-                            tool_name: tool_name.to_string(),
+                if let Some(session) = self.ws_connections.get_mut(&channel_id) {
+                    session.pending.insert(
+                        request_id.clone(),
+                        PendingMcpRequest {
+                            request_id: request_id.clone(),
+                            conversation_id: conversation_id.clone(),
+                            server_id: server_id.to_string(),
+                            request_type: McpRequestType::ToolCall {
+                                tool_name: tool_name.to_string(),
+                            },
                         },
-                    },
-                );
+                    );
+                }
 
                 // Send the tool call to MCP server
                 println!(
@@ -2138,25 +5540,17 @@ impl SpiderState {
                 let timeout = std::time::Duration::from_secs(60);
 
                 loop {
-                    // Check if we have a response
+                    // Check if we have a response (or the session closed under us)
                     if let Some(response) = self.tool_responses.remove(&request_id) {
-                        self.pending_mcp_requests.remove(&request_id);
-
-                        // Parse the MCP result
-                        if let Some(content) = response.get("content") {
-                            return Ok(serde_json::to_value(ToolExecutionResult {
-                                result: content.clone(),
-                                success: true,
-                            })
-                            .unwrap());
-                        } else {
-                            return Ok(response);
-                        }
+                        return response
+                            .map_err(|e| format!("Tool call {} failed: {}", tool_name, e));
                     }
 
                     // Check timeout
                     if start.elapsed() > timeout {
-                        self.pending_mcp_requests.remove(&request_id);
+                        if let Some(session) = self.ws_connections.get_mut(&channel_id) {
+                            session.pending.remove(&request_id);
+                        }
                         return Err(format!(
                             "Tool call {} timed out after 60 seconds",
                             tool_name
@@ -2168,6 +5562,14 @@ impl SpiderState {
                     let _ = hyperware_process_lib::hyperapp::sleep(100).await;
                 }
             }
+            "spider-remote" => {
+                let node = server
+                    .transport
+                    .remote_node
+                    .clone()
+                    .ok_or_else(|| "Spider-remote server has no remote node".to_string())?;
+                federation::call_remote_tool(&node, tool_name, parameters).await
+            }
             "http" => {
                 // Execute via HTTP
                 // This is a placeholder - actual implementation would make HTTP requests
@@ -2187,10 +5589,16 @@ impl SpiderState {
         }
     }
 
+    // `available_tools` are the tools the run was offered; calls to anything else are refused.
+    // MCP tools run on the server `tool_servers` says offered them.
     async fn process_tool_calls(
         &mut self,
+        actor: &str,
         tool_calls_json: &str,
         conversation_id: Option<String>,
+        available_tools: &[Tool],
+        tool_servers: &HashMap<String, String>,
+        scope: &RunScope,
     ) -> Result<Vec<ToolResult>, String> {
         let tool_calls: Vec<ToolCall> = serde_json::from_str(tool_calls_json)
             .map_err(|e| format!("Failed to parse tool calls: {}", e))?;
@@ -2198,44 +5606,198 @@ impl SpiderState {
         let mut results = Vec::new();
 
         for tool_call in tool_calls {
-            // Find which MCP server has this tool and get its ID
-            let server_id = self
-                .mcp_servers
+            let offered = available_tools
                 .iter()
-                .find(|s| s.connected && s.tools.iter().any(|t| t.name == tool_call.tool_name))
-                .map(|s| s.id.clone());
+                .find(|t| t.name == tool_call.tool_name);
+            let found = offered.and_then(|tool| {
+                tool_servers
+                    .get(&tool.name)
+                    .map(|server_id| (server_id.clone(), tool.clone()))
+            });
 
-            let result = if let Some(server_id) = server_id {
-                let params: Value = serde_json::from_str(&tool_call.parameters)
-                    .unwrap_or(Value::Object(serde_json::Map::new()));
-                match self
-                    .execute_mcp_tool(
-                        &server_id,
-                        &tool_call.tool_name,
-                        &params,
-                        conversation_id.clone(),
-                    )
+            let result = if let Some(tool) = offered.filter(|t| t.name == delegation::DELEGATE_TOOL)
+            {
+                match validation::parse_tool_arguments(&tool_call.parameters).and_then(|params| {
+                    validation::validate_tool_arguments(tool, &params).map(|_| params)
+                }) {
+                    Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    Ok(params) => match Box::pin(self.delegate(
+                        actor,
+                        params,
+                        conversation_id.as_deref().unwrap_or_default(),
+                        available_tools,
+                        scope,
+                    ))
                     .await
-                {
-                    Ok(res) => res.to_string(),
-                    Err(e) => format!(r#"{{"error":"{}"}}"#, e),
+                    {
+                        Ok(output) => (output, false),
+                        Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    },
+                }
+            } else if let Some(tool) = offered.filter(|t| builtin_tools::is_builtin(&t.name)) {
+                match validation::parse_tool_arguments(&tool_call.parameters).and_then(|params| {
+                    validation::validate_tool_arguments(tool, &params).map(|_| params)
+                }) {
+                    Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    Ok(params) => match self
+                        .execute_builtin_tool(actor, &tool.name, &params, scope)
+                        .await
+                    {
+                        Ok(output) => (output, false),
+                        Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    },
+                }
+            } else if offered.is_none() {
+                (
+                    serde_json::json!({
+                        "error": format!("Tool {} is not available in this conversation", tool_call.tool_name)
+                    })
+                    .to_string(),
+                    true,
+                )
+            } else if let Some((server_id, tool)) = found {
+                // Malformed calls go back to the model so it can correct them, rather than
+                // reaching the server
+                match validation::parse_tool_arguments(&tool_call.parameters).and_then(|params| {
+                    validation::validate_tool_arguments(&tool, &params).map(|_| params)
+                }) {
+                    Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    Ok(params) => match self
+                        .execute_mcp_tool(
+                            actor,
+                            &server_id,
+                            &tool_call.tool_name,
+                            &params,
+                            conversation_id.clone(),
+                        )
+                        .await
+                    {
+                        Ok(res) => (mcp_server::result_text(&res), res.is_error),
+                        Err(e) => (serde_json::json!({ "error": e }).to_string(), true),
+                    },
                 }
             } else {
-                format!(
-                    r#"{{"error":"Tool {} not found in any connected MCP server"}}"#,
-                    tool_call.tool_name
+                (
+                    format!(
+                        r#"{{"error":"Tool {} not found in any connected MCP server"}}"#,
+                        tool_call.tool_name
+                    ),
+                    true,
                 )
             };
+            let (result, is_error) = result;
+
+            // Keep large outputs out of the model's context: store them and send a preview
+            let result = match conversation_id {
+                Some(ref conv_id)
+                    if result.chars().count()
+                        > self.context_config.large_tool_output_chars as usize =>
+                {
+                    match save_tool_output_to_vfs(conv_id, &tool_call.id, &result).await {
+                        Ok(path) => context::truncate_with_preview(
+                            &result,
+                            self.context_config.tool_output_preview_chars as usize,
+                            &format!("Full output stored at {}", path),
+                        ),
+                        Err(e) => {
+                            println!("Spider: Failed to store large tool output: {}", e);
+                            result
+                        }
+                    }
+                }
+                _ => result,
+            };
 
             results.push(ToolResult {
                 tool_call_id: tool_call.id,
                 result,
+                is_error,
             });
         }
 
         Ok(results)
     }
 
+    // Built-in tool calls are audited like MCP tool calls
+    async fn execute_builtin_tool(
+        &mut self,
+        actor: &str,
+        name: &str,
+        params: &Value,
+        scope: &RunScope,
+    ) -> Result<String, String> {
+        let config = &self.builtin_tools_config;
+        let result = match name {
+            builtin_tools::WORKSPACE_READ => builtin_tools::workspace_read(params, config).await,
+            builtin_tools::WORKSPACE_WRITE => builtin_tools::workspace_write(params).await,
+            builtin_tools::WORKSPACE_LIST => builtin_tools::workspace_list(params).await,
+            builtin_tools::HTTP_FETCH => builtin_tools::http_fetch(params, config).await,
+            builtin_tools::CURRENT_TIME => Ok(builtin_tools::current_time()),
+            builtin_tools::SEARCH_CONVERSATIONS if !scope.read_conversations => {
+                Err("Unauthorized: this run lacks conversations:read permission".to_string())
+            }
+            builtin_tools::SEARCH_CONVERSATIONS => {
+                let query = params
+                    .get("query")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let limit = params.get("limit").and_then(Value::as_u64).unwrap_or(20);
+                let hits: Vec<ConversationSearchHit> = self
+                    .active_conversations
+                    .iter()
+                    .filter(|(_, conv)| conversations::is_visible(conv, None, None, false))
+                    .flat_map(|(_, conv)| conversations::search(conv, query))
+                    .take(limit as usize)
+                    .collect();
+                Ok(serde_json::to_string(&hits).unwrap())
+            }
+            _ => Err(format!("Unknown built-in tool: {}", name)),
+        };
+
+        self.audited(actor, "builtin_tool.call", name, result)
+    }
+
+    // Run the delegate tool: a nested agent whose conversation is linked to the calling one
+    async fn delegate(
+        &mut self,
+        actor: &str,
+        params: Value,
+        conversation_id: &str,
+        available_tools: &[Tool],
+        scope: &RunScope,
+    ) -> Result<String, String> {
+        let args: DelegateArgs = serde_json::from_value(params)
+            .map_err(|e| format!("Invalid delegate arguments: {}", e))?;
+        let child_scope = delegation::child_scope(scope, conversation_id, available_tools, &args)?;
+        let profile_id = args
+            .profile
+            .as_deref()
+            .map(|name| {
+                self.agent_profiles
+                    .iter()
+                    .find(|p| p.name == name || p.id == name)
+                    .map(|p| p.id.clone())
+                    .ok_or_else(|| format!("Profile {} not found", name))
+            })
+            .transpose()?;
+        let request =
+            self.agent_chat_request(profile_id.as_deref(), None, args.task, "delegate")?;
+
+        println!(
+            "Spider: Delegating from conversation {} at depth {}",
+            conversation_id, child_scope.depth
+        );
+        let pinned_key_id = child_scope.pinned_key_id.clone();
+        let response = self
+            .run_chat(request, None, actor, pinned_key_id, Some(child_scope))
+            .await?;
+        self.delegated_conversations
+            .entry(conversation_id.to_string())
+            .or_default()
+            .push(response.conversation_id.clone());
+        Ok(delegation::output(&response))
+    }
+
     async fn test_hypergrid_connection(
         &self,
         url: &str,
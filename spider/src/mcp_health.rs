@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+/// How often the health loop wakes up to send pings, retry connections and time out handshakes
+pub(crate) const HEALTH_TICK_MS: u64 = 5_000;
+
+/// Request body that runs `mcp_health_tick`: hyperapp encodes a handler without arguments as a
+/// unit variant, i.e. its name as a JSON string
+pub(crate) fn tick_body() -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!("McpHealthTick")).unwrap()
+}
+
+/// Ready servers are pinged this often
+pub(crate) const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A server that misses this many pings in a row is treated as dead and reconnected
+pub(crate) const MAX_MISSED_PINGS: u32 = 3;
+
+/// How long a server may take to answer `initialize` and `tools/list`
+pub(crate) const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const BACKOFF_BASE_MS: u64 = 1_000;
+const BACKOFF_MAX_MS: u64 = 5 * 60 * 1_000;
+
+/// Transports backed by a long-lived connection that can drop and be re-established.
+/// Hypergrid and HTTP are stateless, and spider-remote is reached through messaging.
+pub(crate) fn reconnects(transport_type: &str) -> bool {
+    matches!(transport_type, "websocket" | "stdio")
+}
+
+/// Delay before reconnect attempt `attempt` (1-based): exponential, capped, with the upper
+/// half jittered so servers that dropped together don't all retry together
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+    let ceiling = BACKOFF_BASE_MS
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX_MS);
+    let half = ceiling / 2;
+    let jitter = (Uuid::new_v4().as_u128() as u64) % (half + 1);
+    Duration::from_millis(half + jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    enum Request {
+        McpHealthTick,
+    }
+
+    #[test]
+    fn tick_body_is_the_handlers_unit_variant() {
+        let request: Request = serde_json::from_slice(&tick_body()).unwrap();
+        assert_eq!(request, Request::McpHealthTick);
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+
+use serde_json::{json, Value};
+
+use crate::types::{McpCallToolResult, McpContent, McpServer, Tool};
+
+/// MCP protocol revision Spider speaks when serving its own tools
+pub(crate) const PROTOCOL_VERSION: &str = "2025-06-18";
+
+// JSON-RPC error codes
+pub(crate) const PARSE_ERROR: i32 = -32700;
+pub(crate) const INVALID_REQUEST: i32 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i32 = -32601;
+pub(crate) const INVALID_PARAMS: i32 = -32602;
+/// Server-defined error for a missing or under-privileged Spider key
+pub(crate) const UNAUTHORIZED: i32 = -32001;
+
+/// A tool re-exported by Spider's MCP endpoint
+pub(crate) struct ExportedTool {
+    /// Name clients call the tool by. Tools whose name is taken by an earlier server are
+    /// qualified as `<server id>__<tool>`.
+    pub(crate) name: String,
+    pub(crate) server_id: String,
+    pub(crate) tool: Tool,
+}
+
+/// Whether a key's tool scopes allow a tool. Scopes are `<server id>/*`, `<server id>/<tool>`
+/// or a bare tool name; no scopes means every tool. A bare name matches that tool on every
+/// server, including servers added after the key was issued; use `<server id>/<tool>` to pin it.
+pub(crate) fn scope_allows(scopes: Option<&[String]>, server_id: &str, tool_name: &str) -> bool {
+    let Some(scopes) = scopes else {
+        return true;
+    };
+    scopes.iter().any(|scope| match scope.split_once('/') {
+        Some((server, "*")) => server == server_id,
+        Some((server, tool)) => server == server_id && tool == tool_name,
+        None => scope == tool_name,
+    })
+}
+
+/// The union of connected servers' tools visible to a key
+pub(crate) fn exported_tools(
+    servers: &[McpServer],
+    scopes: Option<&[String]>,
+) -> Vec<ExportedTool> {
+    let mut taken = HashSet::new();
+    let mut exported = Vec::new();
+
+    for server in servers.iter().filter(|s| s.connected) {
+        for tool in &server.tools {
+            // Qualify on collision so every server's tools stay reachable
+            let name = if taken.contains(&tool.name) {
+                format!("{}__{}", server.id, tool.name)
+            } else {
+                tool.name.clone()
+            };
+            taken.insert(name.clone());
+
+            if scope_allows(scopes, &server.id, &tool.name) {
+                exported.push(ExportedTool {
+                    name,
+                    server_id: server.id.clone(),
+                    tool: tool.clone(),
+                });
+            }
+        }
+    }
+
+    exported
+}
+
+pub(crate) fn tool_descriptor(exported: &ExportedTool) -> Value {
+    let schema = exported
+        .tool
+        .input_schema_json
+        .as_deref()
+        .unwrap_or(&exported.tool.parameters);
+    json!({
+        "name": exported.name,
+        "description": exported.tool.description,
+        "inputSchema": serde_json::from_str::<Value>(schema)
+            .unwrap_or_else(|_| json!({"type": "object"})),
+    })
+}
+
+pub(crate) fn initialize_result() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {
+            "tools": { "listChanged": false }
+        },
+        "serverInfo": {
+            "name": "spider",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// Normalize whatever a transport returned into an MCP `tools/call` result.
+/// Upstream servers answer in MCP form already; built-in transports use `ToolExecutionResult`.
+pub(crate) fn parse_call_tool_result(value: Value) -> McpCallToolResult {
+    if let Some(items) = value.get("content").and_then(Value::as_array) {
+        if let Ok(result) = serde_json::from_value::<McpCallToolResult>(value.clone()) {
+            return result;
+        }
+        // A malformed item must not cost the other items or the error flag
+        return McpCallToolResult {
+            content: items.iter().map(parse_content).collect(),
+            structured_content: value
+                .get("structuredContent")
+                .filter(|s| !s.is_null())
+                .cloned(),
+            is_error: value
+                .get("isError")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        };
+    }
+    if let (Some(inner), Some(success)) = (
+        value.get("result"),
+        value.get("success").and_then(Value::as_bool),
+    ) {
+        let content =
+            serde_json::from_value::<Vec<McpContent>>(inner.clone()).unwrap_or_else(|_| {
+                vec![McpContent::Text {
+                    text: text_of(inner),
+                }]
+            });
+        return McpCallToolResult {
+            content,
+            structured_content: None,
+            is_error: !success,
+        };
+    }
+    McpCallToolResult {
+        content: vec![McpContent::Text {
+            text: text_of(&value),
+        }],
+        structured_content: None,
+        is_error: false,
+    }
+}
+
+/// The result of `execute_mcp_tool` as an MCP `tools/call` result; failures to run the tool
+/// at all are reported as error results, as the spec asks
+pub(crate) fn call_tool_result(result: Result<McpCallToolResult, String>) -> Value {
+    let result = result.unwrap_or_else(|e| McpCallToolResult {
+        content: vec![McpContent::Text { text: e }],
+        structured_content: None,
+        is_error: true,
+    });
+    serde_json::to_value(result).unwrap()
+}
+
+/// A tool result as text for a model. Binary content is described rather than inlined.
+pub(crate) fn result_text(result: &McpCallToolResult) -> String {
+    if result.content.is_empty() {
+        return result
+            .structured_content
+            .as_ref()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+    }
+    result
+        .content
+        .iter()
+        .map(|content| match content {
+            McpContent::Text { text } => text.clone(),
+            McpContent::Image { mime_type, .. } => format!("[image: {}]", mime_type),
+            McpContent::Audio { mime_type, .. } => format!("[audio: {}]", mime_type),
+            McpContent::Resource { resource } => match &resource.text {
+                Some(text) => format!("[resource: {}]\n{}", resource.uri, text),
+                None => format!("[resource: {}]", resource.uri),
+            },
+            McpContent::ResourceLink { uri, name, .. } if name.is_empty() => {
+                format!("[resource link: {}]", uri)
+            }
+            McpContent::ResourceLink { uri, name, .. } => {
+                format!("[resource link: {} ({})]", name, uri)
+            }
+            McpContent::Unknown => "[unsupported content]".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A content item, kept as text when it does not match any content type
+fn parse_content(item: &Value) -> McpContent {
+    serde_json::from_value(item.clone()).unwrap_or_else(|_| McpContent::Text {
+        text: text_of(item),
+    })
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub(crate) fn success(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub(crate) fn error(id: Value, code: i32, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Bearer token from an `Authorization` header value
+pub(crate) fn bearer_token(header: &str) -> Option<&str> {
+    header
+        .strip_prefix("Bearer ")
+        .or_else(|| header.strip_prefix("bearer "))
+        .map(str::trim)
+}
+
+/// Spider API key an `initialize` request carries in `params._meta.authorization`
+/// (`Bearer <key>`). WebSocket clients that can't send Spider's auth frame authenticate this way.
+pub(crate) fn initialize_api_key(message: &Value) -> Option<&str> {
+    if message.get("method").and_then(|m| m.as_str()) != Some("initialize") {
+        return None;
+    }
+    message
+        .pointer("/params/_meta/authorization")
+        .and_then(|a| a.as_str())
+        .and_then(bearer_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_match_servers_tools_and_bare_names() {
+        let scopes = vec![
+            "files/*".to_string(),
+            "github/create_issue".to_string(),
+            "search".to_string(),
+        ];
+        let scopes = Some(scopes.as_slice());
+        assert!(scope_allows(scopes, "files", "read"));
+        assert!(scope_allows(scopes, "github", "create_issue"));
+        assert!(!scope_allows(scopes, "github", "delete_repo"));
+        assert!(!scope_allows(scopes, "gitlab", "create_issue"));
+        // A bare name is not tied to a server
+        assert!(scope_allows(scopes, "web", "search"));
+        assert!(scope_allows(scopes, "docs", "search"));
+        assert!(!scope_allows(scopes, "web", "fetch"));
+
+        assert!(scope_allows(None, "any", "tool"));
+        assert!(!scope_allows(Some(&[]), "any", "tool"));
+    }
+
+    #[test]
+    fn call_results_keep_text_image_and_resource_content() {
+        let result = parse_call_tool_result(json!({
+            "content": [
+                { "type": "text", "text": "3 matches" },
+                { "type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png" },
+                {
+                    "type": "resource",
+                    "resource": { "uri": "file:///notes.md", "mimeType": "text/markdown", "text": "# Notes" }
+                }
+            ]
+        }));
+        assert!(!result.is_error);
+        assert_eq!(result.content.len(), 3);
+        assert_eq!(
+            result_text(&result),
+            "3 matches\n[image: image/png]\n[resource: file:///notes.md]\n# Notes"
+        );
+    }
+
+    #[test]
+    fn malformed_call_results_keep_the_error_flag() {
+        let result = parse_call_tool_result(json!({
+            "content": [
+                { "type": "text", "text": "Permission denied" },
+                { "type": "image", "mimeType": "image/png" }
+            ],
+            "structuredContent": { "code": 403 },
+            "isError": true
+        }));
+        assert!(result.is_error);
+        assert_eq!(result.structured_content, Some(json!({ "code": 403 })));
+        assert_eq!(
+            result.content[0],
+            McpContent::Text {
+                text: "Permission denied".to_string()
+            }
+        );
+        assert!(
+            matches!(&result.content[1], McpContent::Text { text } if text.contains("image/png"))
+        );
+
+        // Built-in transports report failures with `success`
+        let result = parse_call_tool_result(json!({ "success": false, "result": "timed out" }));
+        assert!(result.is_error);
+        assert_eq!(result_text(&result), "timed out");
+    }
+
+    #[test]
+    fn initialize_carries_the_key_in_meta() {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": PROTOCOL_VERSION,
+                "_meta": { "authorization": "Bearer sp_test" }
+            }
+        });
+        assert_eq!(initialize_api_key(&message), Some("sp_test"));
+
+        let list = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/list",
+            "params": { "_meta": { "authorization": "Bearer sp_test" } }
+        });
+        assert_eq!(initialize_api_key(&list), None);
+        assert_eq!(
+            initialize_api_key(&json!({ "method": "initialize", "params": {} })),
+            None
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use hyperware_process_lib::http::{client::send_request_await_response, Method};
+
+use crate::provider::{create_llm_provider, ProviderErrorKind};
+use crate::types::{Message, OAuthTokenResponse};
+
+pub(crate) const OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+const OAUTH_TOKEN_URL: &str = "https://console.anthropic.com/v1/oauth/token";
+const OAUTH_REDIRECT_URI: &str = "https://console.anthropic.com/oauth/code/callback";
+
+/// Refresh access tokens this many seconds before they expire
+pub(crate) const REFRESH_MARGIN_SECS: u64 = 300;
+
+/// Exchange an authorization code (formatted `code#state`) for tokens
+pub(crate) async fn exchange_code(
+    code_with_state: &str,
+    verifier: &str,
+) -> Result<OAuthTokenResponse, String> {
+    // Parse the code to separate code and state
+    let parts: Vec<&str> = code_with_state.split('#').collect();
+    let code = parts.first().unwrap_or(&"").to_string();
+    let state = parts.get(1).unwrap_or(&"").to_string();
+
+    let body = serde_json::json!({
+        "code": code,
+        "state": state,
+        "grant_type": "authorization_code",
+        "client_id": OAUTH_CLIENT_ID,
+        "redirect_uri": OAUTH_REDIRECT_URI,
+        "code_verifier": verifier
+    });
+
+    request_tokens(body, "OAuth exchange").await
+}
+
+/// Trade a refresh token for a new access token
+pub(crate) async fn refresh_tokens(refresh_token: &str) -> Result<OAuthTokenResponse, String> {
+    let body = serde_json::json!({
+        "grant_type": "refresh_token",
+        "refresh_token": refresh_token,
+        "client_id": OAUTH_CLIENT_ID
+    });
+
+    request_tokens(body, "OAuth refresh").await
+}
+
+/// Check that an access token is genuine by making the smallest possible request with it
+pub(crate) async fn verify_access_token(access_token: &str) -> Result<(), String> {
+    let provider = create_llm_provider("anthropic-oauth", access_token)?;
+    let probe = vec![Message {
+        role: "user".to_string(),
+        content: "ping".to_string(),
+        tool_calls_json: None,
+        tool_results_json: None,
+        timestamp: chrono::Utc::now().timestamp() as u64,
+    }];
+
+    match provider.complete(&probe, &[], None, 1, 0.0).await {
+        Ok(_) => Ok(()),
+        Err(e) => match e.kind() {
+            ProviderErrorKind::Auth => Err("OAuth token was rejected by Anthropic".to_string()),
+            _ => Err(format!("Could not verify OAuth token, please retry: {}", e)),
+        },
+    }
+}
+
+async fn request_tokens(body: Value, operation: &str) -> Result<OAuthTokenResponse, String> {
+    let mut headers = HashMap::new();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+
+    let url = url::Url::parse(OAUTH_TOKEN_URL).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    let response = send_request_await_response(
+        Method::POST,
+        url,
+        Some(headers),
+        30000,
+        body.to_string().into_bytes(),
+    )
+    .await
+    .map_err(|e| format!("HTTP request failed: {:?}", e))?;
+
+    if !response.status().is_success() {
+        let body_str = String::from_utf8_lossy(response.body());
+        return Err(format!(
+            "{} failed with status {}: {}",
+            operation,
+            response.status(),
+            body_str
+        ));
+    }
+
+    let json = serde_json::from_slice::<Value>(response.body())
+        .map_err(|e| format!("Failed to parse OAuth response: {}", e))?;
+
+    Ok(OAuthTokenResponse {
+        refresh: json["refresh_token"].as_str().unwrap_or("").to_string(),
+        access: json["access_token"].as_str().unwrap_or("").to_string(),
+        expires: chrono::Utc::now().timestamp() as u64
+            + json["expires_in"].as_u64().unwrap_or(3600),
+    })
+}
@@ -0,0 +1,197 @@
+/// Permissions a Spider API key can hold. Keys store them as their string form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Permission {
+    /// Run chats over HTTP, WebSocket or local requests, and read the MCP servers and
+    /// configuration a chat client needs to pick from
+    Chat,
+    /// Call tools through Spider's own MCP endpoint, limited by the key's tool scopes
+    ToolsCall,
+    /// List, fetch and search stored conversations
+    ConversationsRead,
+    /// Rename, tag, import and restore stored conversations
+    ConversationsWrite,
+    /// Move stored conversations to the trash and delete them permanently
+    ConversationsDelete,
+    /// Add, connect, disconnect and remove MCP servers
+    McpManage,
+    /// Set, list and remove LLM provider keys and Claude logins
+    KeysManage,
+    /// Change Spider configuration
+    ConfigWrite,
+    /// Create and edit agent profiles, scheduled tasks and webhook triggers
+    AgentsManage,
+    /// Implies every other permission; also required to issue and revoke Spider keys
+    Admin,
+}
+
+impl Permission {
+    pub(crate) const ALL: [Permission; 10] = [
+        Permission::Chat,
+        Permission::ToolsCall,
+        Permission::ConversationsRead,
+        Permission::ConversationsWrite,
+        Permission::ConversationsDelete,
+        Permission::McpManage,
+        Permission::KeysManage,
+        Permission::ConfigWrite,
+        Permission::AgentsManage,
+        Permission::Admin,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Chat => "chat",
+            Permission::ToolsCall => "tools:call",
+            Permission::ConversationsRead => "conversations:read",
+            Permission::ConversationsWrite => "conversations:write",
+            Permission::ConversationsDelete => "conversations:delete",
+            Permission::McpManage => "mcp:manage",
+            Permission::KeysManage => "keys:manage",
+            Permission::ConfigWrite => "config:write",
+            Permission::AgentsManage => "agents:manage",
+            Permission::Admin => "admin",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Permission> {
+        Permission::ALL.into_iter().find(|p| p.as_str() == s)
+    }
+}
+
+/// Named bundles of permissions offered when creating a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// Full control
+    Admin,
+    /// Everything except issuing Spider keys
+    Operator,
+    /// Chat, call tools and review conversations
+    User,
+    /// Read-only access to conversations
+    Viewer,
+}
+
+impl Role {
+    pub(crate) const ALL: [Role; 4] = [Role::Admin, Role::Operator, Role::User, Role::Viewer];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+            Role::User => "user",
+            Role::Viewer => "viewer",
+        }
+    }
+
+    pub(crate) fn parse(s: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|r| r.as_str() == s)
+    }
+
+    pub(crate) fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[Permission::Admin],
+            Role::Operator => &[
+                Permission::Chat,
+                Permission::ToolsCall,
+                Permission::ConversationsRead,
+                Permission::ConversationsWrite,
+                Permission::ConversationsDelete,
+                Permission::McpManage,
+                Permission::KeysManage,
+                Permission::ConfigWrite,
+                Permission::AgentsManage,
+            ],
+            Role::User => &[
+                Permission::Chat,
+                Permission::ToolsCall,
+                Permission::ConversationsRead,
+            ],
+            Role::Viewer => &[Permission::ConversationsRead],
+        }
+    }
+}
+
+fn to_strings(permissions: &[Permission]) -> Vec<String> {
+    // Emit in canonical order so stored keys are stable and easy to compare
+    Permission::ALL
+        .iter()
+        .filter(|p| permissions.contains(p))
+        .map(|p| p.as_str().to_string())
+        .collect()
+}
+
+/// Validate requested permissions and expand an optional role into the stored permission list
+pub(crate) fn resolve_permissions(
+    requested: &[String],
+    role: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let mut permissions = Vec::new();
+
+    if let Some(role) = role {
+        let role = Role::parse(role).ok_or_else(|| {
+            format!(
+                "Unknown role: {} (expected one of: {})",
+                role,
+                Role::ALL.map(|r| r.as_str()).join(", ")
+            )
+        })?;
+        permissions.extend_from_slice(role.permissions());
+    }
+
+    for name in requested {
+        if let Some(legacy) = legacy_permissions(name) {
+            // Older clients (and other packages creating keys for themselves) still send these
+            permissions.extend_from_slice(legacy);
+            continue;
+        }
+        let permission = Permission::parse(name).ok_or_else(|| {
+            format!(
+                "Unknown permission: {} (expected one of: {})",
+                name,
+                Permission::ALL.map(|p| p.as_str()).join(", ")
+            )
+        })?;
+        permissions.push(permission);
+    }
+
+    if permissions.is_empty() {
+        return Err("A Spider key needs at least one permission or a role".to_string());
+    }
+
+    Ok(to_strings(&permissions))
+}
+
+fn legacy_permissions(name: &str) -> Option<&'static [Permission]> {
+    match name {
+        "read" | "list" => Some(&[Permission::ConversationsRead]),
+        // "write" was what chat and every mutating endpoint actually checked
+        "write" => Some(Role::Operator.permissions()),
+        _ => None,
+    }
+}
+
+/// Map the free-form permissions used before the typed model onto their closest equivalents,
+/// dropping anything unrecognised
+pub(crate) fn migrate_legacy_permissions(permissions: &[String]) -> Vec<String> {
+    let mut migrated = Vec::new();
+    for name in permissions {
+        if let Some(legacy) = legacy_permissions(name) {
+            migrated.extend_from_slice(legacy);
+        } else if let Some(permission) = Permission::parse(name) {
+            // Before conversations:write existed, conversations:delete also covered renaming,
+            // tagging, importing and restoring
+            if permission == Permission::ConversationsDelete {
+                migrated.push(Permission::ConversationsWrite);
+            }
+            migrated.push(permission);
+        }
+    }
+    to_strings(&migrated)
+}
+
+/// Whether a stored permission list grants `permission`
+pub(crate) fn grants(permissions: &[String], permission: Permission) -> bool {
+    permissions
+        .iter()
+        .any(|p| p == permission.as_str() || p == Permission::Admin.as_str())
+}
@@ -1,17 +1,22 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 
 use chrono::Utc;
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use hyperware_anthropic_sdk::{
-    AnthropicClient, Content, CreateMessageRequest, Message as SdkMessage, ResponseContentBlock,
-    Role, Tool as SdkTool, ToolChoice,
-};
+use hyperware_process_lib::http::{client::send_request_await_response, Method};
 
-use crate::provider::LlmProvider;
+use crate::provider::schema::normalize_input_schema;
+use crate::provider::{LlmProvider, ProviderError};
 use crate::types::{Message, Tool, ToolCall, ToolResult};
 
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const API_VERSION: &str = "2023-06-01";
+const OAUTH_BETA: &str = "oauth-2025-04-20";
+const DEFAULT_MODEL: &str = "claude-sonnet-4-20250514";
+const REQUEST_TIMEOUT_MS: u64 = 600000;
+
 pub(crate) struct AnthropicProvider {
     api_key: String,
     is_oauth: bool,
@@ -31,7 +36,7 @@ impl LlmProvider for AnthropicProvider {
         model: Option<&'a str>,
         max_tokens: u32,
         temperature: f32,
-    ) -> Pin<Box<dyn Future<Output = Result<Message, String>> + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = Result<Message, ProviderError>> + 'a>> {
         Box::pin(async move {
             // For simplicity in WASM, skip retry logic for now
             self.complete_with_retry(messages, tools, model, max_tokens, temperature)
@@ -45,347 +50,335 @@ impl LlmProvider for AnthropicProvider {
 }
 
 impl AnthropicProvider {
-    // Transform MCP JSON Schema to Anthropic-compatible format
-    fn transform_mcp_to_anthropic_schema(&self, mcp_schema: &Value) -> Value {
-        // Start with basic structure
-        let mut anthropic_schema = serde_json::json!({
-            "type": "object"
-        });
-
-        if let Some(t) = mcp_schema.get("type") {
-            anthropic_schema["type"] = t.clone();
-        }
+    async fn complete_with_retry(
+        &self,
+        messages: &[Message],
+        tools: &[Tool],
+        model: Option<&str>,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<Message, ProviderError> {
+        let body = self.request_body(messages, tools, model, max_tokens, temperature);
 
-        // Handle $defs and resolve references if present
-        let resolved_schema = if mcp_schema.get("$defs").is_some()
-            || mcp_schema
-                .as_object()
-                .map(|o| o.keys().any(|k| k.contains("$ref")))
-                .unwrap_or(false)
-        {
-            self.resolve_schema_refs(mcp_schema, mcp_schema.get("$defs"))
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        headers.insert("anthropic-version".to_string(), API_VERSION.to_string());
+        if self.is_oauth {
+            headers.insert(
+                "Authorization".to_string(),
+                format!("Bearer {}", self.api_key),
+            );
+            headers.insert("anthropic-beta".to_string(), OAUTH_BETA.to_string());
         } else {
-            mcp_schema.clone()
-        };
+            headers.insert("x-api-key".to_string(), self.api_key.clone());
+        }
 
-        // Extract and clean properties
-        if let Some(properties) = resolved_schema.get("properties") {
-            anthropic_schema["properties"] = self.clean_properties_for_anthropic(properties);
+        let url = url::Url::parse(MESSAGES_URL).map_err(|e| format!("Invalid URL: {}", e))?;
+        let response = send_request_await_response(
+            Method::POST,
+            url,
+            Some(headers),
+            REQUEST_TIMEOUT_MS,
+            body.to_string().into_bytes(),
+        )
+        .await
+        .map_err(|e| format!("HTTP request to Anthropic failed: {:?}", e))?;
+
+        let status = response.status().as_u16();
+        let body = serde_json::from_slice::<Value>(response.body()).ok();
+        if !response.status().is_success() {
+            return Err(api_error(status, body.as_ref(), response.body()));
         }
+        let body = body.ok_or_else(|| ProviderError {
+            status: Some(status),
+            message: "Anthropic returned a response that is not JSON".to_string(),
+        })?;
+        Ok(parse_response(&body))
+    }
+
+    fn request_body(
+        &self,
+        messages: &[Message],
+        tools: &[Tool],
+        model: Option<&str>,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Value {
+        let mut body = json!({
+            "model": model.unwrap_or(DEFAULT_MODEL),
+            "max_tokens": max_tokens,
+            "temperature": temperature,
+            "messages": request_messages(messages),
+        });
 
-        // Extract required fields
-        if let Some(required) = resolved_schema.get("required") {
-            anthropic_schema["required"] = required.clone();
+        // OAuth tokens are only accepted with this system prompt
+        if self.is_oauth {
+            body["system"] = json!("You are Claude Code, Anthropic's official CLI for Claude.");
         }
 
-        anthropic_schema
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools.iter().map(request_tool).collect());
+            body["tool_choice"] = json!({ "type": "auto", "disable_parallel_tool_use": false });
+        }
+        body
     }
+}
 
-    // Resolve JSON Schema $ref references
-    fn resolve_schema_refs(&self, schema: &Value, defs: Option<&Value>) -> Value {
-        match schema {
-            Value::Object(map) => {
-                let mut resolved = serde_json::Map::new();
-
-                for (key, value) in map {
-                    if key == "$ref" {
-                        // Resolve the reference
-                        if let Some(ref_path) = value.as_str() {
-                            if let Some(resolved_def) = self.resolve_ref_path(ref_path, defs) {
-                                // Merge the resolved definition into current level
-                                if let Value::Object(def_map) = resolved_def {
-                                    for (def_key, def_value) in def_map {
-                                        if def_key != "$ref" {
-                                            resolved.insert(
-                                                def_key,
-                                                self.resolve_schema_refs(&def_value, defs),
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    } else if key != "$defs" && key != "$schema" {
-                        // Recursively resolve nested schemas, skip $defs and $schema
-                        resolved.insert(key.clone(), self.resolve_schema_refs(value, defs));
-                    }
+/// Convert our messages to the Messages API format. Tool calls and their results become
+/// `tool_use` and `tool_result` blocks when they pair up, as the API requires; an unpaired one,
+/// e.g. from a run stopped at its iteration limit, is sent as text instead.
+fn request_messages(messages: &[Message]) -> Vec<Value> {
+    let calls: Vec<Vec<ToolCall>> = messages
+        .iter()
+        .map(|m| parse_list(m.tool_calls_json.as_deref()))
+        .collect();
+    let results: Vec<Vec<ToolResult>> = messages
+        .iter()
+        .map(|m| parse_list(m.tool_results_json.as_deref()))
+        .collect();
+
+    let mut request: Vec<Value> = Vec::new();
+    for (i, msg) in messages.iter().enumerate() {
+        // Tool results are sent as user messages in Claude API
+        let role = if msg.role == "assistant" {
+            "assistant"
+        } else {
+            "user"
+        };
+        let mut blocks = Vec::new();
+
+        if msg.tool_results_json.is_some() {
+            let previous_calls = i.checked_sub(1).map_or(&[][..], |p| &calls[p]);
+            for result in &results[i] {
+                if previous_calls.iter().any(|c| c.id == result.tool_call_id) {
+                    blocks.push(json!({
+                        "type": "tool_result",
+                        "tool_use_id": result.tool_call_id,
+                        "content": result.result,
+                        "is_error": result.is_error,
+                    }));
+                } else {
+                    let status = if result.is_error { " (error)" } else { "" };
+                    blocks.push(text_block(&format!(
+                        "Result of tool call {}{}: {}",
+                        result.tool_call_id, status, result.result
+                    )));
                 }
-
-                Value::Object(resolved)
             }
-            Value::Array(arr) => Value::Array(
-                arr.iter()
-                    .map(|v| self.resolve_schema_refs(v, defs))
-                    .collect(),
-            ),
-            other => other.clone(),
+        } else if !msg.content.is_empty() {
+            blocks.push(text_block(&msg.content));
         }
-    }
 
-    // Helper to resolve a $ref path
-    fn resolve_ref_path(&self, ref_path: &str, defs: Option<&Value>) -> Option<Value> {
-        // Handle references like "#/$defs/TextOrSearchReplaceBlock"
-        if ref_path.starts_with("#/$defs/") {
-            if let Some(defs) = defs {
-                let def_name = &ref_path[8..]; // Skip "#/$defs/"
-                return defs.get(def_name).cloned();
+        let next_results = results.get(i + 1).map_or(&[][..], Vec::as_slice);
+        for call in &calls[i] {
+            if next_results.iter().any(|r| r.tool_call_id == call.id) {
+                let input = serde_json::from_str::<Value>(&call.parameters)
+                    .ok()
+                    .filter(Value::is_object)
+                    .unwrap_or_else(|| json!({}));
+                blocks.push(json!({
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.tool_name,
+                    "input": input,
+                }));
+            } else {
+                blocks.push(text_block(&format!(
+                    "[Called {} with {}]",
+                    call.tool_name, call.parameters
+                )));
             }
         }
-        None
-    }
 
-    // Clean properties to ensure they match Anthropic's requirements
-    fn clean_properties_for_anthropic(&self, properties: &Value) -> Value {
-        match properties {
-            Value::Object(map) => {
-                let mut cleaned = serde_json::Map::new();
-                for (key, value) in map {
-                    // Ensure property names match Anthropic's pattern
-                    if self.is_valid_anthropic_property_name(key) {
-                        // Recursively clean the property value
-                        cleaned.insert(key.clone(), self.clean_schema_value_for_anthropic(value));
-                    }
+        // The API rejects empty content, and consecutive turns of one role are merged
+        if blocks.is_empty() {
+            continue;
+        }
+        match request.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
                 }
-                Value::Object(cleaned)
             }
-            other => other.clone(),
+            _ => request.push(json!({ "role": role, "content": blocks })),
         }
     }
+    request
+}
 
-    // Clean individual schema values
-    fn clean_schema_value_for_anthropic(&self, value: &Value) -> Value {
-        match value {
-            Value::Object(map) => {
-                let mut cleaned = serde_json::Map::new();
-
-                // Check if this object has a default but no type
-                let has_default = map.contains_key("default");
-                let has_type = map.contains_key("type");
-
-                for (key, val) in map {
-                    // Only keep standard JSON Schema properties for Anthropic
-                    if matches!(
-                        key.as_str(),
-                        "type"
-                            | "description"
-                            | "properties"
-                            | "required"
-                            | "items"
-                            | "enum"
-                            | "const"
-                            | "minimum"
-                            | "maximum"
-                            | "minLength"
-                            | "maxLength"
-                            | "pattern"
-                            | "format"
-                    ) {
-                        cleaned.insert(key.clone(), self.clean_schema_value_for_anthropic(val));
-                    }
-                    // Special handling for default - only include if there's also a type
-                    else if key == "default" && has_type {
-                        cleaned.insert(key.clone(), val.clone());
-                    }
-                }
+fn parse_list<T: serde::de::DeserializeOwned>(json: Option<&str>) -> Vec<T> {
+    json.and_then(|j| serde_json::from_str(j).ok())
+        .unwrap_or_default()
+}
 
-                // If we have a default but no type, infer the type from the default value
-                if has_default && !has_type {
-                    if let Some(default_val) = map.get("default") {
-                        let inferred_type = match default_val {
-                            Value::String(_) => "string",
-                            Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
-                            Value::Number(_) => "number",
-                            Value::Bool(_) => "boolean",
-                            Value::Array(_) => "array",
-                            Value::Object(_) => "object",
-                            Value::Null => "null",
-                        };
-                        cleaned
-                            .insert("type".to_string(), Value::String(inferred_type.to_string()));
-                    }
-                }
+fn text_block(text: &str) -> Value {
+    json!({ "type": "text", "text": text })
+}
 
-                Value::Object(cleaned)
-            }
-            Value::Array(arr) => Value::Array(
-                arr.iter()
-                    .map(|v| self.clean_schema_value_for_anthropic(v))
-                    .collect(),
-            ),
-            other => other.clone(),
-        }
+fn request_tool(tool: &Tool) -> Value {
+    // Parse the MCP schema from either inputSchema or parameters
+    let mcp_schema = tool
+        .input_schema_json
+        .as_deref()
+        .unwrap_or(&tool.parameters);
+    let mcp_schema = serde_json::from_str::<Value>(mcp_schema).unwrap_or_else(|_| json!({}));
+
+    // Transform MCP schema to Anthropic-compatible format
+    let anthropic_schema = normalize_input_schema(&mcp_schema);
+
+    // Debug: Log the transformed schema
+    println!(
+        "Spider: Tool {} transformed schema: {}",
+        tool.name,
+        serde_json::to_string_pretty(&anthropic_schema).unwrap_or_else(|_| "error".to_string())
+    );
+
+    let mut input_schema = json!({ "type": "object" });
+    if let Some(properties) = anthropic_schema.get("properties") {
+        input_schema["properties"] = properties.clone();
+    }
+    if let Some(required) = anthropic_schema.get("required") {
+        input_schema["required"] = required.clone();
     }
 
-    // Validate property names against Anthropic's pattern
-    fn is_valid_anthropic_property_name(&self, name: &str) -> bool {
-        // Pattern: ^[a-zA-Z0-9_.-]{1,64}$
-        name.len() <= 64
-            && name.len() >= 1
-            && name
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+    json!({
+        "name": tool.name,
+        "description": tool.description,
+        "input_schema": input_schema,
+    })
+}
+
+/// An error response, keeping the HTTP status so callers can decide whether to fall back
+fn api_error(status: u16, body: Option<&Value>, raw: &[u8]) -> ProviderError {
+    let message = body
+        .and_then(|b| b["error"]["message"].as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| String::from_utf8_lossy(raw).into_owned());
+    ProviderError {
+        status: Some(status),
+        message: format!("Anthropic API error: {}", message),
     }
+}
 
-    async fn complete_with_retry(
-        &self,
-        messages: &[Message],
-        tools: &[Tool],
-        model: Option<&str>,
-        max_tokens: u32,
-        temperature: f32,
-    ) -> Result<Message, String> {
-        // Initialize the Anthropic SDK client
-        let client = if self.is_oauth {
-            // Use the with_oauth() method for OAuth tokens
-            AnthropicClient::new(&self.api_key).with_oauth()
-        } else {
-            AnthropicClient::new(&self.api_key)
-        };
+/// Convert a Messages API response back to our Message format
+fn parse_response(body: &Value) -> Message {
+    let mut content_text = String::new();
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
 
-        // Convert our Message format to SDK Message format
-        let mut sdk_messages = Vec::new();
-
-        for msg in messages {
-            let role = match msg.role.as_str() {
-                "user" => Role::User,
-                "assistant" => Role::Assistant,
-                "tool" => Role::User, // Tool results are sent as user messages in Claude API
-                _ => Role::User,
-            };
-
-            // Handle different message types
-            let content = if let Some(tool_results_json) = &msg.tool_results_json {
-                // Parse tool results and format them for the SDK
-                let tool_results: Vec<ToolResult> =
-                    serde_json::from_str(tool_results_json).unwrap_or_else(|_| Vec::new());
-
-                // Format tool results as text content
-                let mut result_text = String::from("Tool execution results:\n");
-                for result in tool_results {
-                    result_text.push_str(&format!(
-                        "- Tool call {}: {}\n",
-                        result.tool_call_id, result.result
-                    ));
+    for block in body["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => {
+                if !content_text.is_empty() {
+                    content_text.push(' ');
                 }
-                Content::Text(result_text)
-            } else if let Some(_tool_calls_json) = &msg.tool_calls_json {
-                // For now, include tool calls as text in the message
-                // The SDK will handle tool use blocks separately
-                Content::Text(format!("{}\n[Tool calls pending]", msg.content))
-            } else {
-                Content::Text(msg.content.clone())
-            };
-
-            sdk_messages.push(SdkMessage { role, content });
+                content_text.push_str(block["text"].as_str().unwrap_or_default());
+            }
+            Some("tool_use") => {
+                tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    tool_name: block["name"].as_str().unwrap_or_default().to_string(),
+                    parameters: match &block["input"] {
+                        Value::Null => "{}".to_string(),
+                        input => input.to_string(),
+                    },
+                });
+            }
+            _ => {}
         }
+    }
 
-        // Convert our Tool format to SDK Tool format
-        let sdk_tools: Vec<SdkTool> = tools
-            .iter()
-            .map(|tool| {
-                // Parse the MCP schema from either inputSchema or parameters
-                let mcp_schema = if let Some(ref input_schema_json) = tool.input_schema_json {
-                    serde_json::from_str::<Value>(input_schema_json)
-                        .unwrap_or_else(|_| serde_json::json!({}))
-                } else {
-                    serde_json::from_str::<Value>(&tool.parameters)
-                        .unwrap_or_else(|_| serde_json::json!({}))
-                };
-
-                // Transform MCP schema to Anthropic-compatible format
-                let anthropic_schema = self.transform_mcp_to_anthropic_schema(&mcp_schema);
-
-                // Debug: Log the transformed schema
-                println!(
-                    "Spider: Tool {} transformed schema: {}",
-                    tool.name,
-                    serde_json::to_string_pretty(&anthropic_schema)
-                        .unwrap_or_else(|_| "error".to_string())
-                );
-
-                // Extract required fields from the transformed schema
-                let required = anthropic_schema
-                    .get("required")
-                    .and_then(|r| r.as_array())
-                    .map(|arr| {
-                        arr.iter()
-                            .filter_map(|v| v.as_str().map(String::from))
-                            .collect()
-                    })
-                    .unwrap_or_else(Vec::new);
-
-                SdkTool::new(
-                    tool.name.clone(),
-                    tool.description.clone(),
-                    anthropic_schema["properties"].clone(),
-                    required,
-                    None,
-                    //anthropic_schema.get("type").and_then(|v| v.as_str()).map(|s| s.to_string()),
-                )
-            })
-            .collect();
-
-        // Create the request with the specified model or default
-        let model_id = model.unwrap_or("claude-sonnet-4-20250514");
-        let mut request = CreateMessageRequest::new(model_id, sdk_messages, max_tokens)
-            .with_temperature(temperature);
-
-        // Add system prompt for OAuth tokens
-        if self.is_oauth {
-            request =
-                request.with_system("You are Claude Code, Anthropic's official CLI for Claude.");
+    Message {
+        role: "assistant".to_string(),
+        content: content_text,
+        tool_calls_json: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&tool_calls).unwrap())
+        },
+        tool_results_json: None,
+        timestamp: Utc::now().timestamp() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str, calls: Option<Value>, results: Option<Value>) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls_json: calls.map(|c| c.to_string()),
+            tool_results_json: results.map(|r| r.to_string()),
+            timestamp: 0,
         }
+    }
 
-        println!("Tools: {sdk_tools:?}");
+    #[test]
+    fn tool_calls_and_results_are_sent_as_paired_blocks() {
+        let messages = vec![
+            message("user", "What is in notes.md?", None, None),
+            message(
+                "assistant",
+                "Let me look.",
+                Some(
+                    json!([{ "id": "toolu_1", "tool_name": "workspace_read", "parameters": "{\"path\":\"notes.md\"}" }]),
+                ),
+                None,
+            ),
+            message(
+                "tool",
+                "Tool execution results",
+                None,
+                Some(
+                    json!([{ "tool_call_id": "toolu_1", "result": "No such file", "is_error": true }]),
+                ),
+            ),
+            message("user", "Then create it.", None, None),
+        ];
+        let request = request_messages(&messages);
+
+        assert_eq!(request.len(), 3);
+        assert_eq!(
+            request[1]["content"][1],
+            json!({ "type": "tool_use", "id": "toolu_1", "name": "workspace_read", "input": { "path": "notes.md" } })
+        );
+        // The results and the following user turn form one user message, results first
+        assert_eq!(request[2]["role"], "user");
+        assert_eq!(
+            request[2]["content"][0],
+            json!({ "type": "tool_result", "tool_use_id": "toolu_1", "content": "No such file", "is_error": true })
+        );
+        assert_eq!(request[2]["content"][1]["text"], "Then create it.");
+    }
 
-        // Add tools if any
-        if !sdk_tools.is_empty() {
-            request = request
-                .with_tools(sdk_tools)
-                .with_tool_choice(ToolChoice::Auto {
-                    disable_parallel_tool_use: Some(false),
-                });
-        }
+    #[test]
+    fn unpaired_tool_calls_are_sent_as_text() {
+        let messages = vec![
+            message("user", "Research this", None, None),
+            message(
+                "assistant",
+                "",
+                Some(json!([{ "id": "toolu_1", "tool_name": "search", "parameters": "{}" }])),
+                None,
+            ),
+            message("user", "Never mind", None, None),
+        ];
+        let request = request_messages(&messages);
 
-        // Send the message using the SDK
-        let response = client
-            .send_message(request)
-            .await
-            .map_err(|e| format!("Failed to send message via SDK: {:?}", e))?;
-
-        // Convert SDK response back to our Message format
-        let mut content_text = String::new();
-        let mut tool_calls: Vec<ToolCall> = Vec::new();
-
-        for block in &response.content {
-            match block {
-                ResponseContentBlock::Text { text, .. } => {
-                    if !content_text.is_empty() {
-                        content_text.push(' ');
-                    }
-                    content_text.push_str(text);
-                }
-                ResponseContentBlock::ToolUse { id, name, input } => {
-                    tool_calls.push(ToolCall {
-                        id: id.clone(),
-                        tool_name: name.clone(),
-                        parameters: serde_json::to_string(input)
-                            .unwrap_or_else(|_| "{}".to_string()),
-                    });
-                }
-            }
-        }
+        assert_eq!(request.len(), 3);
+        assert_eq!(request[1]["content"][0]["type"], "text");
+        assert!(request
+            .iter()
+            .flat_map(|m| m["content"].as_array().unwrap())
+            .all(|b| b["type"] == "text"));
+    }
 
-        Ok(Message {
-            role: "assistant".to_string(),
-            content: content_text,
-            tool_calls_json: if tool_calls.is_empty() {
-                None
-            } else {
-                Some(serde_json::to_string(&tool_calls).unwrap())
-            },
-            tool_results_json: None,
-            timestamp: Utc::now().timestamp() as u64,
-        })
+    #[test]
+    fn error_responses_keep_their_status() {
+        let body = json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } });
+        let error = api_error(529, Some(&body), b"");
+        assert_eq!(error.status, Some(529));
+        assert_eq!(error.message, "Anthropic API error: Overloaded");
     }
 }
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -6,6 +7,11 @@ use crate::types::{Message, Tool};
 mod anthropic;
 use anthropic::AnthropicProvider;
 
+mod schema;
+
+/// Provider names accepted by `create_llm_provider`, in the order they are offered to users
+pub(crate) const KNOWN_PROVIDERS: &[&str] = &["anthropic", "anthropic-oauth", "openai"];
+
 pub(crate) trait LlmProvider {
     fn complete<'a>(
         &'a self,
@@ -14,7 +20,7 @@ pub(crate) trait LlmProvider {
         model: Option<&'a str>,
         max_tokens: u32,
         temperature: f32,
-    ) -> Pin<Box<dyn Future<Output = Result<Message, String>> + 'a>>;
+    ) -> Pin<Box<dyn Future<Output = Result<Message, ProviderError>> + 'a>>;
     fn name(&self) -> &str;
 }
 
@@ -37,8 +43,8 @@ impl LlmProvider for OpenAIProvider {
         _model: Option<&'a str>,
         _max_tokens: u32,
         _temperature: f32,
-    ) -> Pin<Box<dyn Future<Output = Result<Message, String>> + 'a>> {
-        Box::pin(async move { Err("OpenAI provider not yet implemented".to_string()) })
+    ) -> Pin<Box<dyn Future<Output = Result<Message, ProviderError>> + 'a>> {
+        Box::pin(async move { Err("OpenAI provider not yet implemented".to_string().into()) })
     }
 
     fn name(&self) -> &str {
@@ -46,20 +52,119 @@ impl LlmProvider for OpenAIProvider {
     }
 }
 
-pub(crate) fn create_llm_provider(provider_type: &str, api_key: &str) -> Box<dyn LlmProvider> {
+pub(crate) fn create_llm_provider(
+    provider_type: &str,
+    api_key: &str,
+) -> Result<Box<dyn LlmProvider>, String> {
     use crate::utils::is_oauth_token;
 
     match provider_type {
         "anthropic" => {
             // Check if this is an OAuth token by examining the third field
             let is_oauth = is_oauth_token(api_key);
-            Box::new(AnthropicProvider::new(api_key.to_string(), is_oauth))
+            Ok(Box::new(AnthropicProvider::new(
+                api_key.to_string(),
+                is_oauth,
+            )))
         }
-        "openai" => Box::new(OpenAIProvider::new(api_key.to_string())),
-        _ => {
-            // Default to Anthropic
-            let is_oauth = is_oauth_token(api_key);
-            Box::new(AnthropicProvider::new(api_key.to_string(), is_oauth))
+        "anthropic-oauth" => Ok(Box::new(AnthropicProvider::new(api_key.to_string(), true))),
+        "openai" => Ok(Box::new(OpenAIProvider::new(api_key.to_string()))),
+        _ => Err(format!(
+            "Unknown LLM provider: {} (expected one of: {})",
+            provider_type,
+            KNOWN_PROVIDERS.join(", ")
+        )),
+    }
+}
+
+/// Providers that accept the same model names, so a requested model can follow a fallback
+pub(crate) fn provider_family(provider_type: &str) -> &str {
+    match provider_type {
+        "anthropic" | "anthropic-oauth" => "anthropic",
+        other => other,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ProviderErrorKind {
+    RateLimited,
+    Overloaded,
+    Auth,
+    Other,
+}
+
+impl ProviderErrorKind {
+    /// Errors that another key or provider might not hit
+    pub(crate) fn should_fall_back(&self) -> bool {
+        !matches!(self, ProviderErrorKind::Other)
+    }
+}
+
+/// A failed completion. `status` is the provider's HTTP status; it is absent when the request
+/// never got a response or failed before being sent.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ProviderError {
+    pub(crate) status: Option<u16>,
+    pub(crate) message: String,
+}
+
+impl ProviderError {
+    pub(crate) fn kind(&self) -> ProviderErrorKind {
+        match self.status {
+            Some(429) => ProviderErrorKind::RateLimited,
+            Some(503) | Some(529) => ProviderErrorKind::Overloaded,
+            Some(401) | Some(403) => ProviderErrorKind::Auth,
+            _ => ProviderErrorKind::Other,
+        }
+    }
+}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        Self {
+            status: None,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} (HTTP {})", self.message, status),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(status: Option<u16>, message: &str) -> ProviderError {
+        ProviderError {
+            status,
+            message: message.to_string(),
         }
     }
+
+    #[test]
+    fn errors_are_classified_by_status_not_text() {
+        assert_eq!(error(Some(429), "").kind(), ProviderErrorKind::RateLimited);
+        assert_eq!(error(Some(529), "").kind(), ProviderErrorKind::Overloaded);
+        assert_eq!(error(Some(503), "").kind(), ProviderErrorKind::Overloaded);
+        assert_eq!(error(Some(401), "").kind(), ProviderErrorKind::Auth);
+        assert_eq!(error(Some(403), "").kind(), ProviderErrorKind::Auth);
+        // Tool output or model text that merely mentions a status must not trigger a fallback
+        assert_eq!(
+            error(Some(400), "prompt mentions 401 and a rate limit").kind(),
+            ProviderErrorKind::Other
+        );
+        assert_eq!(
+            error(None, "HTTP request failed: 503 api key").kind(),
+            ProviderErrorKind::Other
+        );
+        assert!(!ProviderErrorKind::Other.should_fall_back());
+        assert!(ProviderErrorKind::Auth.should_fall_back());
+    }
 }
@@ -0,0 +1,489 @@
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+/// Keywords passed through to providers. Anything else (titles, `$schema`, vendor
+/// extensions) is dropped.
+const KEPT_KEYWORDS: &[&str] = &[
+    "type",
+    "description",
+    "properties",
+    "required",
+    "additionalProperties",
+    "patternProperties",
+    "minProperties",
+    "maxProperties",
+    "items",
+    "prefixItems",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "enum",
+    "const",
+    "default",
+    "examples",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "format",
+    "anyOf",
+    "oneOf",
+    "allOf",
+    "not",
+];
+
+/// Keywords whose value is a single subschema
+const SUBSCHEMA_KEYWORDS: &[&str] = &["items", "additionalProperties", "not"];
+
+/// Keywords whose value is a list of subschemas
+const SUBSCHEMA_LIST_KEYWORDS: &[&str] = &["anyOf", "oneOf", "allOf", "prefixItems"];
+
+/// Keywords whose value maps names to subschemas
+const SUBSCHEMA_MAP_KEYWORDS: &[&str] = &["properties", "patternProperties"];
+
+/// Normalize an MCP tool's input schema into a self-contained object schema providers accept.
+///
+/// Local `$ref`s (into `$defs`, `definitions` or any other JSON pointer) are inlined. A
+/// reference back into a definition that is already being expanded is replaced by that
+/// definition's type and description, so recursive schemas terminate. Single-branch
+/// combinators are merged into their parent, `anyOf: [T, null]` and OpenAPI's `nullable`
+/// become type unions, and property names providers reject are dropped.
+pub(crate) fn normalize_input_schema(schema: &Value) -> Value {
+    let mut normalizer = Normalizer {
+        root: schema,
+        expanding: HashSet::new(),
+    };
+    let mut normalized = match normalizer.normalize(schema) {
+        Value::Object(map) => map,
+        // `true`, `{}` or garbage: accept any object
+        _ => Map::new(),
+    };
+    normalized.insert("type".to_string(), Value::String("object".to_string()));
+    if !normalized.contains_key("properties") {
+        normalized.insert("properties".to_string(), Value::Object(Map::new()));
+    }
+    Value::Object(normalized)
+}
+
+/// Property names Anthropic accepts: `^[a-zA-Z0-9_.-]{1,64}$`
+fn is_valid_property_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+struct Normalizer<'a> {
+    root: &'a Value,
+    /// References currently being inlined
+    expanding: HashSet<String>,
+}
+
+impl Normalizer<'_> {
+    fn normalize(&mut self, schema: &Value) -> Value {
+        let Value::Object(map) = schema else {
+            // Boolean schemas are already as simple as they get
+            return schema.clone();
+        };
+
+        let mut out = Map::new();
+
+        if let Some(reference) = map.get("$ref").and_then(|r| r.as_str()) {
+            if let Value::Object(resolved) = self.expand_ref(reference) {
+                out = resolved;
+            }
+        }
+
+        for (key, value) in map {
+            if !KEPT_KEYWORDS.contains(&key.as_str()) {
+                continue;
+            }
+            let normalized = if SUBSCHEMA_KEYWORDS.contains(&key.as_str()) {
+                self.normalize(value)
+            } else if SUBSCHEMA_LIST_KEYWORDS.contains(&key.as_str()) {
+                match value {
+                    Value::Array(list) => {
+                        Value::Array(list.iter().map(|s| self.normalize(s)).collect())
+                    }
+                    other => other.clone(),
+                }
+            } else if SUBSCHEMA_MAP_KEYWORDS.contains(&key.as_str()) {
+                match value {
+                    Value::Object(properties) => Value::Object(
+                        properties
+                            .iter()
+                            .filter(|(name, _)| key != "properties" || is_valid_property_name(name))
+                            .map(|(name, s)| (name.clone(), self.normalize(s)))
+                            .collect(),
+                    ),
+                    other => other.clone(),
+                }
+            } else {
+                value.clone()
+            };
+            // Keywords beside a $ref refine the referenced schema
+            out.insert(key.clone(), normalized);
+        }
+
+        if map.get("nullable").and_then(|n| n.as_bool()) == Some(true) {
+            add_null_type(&mut out);
+        }
+        merge_single_branches(&mut out);
+        collapse_nullable_union(&mut out);
+        infer_type_from_default(&mut out);
+
+        Value::Object(out)
+    }
+
+    fn expand_ref(&mut self, reference: &str) -> Value {
+        let Some(target) = resolve_ref(self.root, reference) else {
+            // Unresolvable (or remote) reference: leave the field unconstrained
+            return Value::Object(Map::new());
+        };
+
+        if self.expanding.contains(reference) {
+            // Recursive definition: describe it without expanding it again
+            let mut stub = Map::new();
+            for key in ["type", "description"] {
+                if let Some(value) = target.get(key) {
+                    stub.insert(key.to_string(), value.clone());
+                }
+            }
+            return Value::Object(stub);
+        }
+
+        self.expanding.insert(reference.to_string());
+        let expanded = self.normalize(target);
+        self.expanding.remove(reference);
+        expanded
+    }
+}
+
+/// Resolve a local reference such as `#/$defs/Address` or `#/definitions/Address`
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(&pointer.replace("%24", "$").replace("%25", "%"))
+}
+
+/// `allOf: [X]`, `anyOf: [X]` and `oneOf: [X]` mean X; Pydantic emits them to attach a
+/// description to a referenced model.
+fn merge_single_branches(out: &mut Map<String, Value>) {
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        let single = match out.get(keyword) {
+            Some(Value::Array(branches)) if branches.len() == 1 => branches[0].clone(),
+            _ => continue,
+        };
+        out.remove(keyword);
+        if let Value::Object(branch) = single {
+            for (key, value) in branch {
+                // The outer schema's own keywords (usually its description) win
+                out.entry(key).or_insert(value);
+            }
+        }
+    }
+}
+
+/// `anyOf: [{type: T, ...}, {type: null}]` becomes `type: [T, null]`, which every provider
+/// understands and is how `Optional[T]` is usually meant.
+fn collapse_nullable_union(out: &mut Map<String, Value>) {
+    let Some(Value::Array(branches)) = out.get("anyOf") else {
+        return;
+    };
+    if branches.len() != 2 {
+        return;
+    }
+    let is_null = |b: &Value| b.get("type").and_then(|t| t.as_str()) == Some("null");
+    let other = match (is_null(&branches[0]), is_null(&branches[1])) {
+        (true, false) => branches[1].clone(),
+        (false, true) => branches[0].clone(),
+        _ => return,
+    };
+    // Only collapse when the other branch is a plain typed schema; unions of unions stay
+    let Value::Object(other) = other else {
+        return;
+    };
+    if !other.get("type").is_some_and(Value::is_string) {
+        return;
+    }
+
+    out.remove("anyOf");
+    for (key, value) in other {
+        out.entry(key).or_insert(value);
+    }
+    add_null_type(out);
+}
+
+fn add_null_type(out: &mut Map<String, Value>) {
+    let null = Value::String("null".to_string());
+    match out.get_mut("type") {
+        Some(Value::String(t)) if t != "null" => {
+            let t = Value::String(t.clone());
+            out.insert("type".to_string(), Value::Array(vec![t, null]));
+        }
+        Some(Value::Array(types)) if !types.contains(&null) => types.push(null),
+        _ => {}
+    }
+}
+
+/// Some servers give only a default; providers require a type, so infer one from it
+fn infer_type_from_default(out: &mut Map<String, Value>) {
+    let constrained = ["type", "$ref", "anyOf", "oneOf", "allOf", "enum", "const"]
+        .iter()
+        .any(|k| out.contains_key(*k));
+    if constrained {
+        return;
+    }
+    let inferred = match out.get("default") {
+        Some(Value::String(_)) => "string",
+        Some(Value::Number(n)) if n.is_i64() || n.is_u64() => "integer",
+        Some(Value::Number(_)) => "number",
+        Some(Value::Bool(_)) => "boolean",
+        Some(Value::Array(_)) => "array",
+        Some(Value::Object(_)) => "object",
+        // A null default says nothing about the type
+        Some(Value::Null) | None => return,
+    };
+    out.insert("type".to_string(), Value::String(inferred.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// FastMCP (Pydantic v2) tool with a nested model, an optional field and a list of models
+    #[test]
+    fn pydantic_models_are_inlined() {
+        let schema = json!({
+            "$defs": {
+                "Address": {
+                    "properties": {
+                        "street": { "title": "Street", "type": "string" },
+                        "zip": {
+                            "anyOf": [{ "type": "string" }, { "type": "null" }],
+                            "default": null,
+                            "title": "Zip"
+                        }
+                    },
+                    "required": ["street"],
+                    "title": "Address",
+                    "type": "object"
+                }
+            },
+            "properties": {
+                "name": { "title": "Name", "type": "string" },
+                "home": { "$ref": "#/$defs/Address", "description": "Where they live" },
+                "previous": {
+                    "items": { "$ref": "#/$defs/Address" },
+                    "title": "Previous",
+                    "type": "array"
+                }
+            },
+            "required": ["name", "home"],
+            "title": "create_contactArguments",
+            "type": "object"
+        });
+
+        let address = json!({
+            "type": "object",
+            "properties": {
+                "street": { "type": "string" },
+                "zip": { "type": ["string", "null"], "default": null }
+            },
+            "required": ["street"]
+        });
+        let mut home = address.clone();
+        home["description"] = json!("Where they live");
+
+        assert_eq!(
+            normalize_input_schema(&schema),
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "home": home,
+                    "previous": { "type": "array", "items": address }
+                },
+                "required": ["name", "home"]
+            })
+        );
+    }
+
+    /// Pydantic v1 and zod-to-json-schema put shared schemas under `definitions`
+    #[test]
+    fn definitions_refs_resolve() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "edits": { "type": "array", "items": { "$ref": "#/definitions/Edit" } }
+            },
+            "definitions": {
+                "Edit": {
+                    "type": "object",
+                    "properties": { "oldText": { "type": "string" }, "newText": { "type": "string" } },
+                    "required": ["oldText", "newText"],
+                    "additionalProperties": false
+                }
+            },
+            "$schema": "http://json-schema.org/draft-07/schema#"
+        });
+
+        assert_eq!(
+            normalize_input_schema(&schema)["properties"]["edits"]["items"],
+            json!({
+                "type": "object",
+                "properties": { "oldText": { "type": "string" }, "newText": { "type": "string" } },
+                "required": ["oldText", "newText"],
+                "additionalProperties": false
+            })
+        );
+    }
+
+    /// Discriminated unions keep every branch
+    #[test]
+    fn unions_are_preserved() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "block": {
+                    "oneOf": [
+                        { "$ref": "#/$defs/Text" },
+                        { "$ref": "#/$defs/SearchReplace" }
+                    ]
+                }
+            },
+            "$defs": {
+                "Text": {
+                    "type": "object",
+                    "properties": { "kind": { "const": "text" }, "text": { "type": "string" } }
+                },
+                "SearchReplace": {
+                    "type": "object",
+                    "properties": {
+                        "kind": { "const": "replace" },
+                        "search": { "type": "string" },
+                        "replace": { "type": "string" }
+                    }
+                }
+            }
+        });
+
+        let block = &normalize_input_schema(&schema)["properties"]["block"];
+        let branches = block["oneOf"].as_array().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(
+            branches[0]["properties"]["kind"],
+            json!({ "const": "text" })
+        );
+        assert_eq!(
+            branches[1]["properties"]["search"],
+            json!({ "type": "string" })
+        );
+    }
+
+    #[test]
+    fn recursive_definitions_terminate() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "root": { "$ref": "#/$defs/Node" } },
+            "$defs": {
+                "Node": {
+                    "type": "object",
+                    "description": "A tree node",
+                    "properties": {
+                        "label": { "type": "string" },
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/Node" } }
+                    }
+                }
+            }
+        });
+
+        let root = &normalize_input_schema(&schema)["properties"]["root"];
+        assert_eq!(root["properties"]["label"], json!({ "type": "string" }));
+        assert_eq!(
+            root["properties"]["children"]["items"],
+            json!({ "type": "object", "description": "A tree node" })
+        );
+    }
+
+    #[test]
+    fn self_referencing_root_terminates() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "next": { "$ref": "#" } }
+        });
+        let normalized = normalize_input_schema(&schema);
+        assert_eq!(normalized["properties"]["next"]["type"], json!("object"));
+    }
+
+    #[test]
+    fn single_branch_all_of_merges_into_parent() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "mode": { "allOf": [{ "$ref": "#/$defs/Mode" }], "description": "How to run" }
+            },
+            "$defs": { "Mode": { "type": "string", "enum": ["fast", "slow"], "title": "Mode" } }
+        });
+        assert_eq!(
+            normalize_input_schema(&schema)["properties"]["mode"],
+            json!({ "type": "string", "enum": ["fast", "slow"], "description": "How to run" })
+        );
+    }
+
+    #[test]
+    fn openapi_nullable_becomes_type_union() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "limit": { "type": "integer", "nullable": true } }
+        });
+        assert_eq!(
+            normalize_input_schema(&schema)["properties"]["limit"],
+            json!({ "type": ["integer", "null"] })
+        );
+    }
+
+    #[test]
+    fn type_is_inferred_from_default() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "depth": { "default": 3 }, "note": { "default": null } }
+        });
+        let normalized = normalize_input_schema(&schema);
+        assert_eq!(
+            normalized["properties"]["depth"],
+            json!({ "type": "integer", "default": 3 })
+        );
+        assert_eq!(normalized["properties"]["note"], json!({ "default": null }));
+    }
+
+    #[test]
+    fn invalid_property_names_are_dropped() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "ok_name": { "type": "string" }, "bad name!": { "type": "string" } }
+        });
+        let normalized = normalize_input_schema(&schema);
+        assert!(normalized["properties"].get("ok_name").is_some());
+        assert!(normalized["properties"].get("bad name!").is_none());
+    }
+
+    #[test]
+    fn empty_schemas_accept_any_object() {
+        for schema in [json!({}), json!(true), json!("garbage")] {
+            assert_eq!(
+                normalize_input_schema(&schema),
+                json!({ "type": "object", "properties": {} })
+            );
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::cron::CronSchedule;
+use crate::types::{ScheduledTask, TaskRun};
+
+/// How often the scheduler wakes up to start due tasks. Cron expressions have minute
+/// resolution, so this keeps runs within half a minute of their slot.
+pub(crate) const SCHEDULER_TICK_MS: u64 = 30_000;
+
+/// Request body that runs `scheduler_tick`, the handler's unit variant
+pub(crate) fn tick_body() -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!("SchedulerTick")).unwrap()
+}
+
+/// Runs kept in a task's history
+const MAX_RUNS: usize = 20;
+
+/// Check a task's timing and work out its first run after `now`
+pub(crate) fn first_run(cron: Option<&str>, run_at: Option<u64>, now: u64) -> Result<u64, String> {
+    match (cron, run_at) {
+        (Some(_), Some(_)) => {
+            Err("A task has either a cron expression or a runAt time, not both".to_string())
+        }
+        (Some(expression), None) => CronSchedule::parse(expression)?
+            .next_after(now)
+            .ok_or_else(|| format!("Cron expression '{}' never fires", expression)),
+        (None, Some(run_at)) if run_at <= now => Err("runAt is in the past".to_string()),
+        (None, Some(run_at)) => Ok(run_at),
+        (None, None) => Err("A task needs a cron expression or a runAt time".to_string()),
+    }
+}
+
+/// When the task should run next once its current slot has been taken; one-shot tasks
+/// never run again
+pub(crate) fn next_run_after(task: &ScheduledTask, now: u64) -> Option<u64> {
+    task.cron
+        .as_deref()
+        .and_then(|expression| CronSchedule::parse(expression).ok())
+        .and_then(|schedule| schedule.next_after(now))
+}
+
+pub(crate) fn is_due(task: &ScheduledTask, now: u64) -> bool {
+    task.enabled && task.next_run.is_some_and(|next| next <= now)
+}
+
+/// Add or update a run in the task's history, dropping the oldest beyond the cap
+pub(crate) fn record_run(task: &mut ScheduledTask, run: TaskRun) {
+    match task.runs.iter_mut().find(|r| r.id == run.id) {
+        Some(existing) => *existing = run,
+        None => task.runs.push(run),
+    }
+    if task.runs.len() > MAX_RUNS {
+        let excess = task.runs.len() - MAX_RUNS;
+        task.runs.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    enum Request {
+        SchedulerTick,
+    }
+
+    #[test]
+    fn tick_body_is_the_handlers_unit_variant() {
+        let request: Request = serde_json::from_slice(&tick_body()).unwrap();
+        assert_eq!(request, Request::SchedulerTick);
+    }
+}
@@ -0,0 +1,703 @@
+use serde_json::{json, Value};
+
+use crate::types::{Conversation, Message, ToolCall, ToolResult};
+
+pub(crate) const FORMAT_MARKDOWN: &str = "markdown";
+pub(crate) const FORMAT_ANTHROPIC_JSONL: &str = "anthropic-jsonl";
+pub(crate) const FORMAT_OPENAI_JSONL: &str = "openai-jsonl";
+
+/// A conversation read back from a transcript
+#[derive(Debug, PartialEq)]
+pub(crate) struct ImportedTranscript {
+    pub(crate) title: Option<String>,
+    pub(crate) messages: Vec<Message>,
+}
+
+/// Render conversations in a transcript format. JSONL formats hold one conversation per line;
+/// Markdown separates conversations with a horizontal rule.
+pub(crate) fn export(conversations: &[Conversation], format: &str) -> Result<String, String> {
+    match format {
+        FORMAT_MARKDOWN => Ok(conversations
+            .iter()
+            .map(to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n---\n\n")),
+        FORMAT_ANTHROPIC_JSONL | FORMAT_OPENAI_JSONL => {
+            let mut out = String::new();
+            for conversation in conversations {
+                let messages = if format == FORMAT_ANTHROPIC_JSONL {
+                    to_anthropic(&conversation.messages)
+                } else {
+                    to_openai(&conversation.messages)
+                };
+                let line = json!({
+                    "id": conversation.id,
+                    "title": conversation.title,
+                    "messages": messages,
+                });
+                out.push_str(&line.to_string());
+                out.push('\n');
+            }
+            Ok(out)
+        }
+        _ => Err(unknown_format(format)),
+    }
+}
+
+/// Parse a transcript into conversations; `now` stamps the imported messages
+pub(crate) fn import(
+    content: &str,
+    format: &str,
+    now: u64,
+) -> Result<Vec<ImportedTranscript>, String> {
+    let transcripts = match format {
+        FORMAT_MARKDOWN => from_markdown(content, now)?,
+        FORMAT_ANTHROPIC_JSONL | FORMAT_OPENAI_JSONL => {
+            let mut transcripts = Vec::new();
+            for (i, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: Value = serde_json::from_str(line)
+                    .map_err(|e| format!("Line {}: invalid JSON: {}", i + 1, e))?;
+                let raw_messages = record
+                    .get("messages")
+                    .and_then(|m| m.as_array())
+                    .ok_or_else(|| format!("Line {}: missing \"messages\" array", i + 1))?;
+                let messages = if format == FORMAT_ANTHROPIC_JSONL {
+                    from_anthropic(raw_messages, now)
+                } else {
+                    from_openai(raw_messages, now)
+                }
+                .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                transcripts.push(ImportedTranscript {
+                    title: record
+                        .get("title")
+                        .and_then(|t| t.as_str())
+                        .map(String::from),
+                    messages,
+                });
+            }
+            transcripts
+        }
+        _ => return Err(unknown_format(format)),
+    };
+
+    if transcripts.iter().all(|t| t.messages.is_empty()) {
+        return Err("The transcript contains no messages".to_string());
+    }
+    Ok(transcripts
+        .into_iter()
+        .filter(|t| !t.messages.is_empty())
+        .collect())
+}
+
+fn unknown_format(format: &str) -> String {
+    format!(
+        "Unknown transcript format: {} (expected one of: {}, {}, {})",
+        format, FORMAT_MARKDOWN, FORMAT_ANTHROPIC_JSONL, FORMAT_OPENAI_JSONL
+    )
+}
+
+fn tool_calls(message: &Message) -> Vec<ToolCall> {
+    message
+        .tool_calls_json
+        .as_ref()
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+fn tool_results(message: &Message) -> Vec<ToolResult> {
+    message
+        .tool_results_json
+        .as_ref()
+        .and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+// Tool call parameters are stored as a JSON string; formats that want an object get one
+fn parameters_value(parameters: &str) -> Value {
+    serde_json::from_str(parameters).unwrap_or_else(|_| json!({}))
+}
+
+fn message(role: &str, content: String, now: u64) -> Message {
+    Message {
+        role: role.to_string(),
+        content,
+        tool_calls_json: None,
+        tool_results_json: None,
+        timestamp: now,
+    }
+}
+
+fn with_tool_calls(mut message: Message, calls: &[ToolCall]) -> Message {
+    if !calls.is_empty() {
+        message.tool_calls_json = serde_json::to_string(calls).ok();
+    }
+    message
+}
+
+fn tool_message(results: &[ToolResult], now: u64) -> Message {
+    Message {
+        tool_results_json: serde_json::to_string(results).ok(),
+        ..message("tool", String::new(), now)
+    }
+}
+
+fn to_anthropic(messages: &[Message]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| {
+            if message.role == "tool" {
+                let blocks: Vec<Value> = tool_results(message)
+                    .into_iter()
+                    .map(|r| {
+                        json!({
+                            "type": "tool_result",
+                            "tool_use_id": r.tool_call_id,
+                            "content": r.result,
+                            "is_error": r.is_error,
+                        })
+                    })
+                    .collect();
+                return json!({ "role": "user", "content": blocks });
+            }
+
+            let mut blocks = Vec::new();
+            if !message.content.is_empty() {
+                blocks.push(json!({ "type": "text", "text": message.content }));
+            }
+            for call in tool_calls(message) {
+                blocks.push(json!({
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.tool_name,
+                    "input": parameters_value(&call.parameters),
+                }));
+            }
+            let role = if message.role == "assistant" {
+                "assistant"
+            } else {
+                "user"
+            };
+            json!({ "role": role, "content": blocks })
+        })
+        .collect()
+}
+
+fn from_anthropic(raw: &[Value], now: u64) -> Result<Vec<Message>, String> {
+    let mut messages = Vec::new();
+    for entry in raw {
+        let role = entry
+            .get("role")
+            .and_then(|r| r.as_str())
+            .ok_or("message without a role")?;
+        if role != "user" && role != "assistant" {
+            return Err(format!("unsupported role: {}", role));
+        }
+        // Content may be a plain string or a list of blocks
+        let blocks = match entry.get("content") {
+            Some(Value::String(text)) => vec![json!({ "type": "text", "text": text })],
+            Some(Value::Array(blocks)) => blocks.clone(),
+            _ => return Err("message without content".to_string()),
+        };
+
+        let mut text = Vec::new();
+        let mut calls = Vec::new();
+        let mut results = Vec::new();
+        for block in &blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => {
+                    if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+                        text.push(t.to_string());
+                    }
+                }
+                Some("tool_use") => calls.push(ToolCall {
+                    id: string_field(block, "id"),
+                    tool_name: string_field(block, "name"),
+                    parameters: block
+                        .get("input")
+                        .map(|i| i.to_string())
+                        .unwrap_or_else(|| "{}".to_string()),
+                }),
+                Some("tool_result") => results.push(ToolResult {
+                    tool_call_id: string_field(block, "tool_use_id"),
+                    result: anthropic_result_text(block.get("content")),
+                    is_error: block
+                        .get("is_error")
+                        .and_then(|e| e.as_bool())
+                        .unwrap_or(false),
+                }),
+                // Images and other media have no place in Spider's text transcript
+                _ => {}
+            }
+        }
+
+        if !results.is_empty() {
+            messages.push(tool_message(&results, now));
+        }
+        if !text.is_empty() || !calls.is_empty() {
+            messages.push(with_tool_calls(message(role, text.join("\n"), now), &calls));
+        }
+    }
+    Ok(messages)
+}
+
+// tool_result content is either a string or a list of text blocks
+fn anthropic_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn to_openai(messages: &[Message]) -> Vec<Value> {
+    let mut out = Vec::new();
+    for message in messages {
+        match message.role.as_str() {
+            // OpenAI expects one message per tool result
+            "tool" => {
+                for result in tool_results(message) {
+                    out.push(json!({
+                        "role": "tool",
+                        "tool_call_id": result.tool_call_id,
+                        "content": result.result,
+                    }));
+                }
+            }
+            "assistant" => {
+                let calls = tool_calls(message);
+                let mut entry = json!({
+                    "role": "assistant",
+                    "content": if message.content.is_empty() && !calls.is_empty() {
+                        Value::Null
+                    } else {
+                        Value::String(message.content.clone())
+                    },
+                });
+                if !calls.is_empty() {
+                    entry["tool_calls"] = calls
+                        .into_iter()
+                        .map(|call| {
+                            json!({
+                                "id": call.id,
+                                "type": "function",
+                                "function": {
+                                    "name": call.tool_name,
+                                    "arguments": call.parameters,
+                                },
+                            })
+                        })
+                        .collect();
+                }
+                out.push(entry);
+            }
+            role => out.push(json!({ "role": role, "content": message.content })),
+        }
+    }
+    out
+}
+
+fn from_openai(raw: &[Value], now: u64) -> Result<Vec<Message>, String> {
+    let mut messages: Vec<Message> = Vec::new();
+    for entry in raw {
+        let role = entry
+            .get("role")
+            .and_then(|r| r.as_str())
+            .ok_or("message without a role")?;
+        let content = match entry.get("content") {
+            Some(Value::String(text)) => text.clone(),
+            // Content parts: keep the text ones
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        };
+
+        match role {
+            "tool" => {
+                let result = ToolResult {
+                    tool_call_id: string_field(entry, "tool_call_id"),
+                    result: content,
+                    is_error: false,
+                };
+                // Consecutive tool messages answer one assistant turn; Spider keeps them together
+                match messages.last_mut() {
+                    Some(last) if last.role == "tool" => {
+                        let mut results = tool_results(last);
+                        results.push(result);
+                        last.tool_results_json = serde_json::to_string(&results).ok();
+                    }
+                    _ => messages.push(tool_message(&[result], now)),
+                }
+            }
+            "assistant" => {
+                let calls: Vec<ToolCall> = entry
+                    .get("tool_calls")
+                    .and_then(|c| c.as_array())
+                    .map(|calls| {
+                        calls
+                            .iter()
+                            .map(|call| ToolCall {
+                                id: string_field(call, "id"),
+                                tool_name: call
+                                    .pointer("/function/name")
+                                    .and_then(|n| n.as_str())
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                parameters: call
+                                    .pointer("/function/arguments")
+                                    .and_then(|a| a.as_str())
+                                    .unwrap_or("{}")
+                                    .to_string(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                messages.push(with_tool_calls(message("assistant", content, now), &calls));
+            }
+            // System prompts have no slot in a Spider conversation; keep them visible as user text
+            "system" | "developer" => {
+                messages.push(message("user", format!("[System] {}", content), now))
+            }
+            "user" => messages.push(message("user", content, now)),
+            other => return Err(format!("unsupported role: {}", other)),
+        }
+    }
+    Ok(messages)
+}
+
+fn string_field(value: &Value, field: &str) -> String {
+    value
+        .get(field)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+const HEADING_USER: &str = "## User";
+const HEADING_ASSISTANT: &str = "## Assistant";
+const HEADING_TOOL: &str = "## Tool results";
+
+// A fence longer than any backtick run in the text, so the text can't close it early
+fn fence_for(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn push_fenced(out: &mut String, language: &str, text: &str) {
+    let fence = fence_for(text);
+    out.push_str(&format!("{}{}\n{}\n{}\n\n", fence, language, text, fence));
+}
+
+fn to_markdown(conversation: &Conversation) -> String {
+    let mut out = format!(
+        "# {}\n\n",
+        conversation
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Conversation {}", conversation.id))
+    );
+    out.push_str(&format!(
+        "- Conversation: {}\n- Started: {}\n- Client: {}\n- Provider: {}\n\n",
+        conversation.id,
+        conversation.metadata.start_time,
+        conversation.metadata.client,
+        conversation.llm_provider
+    ));
+
+    for message in &conversation.messages {
+        match message.role.as_str() {
+            "tool" => {
+                out.push_str(HEADING_TOOL);
+                out.push_str("\n\n");
+                for result in tool_results(message) {
+                    let label = if result.is_error { "Error" } else { "Result" };
+                    out.push_str(&format!("**{}** `{}`\n\n", label, result.tool_call_id));
+                    push_fenced(&mut out, "", &result.result);
+                }
+            }
+            role => {
+                out.push_str(if role == "assistant" {
+                    HEADING_ASSISTANT
+                } else {
+                    HEADING_USER
+                });
+                out.push_str("\n\n");
+                if !message.content.is_empty() {
+                    out.push_str(message.content.trim_end());
+                    out.push_str("\n\n");
+                }
+                for call in tool_calls(message) {
+                    out.push_str(&format!(
+                        "**Tool call** `{}` `{}`\n\n",
+                        call.tool_name, call.id
+                    ));
+                    push_fenced(&mut out, "json", &call.parameters);
+                }
+            }
+        }
+    }
+    out
+}
+
+// Backtick-quoted words of a line such as "**Tool call** `name` `id`"
+fn code_spans(line: &str) -> Vec<String> {
+    line.split('`')
+        .skip(1)
+        .step_by(2)
+        .map(String::from)
+        .collect()
+}
+
+fn from_markdown(content: &str, now: u64) -> Result<Vec<ImportedTranscript>, String> {
+    let mut transcripts: Vec<ImportedTranscript> = Vec::new();
+    let mut current: Option<Message> = None;
+    let mut calls: Vec<ToolCall> = Vec::new();
+    let mut results: Vec<ToolResult> = Vec::new();
+    let mut text: Vec<&str> = Vec::new();
+
+    // Close the message being read and attach what was collected for it
+    fn finish(
+        transcripts: &mut [ImportedTranscript],
+        current: &mut Option<Message>,
+        text: &mut Vec<&str>,
+        calls: &mut Vec<ToolCall>,
+        results: &mut Vec<ToolResult>,
+    ) {
+        let Some(mut message) = current.take() else {
+            text.clear();
+            return;
+        };
+        message.content = text.join("\n").trim().to_string();
+        text.clear();
+        if !calls.is_empty() {
+            message.tool_calls_json = serde_json::to_string(calls).ok();
+            calls.clear();
+        }
+        if !results.is_empty() {
+            message.tool_results_json = serde_json::to_string(results).ok();
+            results.clear();
+        }
+        if let Some(transcript) = transcripts.last_mut() {
+            transcript.messages.push(message);
+        }
+    }
+
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let heading = line.trim_end();
+        if let Some(title) = heading.strip_prefix("# ") {
+            // Drop the rule separating this conversation from the previous one
+            while text.last().is_some_and(|l| l.trim().is_empty()) {
+                text.pop();
+            }
+            if text.last().is_some_and(|l| l.trim() == "---") {
+                text.pop();
+            }
+            finish(
+                &mut transcripts,
+                &mut current,
+                &mut text,
+                &mut calls,
+                &mut results,
+            );
+            transcripts.push(ImportedTranscript {
+                title: Some(title.trim().to_string()),
+                messages: Vec::new(),
+            });
+            continue;
+        }
+        let role = match heading {
+            HEADING_USER => Some("user"),
+            HEADING_ASSISTANT => Some("assistant"),
+            HEADING_TOOL => Some("tool"),
+            _ => None,
+        };
+        if let Some(role) = role {
+            finish(
+                &mut transcripts,
+                &mut current,
+                &mut text,
+                &mut calls,
+                &mut results,
+            );
+            if transcripts.is_empty() {
+                transcripts.push(ImportedTranscript {
+                    title: None,
+                    messages: Vec::new(),
+                });
+            }
+            current = Some(message(role, String::new(), now));
+            continue;
+        }
+
+        let Some(ref message) = current else {
+            // Title block and metadata list before the first message
+            continue;
+        };
+        let is_call = line.starts_with("**Tool call**");
+        let is_result = line.starts_with("**Result**") || line.starts_with("**Error**");
+        if (is_call && message.role != "tool") || (is_result && message.role == "tool") {
+            let spans = code_spans(line);
+            // The fenced body follows, possibly after blank lines
+            let mut body = Vec::new();
+            let fence = lines
+                .by_ref()
+                .map(str::trim_end)
+                .find(|l| !l.is_empty())
+                .filter(|l| l.starts_with("```"))
+                .map(|l| l.chars().take_while(|c| *c == '`').collect::<String>())
+                .ok_or_else(|| format!("Expected a code block after: {}", line))?;
+            for body_line in lines.by_ref() {
+                if body_line.trim_end() == fence {
+                    break;
+                }
+                body.push(body_line);
+            }
+            let body = body.join("\n");
+            if is_call {
+                calls.push(ToolCall {
+                    tool_name: spans.first().cloned().unwrap_or_default(),
+                    id: spans.get(1).cloned().unwrap_or_default(),
+                    parameters: body,
+                });
+            } else {
+                results.push(ToolResult {
+                    tool_call_id: spans.first().cloned().unwrap_or_default(),
+                    result: body,
+                    is_error: line.starts_with("**Error**"),
+                });
+            }
+            continue;
+        }
+        text.push(line);
+    }
+    finish(
+        &mut transcripts,
+        &mut current,
+        &mut text,
+        &mut calls,
+        &mut results,
+    );
+    Ok(transcripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(messages: Vec<Message>) -> Conversation {
+        serde_json::from_value(json!({
+            "id": "conv-1",
+            "title": "Weather check",
+            "messages": messages,
+            "metadata": { "startTime": "2025-01-01T00:00:00Z", "client": "test", "fromStt": false },
+            "llmProvider": "anthropic",
+            "mcpServers": [],
+        }))
+        .unwrap()
+    }
+
+    fn sample() -> Vec<Message> {
+        let calls = vec![ToolCall {
+            id: "call_1".to_string(),
+            tool_name: "weather".to_string(),
+            parameters: r#"{"city":"Paris"}"#.to_string(),
+        }];
+        let results = vec![ToolResult {
+            tool_call_id: "call_1".to_string(),
+            result: "Sunny, with ``` fences".to_string(),
+            is_error: false,
+        }];
+        vec![
+            message("user", "What's the weather in Paris?".to_string(), 1),
+            with_tool_calls(message("assistant", "Let me check.".to_string(), 1), &calls),
+            tool_message(&results, 1),
+            message("assistant", "It is sunny.".to_string(), 1),
+        ]
+    }
+
+    #[test]
+    fn every_format_round_trips_messages_and_tools() {
+        let conv = conversation(sample());
+        for format in [FORMAT_MARKDOWN, FORMAT_ANTHROPIC_JSONL, FORMAT_OPENAI_JSONL] {
+            let exported = export(std::slice::from_ref(&conv), format).unwrap();
+            let imported = import(&exported, format, 1).unwrap();
+            assert_eq!(imported.len(), 1, "{}", format);
+            assert_eq!(imported[0].title.as_deref(), Some("Weather check"));
+            assert_eq!(imported[0].messages, sample(), "{}", format);
+        }
+    }
+
+    #[test]
+    fn anthropic_export_uses_content_blocks() {
+        let exported = export(&[conversation(sample())], FORMAT_ANTHROPIC_JSONL).unwrap();
+        let record: Value = serde_json::from_str(exported.lines().next().unwrap()).unwrap();
+        let messages = record["messages"].as_array().unwrap();
+        assert_eq!(messages[1]["content"][1]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][1]["input"]["city"], "Paris");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "call_1");
+    }
+
+    #[test]
+    fn openai_import_groups_consecutive_tool_messages() {
+        let line = json!({ "messages": [
+            { "role": "system", "content": "Be brief" },
+            { "role": "user", "content": "Hi" },
+            { "role": "assistant", "content": null, "tool_calls": [
+                { "id": "a", "type": "function", "function": { "name": "x", "arguments": "{}" } },
+                { "id": "b", "type": "function", "function": { "name": "y", "arguments": "{}" } }
+            ]},
+            { "role": "tool", "tool_call_id": "a", "content": "1" },
+            { "role": "tool", "tool_call_id": "b", "content": "2" }
+        ]})
+        .to_string();
+        let imported = import(&line, FORMAT_OPENAI_JSONL, 7).unwrap();
+        let messages = &imported[0].messages;
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].content, "[System] Be brief");
+        assert_eq!(tool_calls(&messages[2]).len(), 2);
+        assert_eq!(tool_results(&messages[3]).len(), 2);
+    }
+
+    #[test]
+    fn markdown_keeps_multiple_conversations_and_errors() {
+        let mut second = sample();
+        second[2].tool_results_json = Some(
+            serde_json::to_string(&[ToolResult {
+                tool_call_id: "call_1".to_string(),
+                result: "timeout".to_string(),
+                is_error: true,
+            }])
+            .unwrap(),
+        );
+        let exported = export(
+            &[conversation(sample()), conversation(second.clone())],
+            FORMAT_MARKDOWN,
+        )
+        .unwrap();
+        let imported = import(&exported, FORMAT_MARKDOWN, 1).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[1].messages, second);
+    }
+
+    #[test]
+    fn rejects_unknown_formats_and_empty_transcripts() {
+        assert!(import("{}", "csv", 0).is_err());
+        assert!(import("", FORMAT_OPENAI_JSONL, 0).is_err());
+        assert!(import(r#"{"messages":[{"role":"robot"}]}"#, FORMAT_OPENAI_JSONL, 0).is_err());
+    }
+}
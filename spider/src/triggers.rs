@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use hyperware_process_lib::http::{client::send_request_await_response, Method};
+
+use crate::types::{ChatResponse, JobStatus, TriggerResult};
+
+/// Characters of a trigger secret shown when listing triggers
+const SECRET_PREVIEW_CHARS: usize = 7;
+
+pub(crate) fn generate_secret() -> String {
+    format!("tr_{}", Uuid::new_v4().simple())
+}
+
+pub(crate) fn mask_secret(secret: &str) -> String {
+    let preview: String = secret.chars().take(SECRET_PREVIEW_CHARS).collect();
+    format!("{}…", preview)
+}
+
+/// Compare a presented secret with a trigger's without stopping at the first differing byte, so
+/// response times do not tell a caller how much of a guess was right
+pub(crate) fn secret_matches(presented: &str, secret: &str) -> bool {
+    let (presented, secret) = (presented.as_bytes(), secret.as_bytes());
+    presented.len() == secret.len()
+        && presented
+            .iter()
+            .zip(secret)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Callbacks must be plain HTTP(S) URLs
+pub(crate) fn validate_callback_url(url: &str) -> Result<(), String> {
+    let parsed =
+        url::Url::parse(url).map_err(|e| format!("Invalid callback URL {}: {}", url, e))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!(
+            "Callback URLs must use http or https, not {}",
+            scheme
+        )),
+    }
+}
+
+/// The outcome of a finished trigger run, as returned to the caller or its callback
+pub(crate) fn result(
+    trigger_id: &str,
+    run_id: String,
+    outcome: &Result<ChatResponse, String>,
+) -> TriggerResult {
+    let (status, conversation_id, response, error) = match outcome {
+        Ok(response) => (
+            JobStatus::Completed,
+            Some(response.conversation_id.clone()),
+            Some(response.response.content.clone()),
+            None,
+        ),
+        Err(e) => (JobStatus::Failed, None, None, Some(e.clone())),
+    };
+    TriggerResult {
+        trigger_id: trigger_id.to_string(),
+        run_id,
+        status,
+        conversation_id,
+        response,
+        error,
+    }
+}
+
+/// POST a run's result to the trigger's callback URL
+pub(crate) async fn post_callback(url: &str, result: &TriggerResult) -> Result<(), String> {
+    let mut headers = HashMap::new();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+
+    let url = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let body = serde_json::to_vec(result).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    let response = send_request_await_response(Method::POST, url, Some(headers), 30000, body)
+        .await
+        .map_err(|e| format!("HTTP request failed: {:?}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Callback returned {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Fill a prompt template from a webhook payload. `{{body}}` is the whole payload,
+/// `{{body.a.b}}` (or `{{body.items.0}}`) a value inside it and `{{trigger}}` the trigger's
+/// name. Strings are inserted as-is, other values as JSON and missing values as nothing.
+pub(crate) fn render(template: &str, trigger_name: &str, payload: &Value) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let placeholder = rest[start + 2..start + 2 + len].trim();
+        rendered.push_str(&resolve(placeholder, trigger_name, payload));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn resolve(placeholder: &str, trigger_name: &str, payload: &Value) -> String {
+    if placeholder == "trigger" {
+        return trigger_name.to_string();
+    }
+    let value = match placeholder {
+        "body" => Some(payload),
+        _ => placeholder.strip_prefix("body.").and_then(|path| {
+            path.split('.')
+                .try_fold(payload, |value, segment| match value {
+                    Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                    _ => value.get(segment),
+                })
+        }),
+    };
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(value @ (Value::Object(_) | Value::Array(_))) => {
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        }
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_must_match_exactly() {
+        let secret = generate_secret();
+        assert!(secret_matches(&secret, &secret));
+        assert!(!secret_matches(&secret[..secret.len() - 1], &secret));
+        assert!(!secret_matches(&format!("{}0", secret), &secret));
+        assert!(!secret_matches(&generate_secret(), &secret));
+        assert!(!secret_matches("", &secret));
+    }
+
+    #[test]
+    fn renders_paths_into_the_payload() {
+        let payload = json!({
+            "repository": { "name": "spider" },
+            "commits": [{ "message": "Fix build" }],
+            "failed": true,
+        });
+        let rendered = render(
+            "{{trigger}}: build of {{ body.repository.name }} failed={{body.failed}} after \
+             '{{body.commits.0.message}}'{{body.missing}}",
+            "ci",
+            &payload,
+        );
+        assert_eq!(
+            rendered,
+            "ci: build of spider failed=true after 'Fix build'"
+        );
+    }
+
+    #[test]
+    fn inserts_structured_values_as_json_and_keeps_unclosed_braces() {
+        let payload = json!({ "labels": ["a", "b"] });
+        assert_eq!(
+            render("Labels: {{body.labels}} {{oops", "t", &payload),
+            "Labels: [\n  \"a\",\n  \"b\"\n] {{oops"
+        );
+    }
+}
@@ -14,24 +14,61 @@ pub struct SpiderState {
     pub default_llm_provider: String,
     pub max_tokens: u32,
     pub temperature: f32,
+    #[serde(default)]
+    pub context_config: ContextConfig,
+    #[serde(default)]
+    pub fallback_chains: Vec<(String, Vec<String>)>, // primary provider -> providers tried in order
+    #[serde(default)]
+    pub key_selection: String, // "round-robin" or "least-used"
+    #[serde(default)]
+    pub federation_peers: Vec<FederationPeer>, // Remote nodes allowed to use this Spider
+    #[serde(default)]
+    pub process_grants: Vec<ProcessGrant>, // Local processes allowed to use process_request
+    #[serde(default)]
+    pub conversation_retention_days: u32, // Days a deleted conversation stays in the trash; 0 keeps it until deleted permanently
+    #[serde(default)]
+    pub summary_config: SummaryConfig,
+    #[serde(default)]
+    pub builtin_tools_config: BuiltinToolsConfig,
+    #[serde(default)]
+    pub chat_jobs: Vec<ChatJob>, // Background chat runs and their progress, oldest first
+    #[serde(default)]
+    pub agent_profiles: Vec<AgentProfile>,
+    #[serde(default)]
+    pub scheduled_tasks: Vec<ScheduledTask>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
     #[serde(skip)]
-    pub ws_connections: HashMap<u32, WsConnection>, // channel_id -> connection info
+    pub trigger_runs: HashMap<String, (String, ChatRequest)>, // run_id -> (trigger_id, request) awaiting a callback run
+    #[serde(skip)]
+    pub delegated_conversations: HashMap<String, Vec<String>>, // conversation_id -> child conversations from its running turn
+    #[serde(skip)]
+    pub job_requests: HashMap<String, ChatRequest>, // job_id -> request waiting to start
     #[serde(skip)]
-    pub pending_mcp_requests: HashMap<String, PendingMcpRequest>, // request_id -> pending request
+    pub job_subscribers: HashMap<String, Vec<u32>>, // job_id -> channels receiving its events
     #[serde(skip)]
-    pub tool_responses: HashMap<String, Value>, // request_id -> response received from MCP
+    pub ws_connections: HashMap<u32, WsConnection>, // channel_id -> connection info
+    #[serde(skip)]
+    pub tool_responses: HashMap<String, Result<Value, String>>, // request_id -> tool call outcome awaiting pickup
     #[serde(skip)]
     pub next_channel_id: u32,
     #[serde(skip)]
     pub chat_clients: HashMap<u32, ChatClient>, // channel_id -> chat client connection
     #[serde(skip)]
-    pub active_chat_cancellation: HashMap<u32, Arc<AtomicBool>>, // channel_id -> cancellation flag
+    pub active_chat_cancellation: HashMap<String, Arc<AtomicBool>>, // job_id -> cancellation flag
     #[serde(skip)]
     pub hypergrid_connections: HashMap<String, HypergridConnection>, // server_id -> hypergrid connection
     #[serde(skip)]
     pub show_trial_key_notification: bool, // Flag to show trial key notification popup
+    #[serde(skip)]
+    pub key_cursors: HashMap<String, usize>, // provider -> next round-robin position
+    #[serde(skip)]
+    pub mcp_health: HashMap<String, McpHealth>, // server_id -> health-check state
+    #[serde(skip)]
+    pub audit_log: AuditLog, // Audit drive and its segments, opened at init
 }
 
+// One MCP client session over a WebSocket. Requests awaiting a response live and die with it.
 #[derive(Clone, Debug)]
 pub(crate) struct WsConnection {
     pub(crate) server_id: String,
@@ -39,6 +76,7 @@ pub(crate) struct WsConnection {
     pub(crate) channel_id: u32,
     pub(crate) tools: Vec<Tool>,
     pub(crate) initialized: bool,
+    pub(crate) pending: HashMap<String, PendingMcpRequest>, // request_id -> request sent on this session
 }
 
 #[derive(Clone, Debug)]
@@ -54,6 +92,26 @@ pub(crate) enum McpRequestType {
     Initialize,
     ToolsList,
     ToolCall { tool_name: String },
+    Ping,
+}
+
+// Health-check and reconnect bookkeeping for one MCP server
+#[derive(Clone, Debug, Default)]
+pub(crate) struct McpHealth {
+    pub(crate) reconnect_attempts: u32,
+    pub(crate) next_retry_at: Option<std::time::Instant>,
+    pub(crate) connecting_since: Option<std::time::Instant>,
+    pub(crate) last_ping_at: Option<std::time::Instant>,
+    pub(crate) outstanding_ping: Option<String>, // request id of the unanswered ping
+    pub(crate) missed_pings: u32,
+}
+
+// The audit drive, created once at startup, and the segments written to it
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditLog {
+    pub(crate) drive_path: String, // empty until the drive has been created
+    pub(crate) segments: Vec<String>, // segment paths, oldest first
+    pub(crate) current_len: u64,   // bytes in the newest segment
 }
 
 #[derive(Clone, Debug)]
@@ -62,18 +120,295 @@ pub(crate) struct ChatClient {
     pub(crate) api_key: String,
     pub(crate) conversation_id: Option<String>,
     pub(crate) connected_at: u64,
+    pub(crate) job_id: Option<String>, // Latest chat job started from this channel
+    pub(crate) watched_conversations: Vec<String>, // Conversations whose runs reach this channel
+    pub(crate) watch_all_conversations: bool,
+}
+
+// A chat run executed in the background. Its events are kept so clients that reconnect can
+// catch up on what they missed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ChatJob {
+    pub(crate) id: String,
+    pub(crate) owner: String, // Name of the Spider key that started the job, for display
+    #[serde(default, rename = "ownerId")]
+    pub(crate) owner_id: String, // Hash of that key; names are not unique, so access checks use this
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: Option<String>,
+    pub(crate) status: JobStatus,
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: u64,
+    #[serde(rename = "updatedAt")]
+    pub(crate) updated_at: u64,
+    pub(crate) events: Vec<JobEvent>,
+    pub(crate) result: Option<ChatResponse>,
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct JobEvent {
+    pub(crate) seq: u32,
+    pub(crate) timestamp: u64,
+    pub(crate) event: String, // Serialized WsServerMessage, as pushed to subscribers
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct GetJobStatusRequest {
+    #[serde(rename = "jobId")]
+    pub(crate) job_id: String,
+    #[serde(rename = "afterSeq")]
+    pub(crate) after_seq: Option<u32>, // Only events newer than this are returned
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// Reusable settings for agent runs that no client is present to choose
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AgentProfile {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: Option<String>, // Spider's default provider when unset
+    pub(crate) model: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>, // Every connected server when unset
+    #[serde(default, rename = "builtinTools")]
+    pub(crate) builtin_tools: Vec<String>, // Spider's own tools this profile may use
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SaveAgentProfileRequest {
+    pub(crate) id: Option<String>, // Updates the profile with this id; creates one when absent
+    pub(crate) name: String,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: Option<String>,
+    pub(crate) model: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>,
+    #[serde(default, rename = "builtinTools")]
+    pub(crate) builtin_tools: Option<Vec<String>>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct DeleteAgentProfileRequest {
+    #[serde(rename = "profileId")]
+    pub(crate) profile_id: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ListAgentProfilesRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// A prompt run on a cron schedule or once at a given time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ScheduledTask {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) prompt: String,
+    #[serde(rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>, // Overrides the profile's servers when set
+    pub(crate) cron: Option<String>, // Five-field expression in UTC
+    #[serde(rename = "runAt")]
+    pub(crate) run_at: Option<u64>, // One-shot time in unix seconds, used when there is no cron
+    pub(crate) enabled: bool,
+    #[serde(rename = "notifyWatchers")]
+    pub(crate) notify_watchers: bool, // Push each run to WebSocket clients watching all conversations
+    #[serde(rename = "notifyProcess")]
+    pub(crate) notify_process: Option<String>, // Address of a process sent each run as a TaskNotification
+    #[serde(rename = "nextRun")]
+    pub(crate) next_run: Option<u64>,
+    #[serde(rename = "createdBy")]
+    pub(crate) created_by: String,
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: u64,
+    pub(crate) runs: Vec<TaskRun>, // Most recent last
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TaskRun {
+    pub(crate) id: String,
+    #[serde(rename = "startedAt")]
+    pub(crate) started_at: u64,
+    #[serde(rename = "finishedAt")]
+    pub(crate) finished_at: Option<u64>,
+    pub(crate) status: JobStatus,
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: Option<String>,
+    pub(crate) response: Option<String>, // Final assistant message
+    pub(crate) error: Option<String>,
+}
+
+// Body sent to a task's notifyProcess after each run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TaskNotification {
+    #[serde(rename = "taskId")]
+    pub(crate) task_id: String,
+    #[serde(rename = "taskName")]
+    pub(crate) task_name: String,
+    pub(crate) run: TaskRun,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SaveScheduledTaskRequest {
+    #[serde(rename = "taskId")]
+    pub(crate) task_id: Option<String>, // Updates the task with this id, keeping its run history
+    pub(crate) name: String,
+    pub(crate) prompt: String,
+    #[serde(rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>,
+    pub(crate) cron: Option<String>,
+    #[serde(rename = "runAt")]
+    pub(crate) run_at: Option<u64>,
+    pub(crate) enabled: Option<bool>, // Defaults to true
+    #[serde(rename = "notifyWatchers")]
+    pub(crate) notify_watchers: Option<bool>,
+    #[serde(rename = "notifyProcess")]
+    pub(crate) notify_process: Option<String>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct DeleteScheduledTaskRequest {
+    #[serde(rename = "taskId")]
+    pub(crate) task_id: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ListScheduledTasksRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// A webhook that runs an agent when something POSTs to /api/trigger with its secret
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Trigger {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) secret: String, // Sent as `Authorization: Bearer <secret>`; masked when listed
+    #[serde(rename = "promptTemplate")]
+    pub(crate) prompt_template: String, // See triggers::render for placeholders
+    #[serde(rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
+    #[serde(rename = "callbackUrl")]
+    pub(crate) callback_url: Option<String>, // Results are POSTed here instead of returned
+    pub(crate) enabled: bool,
+    #[serde(rename = "createdBy")]
+    pub(crate) created_by: String,
+    #[serde(rename = "createdAt")]
+    pub(crate) created_at: u64,
+    #[serde(rename = "lastFired")]
+    pub(crate) last_fired: Option<u64>,
+    #[serde(rename = "fireCount")]
+    pub(crate) fire_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SaveTriggerRequest {
+    #[serde(rename = "triggerId")]
+    pub(crate) trigger_id: Option<String>, // Updates the trigger with this id; creates one when absent
+    pub(crate) name: String,
+    #[serde(rename = "promptTemplate")]
+    pub(crate) prompt_template: String,
+    #[serde(rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
+    #[serde(rename = "callbackUrl")]
+    pub(crate) callback_url: Option<String>,
+    pub(crate) enabled: Option<bool>, // Defaults to true
+    #[serde(rename = "rotateSecret")]
+    pub(crate) rotate_secret: Option<bool>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct DeleteTriggerRequest {
+    #[serde(rename = "triggerId")]
+    pub(crate) trigger_id: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ListTriggersRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// Returned to the caller of /api/trigger, or POSTed to the callback URL once the run finishes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TriggerResult {
+    #[serde(rename = "triggerId")]
+    pub(crate) trigger_id: String,
+    #[serde(rename = "runId")]
+    pub(crate) run_id: String,
+    pub(crate) status: JobStatus, // Queued when the result will go to the callback
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: Option<String>,
+    pub(crate) response: Option<String>,
+    pub(crate) error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct ApiKey {
+    #[serde(default)]
+    pub(crate) id: String,
     pub(crate) provider: String,
     pub(crate) key: String,
     pub(crate) created_at: u64,
     pub(crate) last_used: Option<u64>,
+    #[serde(default)]
+    pub(crate) usage_count: u64,
+    #[serde(default)]
+    pub(crate) last_error: Option<String>,
+    // OAuth-only fields. Like the key itself, the refresh token is only base64-encoded by
+    // `encrypt_key`, so it is stored in plaintext in process state.
+    #[serde(default)]
+    pub(crate) refresh_token: Option<String>,
+    #[serde(default)]
+    pub(crate) expires_at: Option<u64>,
+    #[serde(default)]
+    pub(crate) last_refreshed: Option<u64>,
+    #[serde(default)]
+    pub(crate) delegated: bool, // Linked to a single Spider key and excluded from the shared pool
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct ApiKeyInfo {
+    pub(crate) id: String,
     pub(crate) provider: String,
     #[serde(rename = "createdAt")]
     pub(crate) created_at: u64,
@@ -81,6 +416,10 @@ pub(crate) struct ApiKeyInfo {
     pub(crate) last_used: Option<u64>,
     #[serde(rename = "keyPreview")]
     pub(crate) key_preview: String,
+    #[serde(rename = "usageCount")]
+    pub(crate) usage_count: u64,
+    #[serde(rename = "lastError")]
+    pub(crate) last_error: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -90,6 +429,10 @@ pub(crate) struct SpiderApiKey {
     pub(crate) permissions: Vec<String>,
     #[serde(rename = "createdAt")]
     pub(crate) created_at: u64,
+    #[serde(default, rename = "oauthKeyId")]
+    pub(crate) oauth_key_id: Option<String>, // Delegated Claude login used for this key's chats
+    #[serde(default, rename = "toolScopes")]
+    pub(crate) tool_scopes: Option<Vec<String>>, // Tools reachable through Spider's MCP endpoint; None means all
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -99,12 +442,37 @@ pub(crate) struct McpServer {
     pub(crate) transport: TransportConfig,
     pub(crate) tools: Vec<Tool>,
     pub(crate) connected: bool,
+    #[serde(default)]
+    pub(crate) state: McpConnectionState,
+    #[serde(default, rename = "lastError")]
+    pub(crate) last_error: Option<String>, // Why the server last failed to connect or stay healthy
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum McpConnectionState {
+    #[default]
+    Disconnected, // Not connected and not retrying, e.g. after disconnect_mcp_server
+    Connecting,
+    Ready,
+    Degraded, // Connected but missing health-check pings
+    Failed,   // Connection lost or refused; retried with backoff
+}
+
+impl McpConnectionState {
+    /// Whether tools can be called in this state
+    pub(crate) fn is_usable(self) -> bool {
+        matches!(
+            self,
+            McpConnectionState::Ready | McpConnectionState::Degraded
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct TransportConfig {
     #[serde(rename = "transportType")]
-    pub(crate) transport_type: String, // "stdio", "http", "websocket", or "hypergrid"
+    pub(crate) transport_type: String, // "stdio", "http", "websocket", "hypergrid", or "spider-remote"
     pub(crate) command: Option<String>,
     pub(crate) args: Option<Vec<String>>,
     pub(crate) url: Option<String>,
@@ -115,6 +483,9 @@ pub(crate) struct TransportConfig {
     pub(crate) hypergrid_client_id: Option<String>,
     #[serde(rename = "hypergridNode")]
     pub(crate) hypergrid_node: Option<String>,
+    // Spider-remote-specific fields
+    #[serde(default, rename = "remoteNode")]
+    pub(crate) remote_node: Option<String>, // Node whose Spider serves the tools
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -124,6 +495,8 @@ pub(crate) struct Tool {
     pub(crate) parameters: String, // Deprecated: use input_schema_json instead
     #[serde(rename = "inputSchema")]
     pub(crate) input_schema_json: Option<String>, // Complete JSON schema as string including $defs, annotations, etc.
+    #[serde(default, rename = "outputSchema")]
+    pub(crate) output_schema_json: Option<String>, // Schema the tool's structuredContent must match, if it declares one
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -137,6 +510,51 @@ pub(crate) struct Conversation {
     pub(crate) mcp_servers: Vec<String>,
     #[serde(rename = "mcpServersDetails", skip_serializing_if = "Option::is_none")]
     pub(crate) mcp_servers_details: Option<Vec<McpServerDetails>>,
+    #[serde(default)]
+    pub(crate) title: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default, rename = "deletedAt")]
+    pub(crate) deleted_at: Option<u64>, // Unix seconds; set while the conversation sits in the trash
+    #[serde(default)]
+    pub(crate) summary: Option<String>,
+    #[serde(default, rename = "summaryCovers")]
+    pub(crate) summary_covers: u32, // Messages already folded into the summary
+    #[serde(default)]
+    pub(crate) nodes: Vec<MessageNode>, // Every message on every branch; `messages` is the active branch
+    #[serde(default, rename = "activeLeaf")]
+    pub(crate) active_leaf: Option<String>,
+    #[serde(default, rename = "parentConversationId")]
+    pub(crate) parent_conversation_id: Option<String>, // Set on conversations run by the delegate tool
+    #[serde(default, rename = "childConversationIds")]
+    pub(crate) child_conversation_ids: Vec<String>,
+}
+
+// One message in a conversation's tree. Branches share the messages before the point they diverge.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MessageNode {
+    pub(crate) id: String,
+    #[serde(rename = "parentId")]
+    pub(crate) parent_id: Option<String>,
+    pub(crate) message: Message,
+}
+
+// Listing entry for a conversation, without its message bodies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ConversationInfo {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) summary: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) metadata: ConversationMetadata,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: String,
+    #[serde(rename = "messageCount")]
+    pub(crate) message_count: u32,
+    #[serde(rename = "deletedAt")]
+    pub(crate) deleted_at: Option<u64>,
+    #[serde(rename = "parentConversationId")]
+    pub(crate) parent_conversation_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -183,12 +601,16 @@ pub(crate) struct ToolCall {
 pub(crate) struct ToolResult {
     pub(crate) tool_call_id: String,
     pub(crate) result: String,
+    #[serde(default)]
+    pub(crate) is_error: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub(crate) struct SetApiKeyRequest {
     pub(crate) provider: String,
     pub(crate) key: String,
+    #[serde(rename = "addToPool")]
+    pub(crate) add_to_pool: Option<bool>, // Keep existing keys for this provider instead of replacing them
     #[serde(rename = "authKey")]
     pub(crate) auth_key: String,
 }
@@ -197,6 +619,9 @@ pub(crate) struct SetApiKeyRequest {
 pub(crate) struct CreateSpiderKeyRequest {
     pub(crate) name: String,
     pub(crate) permissions: Vec<String>,
+    pub(crate) role: Option<String>, // admin, operator, user or viewer; adds to `permissions`
+    #[serde(rename = "toolScopes")]
+    pub(crate) tool_scopes: Option<Vec<String>>, // `<server id>/*`, `<server id>/<tool>` or `<tool>` on any server
     #[serde(rename = "adminKey")]
     pub(crate) admin_key: String,
 }
@@ -224,6 +649,8 @@ pub(crate) struct ListApiKeysRequest {
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub(crate) struct RemoveApiKeyRequest {
     pub(crate) provider: String,
+    #[serde(rename = "keyId")]
+    pub(crate) key_id: Option<String>, // Remove a single pooled key; None removes all keys for the provider
     #[serde(rename = "authKey")]
     pub(crate) auth_key: String,
 }
@@ -285,6 +712,124 @@ pub(crate) struct ListConversationsRequest {
     pub(crate) limit: Option<u32>,
     pub(crate) offset: Option<u32>,
     pub(crate) client: Option<String>,
+    #[serde(default)]
+    pub(crate) tag: Option<String>,
+    #[serde(default, rename = "includeDeleted")]
+    pub(crate) include_deleted: Option<bool>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SearchConversationsRequest {
+    pub(crate) query: String,
+    pub(crate) limit: Option<u32>,
+    pub(crate) tag: Option<String>,
+    #[serde(rename = "includeDeleted")]
+    pub(crate) include_deleted: Option<bool>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// One message matching a conversation search
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ConversationSearchHit {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    pub(crate) title: Option<String>,
+    #[serde(rename = "messageIndex")]
+    pub(crate) message_index: u32,
+    pub(crate) role: String,
+    pub(crate) snippet: String,
+    pub(crate) timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct UpdateConversationRequest {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    pub(crate) title: Option<String>, // Empty clears the title
+    pub(crate) tags: Option<Vec<String>>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct DeleteConversationRequest {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    pub(crate) permanent: Option<bool>, // Skip the trash and remove the VFS copy now
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ExportTranscriptsRequest {
+    #[serde(rename = "conversationIds")]
+    pub(crate) conversation_ids: Option<Vec<String>>, // All conversations when absent
+    pub(crate) format: String, // "markdown", "anthropic-jsonl" or "openai-jsonl"
+    #[serde(rename = "includeDeleted")]
+    pub(crate) include_deleted: Option<bool>,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ImportTranscriptsRequest {
+    pub(crate) format: String,
+    pub(crate) content: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ForkConversationRequest {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    #[serde(rename = "messageIndex")]
+    pub(crate) message_index: u32, // Position in the active branch
+    pub(crate) content: Option<String>, // Replacement text when editing a user message
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SelectBranchRequest {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    #[serde(rename = "messageId")]
+    pub(crate) message_id: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct RegenerateResponseRequest {
+    #[serde(rename = "apiKey")]
+    pub(crate) api_key: String,
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: Option<String>,
+    pub(crate) model: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct RestoreConversationRequest {
+    #[serde(rename = "conversationId")]
+    pub(crate) conversation_id: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ExportConversationsRequest {
+    #[serde(rename = "conversationIds")]
+    pub(crate) conversation_ids: Option<Vec<String>>, // All conversations when absent
+    #[serde(rename = "includeDeleted")]
+    pub(crate) include_deleted: Option<bool>,
     #[serde(rename = "authKey")]
     pub(crate) auth_key: String,
 }
@@ -296,10 +841,85 @@ pub(crate) struct UpdateConfigRequest {
     #[serde(rename = "maxTokens")]
     pub(crate) max_tokens: Option<u32>,
     pub(crate) temperature: Option<f32>,
+    pub(crate) context: Option<ContextConfig>,
+    #[serde(rename = "fallbackChains")]
+    pub(crate) fallback_chains: Option<Vec<(String, Vec<String>)>>,
+    #[serde(rename = "keySelection")]
+    pub(crate) key_selection: Option<String>,
+    #[serde(rename = "federationPeers")]
+    pub(crate) federation_peers: Option<Vec<FederationPeer>>,
+    #[serde(default, rename = "conversationRetentionDays")]
+    pub(crate) conversation_retention_days: Option<u32>,
+    #[serde(default)]
+    pub(crate) summaries: Option<SummaryConfig>,
+    #[serde(default, rename = "builtinTools")]
+    pub(crate) builtin_tools: Option<BuiltinToolsConfig>,
     #[serde(rename = "authKey")]
     pub(crate) auth_key: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ContextConfig {
+    #[serde(rename = "maxContextTokens")]
+    pub(crate) max_context_tokens: u32,
+    #[serde(rename = "compactionStrategy")]
+    pub(crate) compaction_strategy: String, // "none", "truncate-tool-results", or "summarize"
+    #[serde(rename = "keepRecentMessages")]
+    pub(crate) keep_recent_messages: u32, // Always sent verbatim, never compacted
+    #[serde(rename = "toolOutputPreviewChars")]
+    pub(crate) tool_output_preview_chars: u32,
+    #[serde(rename = "largeToolOutputChars")]
+    pub(crate) large_tool_output_chars: u32, // Outputs above this are stored in VFS
+}
+
+// Automatic conversation titles and summaries, generated by a separate (usually cheaper) model
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct SummaryConfig {
+    pub(crate) enabled: bool, // Off by default: conversation text is sent to the summary model
+    pub(crate) provider: String,
+    pub(crate) model: Option<String>,
+}
+
+// Settings for Spider's built-in tools; which tools a run gets is chosen per agent profile
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct BuiltinToolsConfig {
+    #[serde(rename = "fetchAllowedDomains")]
+    pub(crate) fetch_allowed_domains: Vec<String>, // http_fetch refuses every other host; subdomains are included
+    #[serde(rename = "maxOutputBytes")]
+    pub(crate) max_output_bytes: u32, // Longer file contents and fetched bodies are cut off
+}
+
+impl Default for BuiltinToolsConfig {
+    fn default() -> Self {
+        Self {
+            fetch_allowed_domains: Vec::new(),
+            max_output_bytes: 100_000,
+        }
+    }
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: "anthropic".to_string(),
+            model: Some("claude-3-5-haiku-20241022".to_string()),
+        }
+    }
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            max_context_tokens: 200_000,
+            compaction_strategy: "truncate-tool-results".to_string(),
+            keep_recent_messages: 6,
+            tool_output_preview_chars: 2_000,
+            large_tool_output_chars: 20_000,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub(crate) struct ChatRequest {
     #[serde(rename = "apiKey")]
@@ -312,9 +932,17 @@ pub(crate) struct ChatRequest {
     #[serde(rename = "mcpServers")]
     pub(crate) mcp_servers: Option<Vec<String>>,
     pub(crate) metadata: Option<ConversationMetadata>,
+    // Continue a stored conversation: `messages` then holds only the new turn, appended to
+    // the active branch
+    #[serde(default, rename = "conversationId")]
+    pub(crate) conversation_id: Option<String>,
+    // Run with an agent profile: it fills in provider, model and servers the request leaves
+    // unset, and enables the profile's built-in tools
+    #[serde(default, rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct ChatResponse {
     #[serde(rename = "conversationId")]
     pub(crate) conversation_id: String,
@@ -330,18 +958,83 @@ pub(crate) struct ConfigResponse {
     #[serde(rename = "maxTokens")]
     pub(crate) max_tokens: u32,
     pub(crate) temperature: f32,
+    pub(crate) context: ContextConfig,
+    #[serde(rename = "fallbackChains")]
+    pub(crate) fallback_chains: Vec<(String, Vec<String>)>,
+    #[serde(rename = "keySelection")]
+    pub(crate) key_selection: String,
+    #[serde(rename = "federationPeers")]
+    pub(crate) federation_peers: Vec<FederationPeer>,
+    #[serde(rename = "conversationRetentionDays")]
+    pub(crate) conversation_retention_days: u32,
+    pub(crate) summaries: SummaryConfig,
+    #[serde(rename = "builtinTools")]
+    pub(crate) builtin_tools: BuiltinToolsConfig,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub(crate) struct ProcessRequest {
-    pub(crate) action: String,
-    pub(crate) payload: String,
+pub(crate) enum ProcessRequest {
+    Chat(ProcessChatRequest),
+    ListConversations(ProcessListConversationsRequest),
+    GetConversation(String), // conversation id
+    ListMcpTools,
+    CallTool(ProcessCallToolRequest),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub(crate) struct ProcessResponse {
-    pub(crate) success: bool,
-    pub(crate) data: String,
+pub(crate) enum ProcessResponse {
+    Chat(ChatResponse),
+    Conversations(Vec<Conversation>),
+    Conversation(Box<Conversation>),
+    McpTools(Vec<Tool>), // Named as they must be passed to CallTool
+    ToolResult(String),  // MCP tools/call result as JSON
+}
+
+// Local processes are authorized by their grants rather than a Spider key
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ProcessChatRequest {
+    pub(crate) messages: Vec<Message>,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: Option<String>,
+    pub(crate) model: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>,
+    pub(crate) metadata: Option<ConversationMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ProcessListConversationsRequest {
+    pub(crate) limit: Option<u32>,
+    pub(crate) offset: Option<u32>,
+    pub(crate) client: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ProcessCallToolRequest {
+    #[serde(rename = "toolName")]
+    pub(crate) tool_name: String,
+    #[serde(rename = "argumentsJson")]
+    pub(crate) arguments_json: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProcessGrant {
+    pub(crate) process: String, // e.g. "my-app:my-app:publisher.os"
+    pub(crate) permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct SetProcessGrantRequest {
+    pub(crate) process: String,
+    pub(crate) permissions: Vec<String>, // Empty removes the grant
+    #[serde(rename = "adminKey")]
+    pub(crate) admin_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ListProcessGrantsRequest {
+    #[serde(rename = "adminKey")]
+    pub(crate) admin_key: String,
 }
 
 // JSON-RPC Message Types for MCP Protocol
@@ -414,6 +1107,65 @@ pub(crate) struct ToolExecutionResult {
     pub(crate) success: bool,
 }
 
+// MCP tools/call result (https://modelcontextprotocol.io/specification/2025-06-18/server/tools)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct McpCallToolResult {
+    #[serde(default)]
+    pub(crate) content: Vec<McpContent>,
+    #[serde(
+        default,
+        rename = "structuredContent",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) structured_content: Option<Value>,
+    #[serde(default, rename = "isError")]
+    pub(crate) is_error: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum McpContent {
+    Text {
+        text: String,
+    },
+    Image {
+        data: String, // base64
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Audio {
+        data: String, // base64
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+    Resource {
+        resource: McpEmbeddedResource,
+    },
+    ResourceLink {
+        uri: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default, rename = "mimeType", skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+    },
+    // Content types from newer protocol revisions
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct McpEmbeddedResource {
+    pub(crate) uri: String,
+    #[serde(default, rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub(crate) mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) blob: Option<String>, // base64
+}
+
 // Anthropic schema transformation result
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct AnthropicSchema {
@@ -437,7 +1189,30 @@ pub(crate) enum WsClientMessage {
     #[serde(rename = "chat")]
     Chat { payload: WsChatPayload },
     #[serde(rename = "cancel")]
-    Cancel,
+    Cancel {
+        #[serde(default, rename = "jobId")]
+        job_id: Option<String>, // Defaults to the latest job started on this channel
+    },
+    #[serde(rename = "subscribe_job")]
+    SubscribeJob {
+        #[serde(rename = "jobId")]
+        job_id: String,
+        #[serde(default, rename = "afterSeq")]
+        after_seq: Option<u32>, // Replay events after this one; all when absent
+    },
+    // Receive live updates from runs on these conversations, whoever started them; every
+    // conversation when no ids are given
+    #[serde(rename = "watch_conversations")]
+    WatchConversations {
+        #[serde(default, rename = "conversationIds")]
+        conversation_ids: Option<Vec<String>>,
+    },
+    // Stop watching the given conversations, or everything when no ids are given
+    #[serde(rename = "unwatch_conversations")]
+    UnwatchConversations {
+        #[serde(default, rename = "conversationIds")]
+        conversation_ids: Option<Vec<String>>,
+    },
     #[serde(rename = "ping")]
     Ping,
 }
@@ -452,6 +1227,10 @@ pub(crate) struct WsChatPayload {
     #[serde(rename = "mcpServers")]
     pub(crate) mcp_servers: Option<Vec<String>>,
     pub(crate) metadata: Option<ConversationMetadata>,
+    #[serde(default, rename = "conversationId")]
+    pub(crate) conversation_id: Option<String>,
+    #[serde(default, rename = "profileId")]
+    pub(crate) profile_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -481,6 +1260,41 @@ pub(crate) enum WsServerMessage {
     Error { error: String },
     #[serde(rename = "pong")]
     Pong,
+    #[serde(rename = "job_started")]
+    JobStarted {
+        #[serde(rename = "jobId")]
+        job_id: String,
+    },
+    // Wraps every message produced by a chat job, numbered so clients can resume after `seq`
+    #[serde(rename = "job_event")]
+    JobEvent {
+        #[serde(rename = "jobId")]
+        job_id: String,
+        seq: u32,
+        event: Value,
+    },
+    // A scheduled task finished a run
+    #[serde(rename = "task_finished")]
+    TaskFinished {
+        #[serde(rename = "taskId")]
+        task_id: String,
+        #[serde(rename = "taskName")]
+        task_name: String,
+        run: TaskRun,
+    },
+    // A message from a run on a watched conversation
+    #[serde(rename = "conversation_event")]
+    ConversationEvent {
+        #[serde(rename = "conversationId")]
+        conversation_id: String,
+        event: Value,
+    },
+    #[serde(rename = "mcp_server_state")]
+    McpServerState {
+        server_id: String,
+        state: McpConnectionState,
+        last_error: Option<String>,
+    },
 }
 
 // Trial notification type
@@ -545,6 +1359,8 @@ pub(crate) enum HypergridMessageType {
 pub(crate) struct OAuthExchangeRequest {
     pub(crate) code: String,
     pub(crate) verifier: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -556,6 +1372,129 @@ pub(crate) struct OAuthTokenResponse {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub(crate) struct OAuthRefreshRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct LinkOAuthLoginRequest {
+    pub(crate) code: String,
+    pub(crate) verifier: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String, // The Spider key the login is delegated to
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct UnlinkOAuthLoginRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+// Lets an admin trade a raw OAuth token, formerly used as a Spider key, for a linked Spider key
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct MigrateOAuthTokenRequest {
+    #[serde(rename = "oauthToken")]
+    pub(crate) oauth_token: String,
     #[serde(rename = "refreshToken")]
-    pub(crate) refresh_token: String,
+    pub(crate) refresh_token: Option<String>,
+    #[serde(rename = "expiresAt")]
+    pub(crate) expires_at: Option<u64>,
+    pub(crate) name: Option<String>,
+    #[serde(rename = "adminKey")]
+    pub(crate) admin_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct OAuthStatusRequest {
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct OAuthTokenStatus {
+    pub(crate) configured: bool,
+    pub(crate) healthy: bool, // Configured, not expired (or refreshable), and last refresh succeeded
+    #[serde(rename = "expiresAt")]
+    pub(crate) expires_at: Option<u64>,
+    #[serde(rename = "expiresInSecs")]
+    pub(crate) expires_in_secs: Option<i64>,
+    #[serde(rename = "hasRefreshToken")]
+    pub(crate) has_refresh_token: bool,
+    #[serde(rename = "lastRefreshed")]
+    pub(crate) last_refreshed: Option<u64>,
+    #[serde(rename = "lastError")]
+    pub(crate) last_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AuditEvent {
+    pub(crate) id: String,
+    pub(crate) timestamp: u64,
+    pub(crate) actor: String, // Spider key name, or the calling process for local requests
+    pub(crate) action: String, // e.g. "spider_key.create", "mcp_tool.call"
+    pub(crate) target: String,
+    pub(crate) outcome: String, // success, denied or failure
+    pub(crate) detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct ListAuditEventsRequest {
+    pub(crate) actor: Option<String>,
+    pub(crate) action: Option<String>, // Matches as a prefix, so "mcp_server" covers every server action
+    pub(crate) target: Option<String>, // Matches as a substring
+    pub(crate) outcome: Option<String>,
+    pub(crate) since: Option<u64>,
+    pub(crate) until: Option<u64>,
+    pub(crate) limit: Option<u32>, // Defaults to 100
+    #[serde(rename = "adminKey")]
+    pub(crate) admin_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct FederationPeer {
+    pub(crate) node: String,
+    #[serde(rename = "allowTools")]
+    pub(crate) allow_tools: bool, // List and call our MCP tools
+    #[serde(rename = "allowChat")]
+    pub(crate) allow_chat: bool, // Run chats against our LLM providers
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct RemoteCallToolRequest {
+    #[serde(rename = "toolName")]
+    pub(crate) tool_name: String,
+    #[serde(rename = "argumentsJson")]
+    pub(crate) arguments_json: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct RemoteChatRequest {
+    pub(crate) messages: Vec<Message>,
+    #[serde(rename = "llmProvider")]
+    pub(crate) llm_provider: Option<String>,
+    pub(crate) model: Option<String>,
+    #[serde(rename = "mcpServers")]
+    pub(crate) mcp_servers: Option<Vec<String>>, // Only honoured for peers allowed to use tools
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct InvokeToolRequest {
+    #[serde(rename = "serverId")]
+    pub(crate) server_id: String,
+    #[serde(rename = "toolName")]
+    pub(crate) tool_name: String,
+    #[serde(rename = "argumentsJson")]
+    pub(crate) arguments_json: String,
+    #[serde(rename = "authKey")]
+    pub(crate) auth_key: String, // Local processes may leave this empty and rely on their grant
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct InvokeToolResponse {
+    #[serde(rename = "resultJson")]
+    pub(crate) result_json: String, // MCP tools/call result as returned by the server
+    #[serde(rename = "isError")]
+    pub(crate) is_error: bool,
+    #[serde(rename = "structuredContentJson")]
+    pub(crate) structured_content_json: Option<String>,
 }
@@ -1,9 +1,10 @@
 use hyperware_process_lib::{
-    our,
-    vfs::{create_drive, open_dir, open_file},
+    hyperapp, our,
+    vfs::{create_drive, open_dir, open_file, remove_file},
+    Request,
 };
 
-use crate::types::{Conversation, Tool, TransportConfig};
+use crate::types::{Conversation, McpServer, Tool, TransportConfig};
 
 /// Check if an API key is an OAuth token by examining the third field
 /// OAuth tokens have "oat" followed by 2 digits in the third field (e.g., sk-ant-oat01-...)
@@ -22,9 +23,9 @@ pub(crate) fn is_oauth_token(key: &str) -> bool {
     false
 }
 
+/// Encode a secret for storage. Despite the name and the "encrypted:" marker this is only base64,
+/// not encryption: stored provider keys and OAuth refresh tokens are effectively plaintext.
 pub(crate) fn encrypt_key(key: &str) -> String {
-    // For actual encryption, use base64 encoding with a marker
-    // In production, this should use proper encryption with a key derivation function
     use base64::{engine::general_purpose, Engine as _};
     format!(
         "encrypted:{}",
@@ -47,6 +48,14 @@ pub(crate) fn decrypt_key(encrypted_key: &str) -> String {
     }
 }
 
+/// An MCP server as shown to callers who may not manage servers, without its credentials
+pub(crate) fn redact_mcp_server(server: &McpServer) -> McpServer {
+    let mut server = server.clone();
+    server.transport.hypergrid_token = None;
+    server.transport.hypergrid_client_id = None;
+    server
+}
+
 pub(crate) fn preview_key(encrypted_key: &str) -> String {
     if encrypted_key.len() > 20 {
         format!("{}...", &encrypted_key[..20])
@@ -93,39 +102,104 @@ pub(crate) async fn save_conversation_to_vfs(conversation: &Conversation) -> Res
     Ok(())
 }
 
+/// Paths of every file holding a saved copy of the conversation, oldest first.
+/// Each save writes a new `<timestamp>-<id>.json` file, so a conversation may have several.
+fn conversation_files(drive_path: &str, conversation_id: &str) -> Result<Vec<String>, String> {
+    let dir = open_dir(drive_path, false, None)
+        .map_err(|e| format!("Failed to open conversations directory: {:?}", e))?;
+    let entries = dir
+        .read()
+        .map_err(|e| format!("Failed to read directory: {:?}", e))?;
+
+    let suffix = format!("-{}.json", conversation_id);
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry.path.rsplit('/').next().map(|n| n.to_string()))
+        .filter(|name| name.ends_with(&suffix))
+        .collect();
+    names.sort();
+
+    Ok(names
+        .into_iter()
+        .map(|name| format!("{}/{}", drive_path, name))
+        .collect())
+}
+
 pub(crate) async fn load_conversation_from_vfs(
     conversation_id: &str,
 ) -> Result<Conversation, String> {
-    let dir_path = format!("{}/conversations", our().node);
+    let dir_path = create_drive(our().package_id(), "conversations", None)
+        .map_err(|e| format!("Failed to open conversations drive: {:?}", e))?;
 
-    // Open the conversations directory
-    let dir = open_dir(&dir_path, false, None)
-        .map_err(|e| format!("Failed to open conversations directory: {:?}", e))?;
+    // The newest save is the most complete
+    let file_path = conversation_files(&dir_path, conversation_id)?
+        .pop()
+        .ok_or_else(|| format!("Conversation {} not found in VFS", conversation_id))?;
 
-    // List all files in the directory
-    let entries = dir
+    let file = open_file(&file_path, false, None)
+        .map_err(|e| format!("Failed to open conversation file: {:?}", e))?;
+    let content = file
         .read()
-        .map_err(|e| format!("Failed to read directory: {:?}", e))?;
-
-    // Look for a file containing the conversation ID
-    for entry in entries {
-        if entry.path.contains(conversation_id) {
-            let file_path = format!("{}/{}", dir_path, entry.path);
-            let file = open_file(&file_path, false, None)
-                .map_err(|e| format!("Failed to open conversation file: {:?}", e))?;
+        .map_err(|e| format!("Failed to read conversation file: {:?}", e))?;
 
-            let content = file
-                .read()
-                .map_err(|e| format!("Failed to read conversation file: {:?}", e))?;
+    serde_json::from_slice(&content).map_err(|e| format!("Failed to parse conversation: {}", e))
+}
 
-            let conversation: Conversation = serde_json::from_slice(&content)
-                .map_err(|e| format!("Failed to parse conversation: {}", e))?;
+/// Remove every saved copy of a conversation along with the tool outputs stored for it
+pub(crate) async fn delete_conversation_from_vfs(conversation_id: &str) -> Result<(), String> {
+    let dir_path = create_drive(our().package_id(), "conversations", None)
+        .map_err(|e| format!("Failed to open conversations drive: {:?}", e))?;
+    for file_path in conversation_files(&dir_path, conversation_id)? {
+        remove_file(&file_path, None)
+            .map_err(|e| format!("Failed to remove {}: {:?}", file_path, e))?;
+    }
 
-            return Ok(conversation);
-        }
+    let outputs_path = create_drive(our().package_id(), "tool-outputs", None)
+        .map_err(|e| format!("Failed to open tool-outputs drive: {:?}", e))?;
+    let entries = open_dir(&outputs_path, false, None)
+        .and_then(|dir| dir.read())
+        .map_err(|e| format!("Failed to read tool-outputs directory: {:?}", e))?;
+    let prefix = format!("{}-", conversation_id);
+    for name in entries
+        .iter()
+        .filter_map(|entry| entry.path.rsplit('/').next())
+        .filter(|name| name.starts_with(&prefix))
+    {
+        let file_path = format!("{}/{}", outputs_path, name);
+        remove_file(&file_path, None)
+            .map_err(|e| format!("Failed to remove {}: {:?}", file_path, e))?;
     }
 
-    Err(format!("Conversation {} not found in VFS", conversation_id))
+    Ok(())
+}
+
+/// Store a tool output too large to send to the model, returning its VFS path
+fn path_safe(id: &str) -> String {
+    id.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect()
+}
+
+pub(crate) async fn save_tool_output_to_vfs(
+    conversation_id: &str,
+    tool_call_id: &str,
+    output: &str,
+) -> Result<String, String> {
+    let dir_path = create_drive(our().package_id(), "tool-outputs", None)
+        .map_err(|e| format!("Failed to create tool-outputs drive: {:?}", e))?;
+
+    // Conversation ids can come from clients and tool call ids from the provider, so only
+    // path-safe characters of either reach the file name
+    let safe_conversation_id = path_safe(conversation_id);
+    let safe_call_id = path_safe(tool_call_id);
+    let file_path = format!("{dir_path}/{safe_conversation_id}-{safe_call_id}.txt");
+
+    let file = open_file(&file_path, true, None)
+        .map_err(|e| format!("Failed to open tool output file: {:?}", e))?;
+    file.write(output.as_bytes())
+        .map_err(|e| format!("Failed to write tool output: {:?}", e))?;
+
+    Ok(file_path)
 }
 
 pub(crate) async fn discover_mcp_tools(transport: &TransportConfig) -> Result<Vec<Tool>, String> {
@@ -144,12 +218,14 @@ pub(crate) async fn discover_mcp_tools(transport: &TransportConfig) -> Result<Ve
                     description: "Search for information".to_string(),
                     parameters: r#"{"type":"object","properties":{"query":{"type":"string","description":"The search query"}},"required":["query"]}"#.to_string(),
                     input_schema_json: None,
+                    output_schema_json: None,
                 },
                 Tool {
                     name: "calculate".to_string(),
                     description: "Perform mathematical calculations".to_string(),
                     parameters: r#"{"type":"object","properties":{"expression":{"type":"string","description":"The mathematical expression to evaluate"}},"required":["expression"]}"#.to_string(),
                     input_schema_json: None,
+                    output_schema_json: None,
                 },
             ])
         }
@@ -162,6 +238,7 @@ pub(crate) async fn discover_mcp_tools(transport: &TransportConfig) -> Result<Ve
                 parameters: r#"{"type":"object","properties":{"query":{"type":"string"}}}"#
                     .to_string(),
                 input_schema_json: None,
+                output_schema_json: None,
             }])
         }
         _ => Err(format!(
@@ -170,3 +247,12 @@ pub(crate) async fn discover_mcp_tools(transport: &TransportConfig) -> Result<Ve
         )),
     }
 }
+
+/// Send `body` to ourselves once `delay_ms` has passed. The wait runs on the timer as a task of
+/// its own, so the handler that schedules it returns right away.
+pub(crate) fn send_to_self_after(delay_ms: u64, body: Vec<u8>) {
+    hyperapp::spawn(async move {
+        let _ = hyperapp::sleep(delay_ms).await;
+        let _ = Request::to(our()).body(body).send();
+    });
+}
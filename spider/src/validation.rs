@@ -0,0 +1,589 @@
+use serde_json::Value;
+
+use crate::types::{McpCallToolResult, Tool};
+
+/// The JSON Schema a tool declared for its arguments, if it declared a usable one
+pub(crate) fn tool_input_schema(tool: &Tool) -> Option<Value> {
+    let schema = tool
+        .input_schema_json
+        .as_deref()
+        .unwrap_or(&tool.parameters);
+    serde_json::from_str::<Value>(schema)
+        .ok()
+        .filter(|s| s.is_object())
+}
+
+/// Validate tool arguments against the tool's input schema.
+/// Tools without a parseable schema accept anything, as before validation existed.
+pub(crate) fn validate_tool_arguments(tool: &Tool, arguments: &Value) -> Result<(), String> {
+    let Some(schema) = tool_input_schema(tool) else {
+        return Ok(());
+    };
+    let errors = validate(&schema, arguments);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid arguments for tool {}: {}",
+            tool.name,
+            errors.join("; ")
+        ))
+    }
+}
+
+/// Check a successful result's `structuredContent` against the tool's `outputSchema`.
+/// Tools that declare an output schema must return conforming structured content.
+pub(crate) fn validate_tool_output(tool: &Tool, result: &McpCallToolResult) -> Result<(), String> {
+    if result.is_error {
+        return Ok(());
+    }
+    let Some(schema) = tool
+        .output_schema_json
+        .as_deref()
+        .and_then(|s| serde_json::from_str::<Value>(s).ok())
+    else {
+        return Ok(());
+    };
+    let Some(structured) = &result.structured_content else {
+        return Err(format!(
+            "Tool {} declares an output schema but returned no structured content",
+            tool.name
+        ));
+    };
+    let errors = validate_named(&schema, structured, "structuredContent");
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid structured content from tool {}: {}",
+            tool.name,
+            errors.join("; ")
+        ))
+    }
+}
+
+/// Parse the argument JSON a model produced for a tool call. An empty string means no
+/// arguments, which some providers send for parameterless tools.
+pub(crate) fn parse_tool_arguments(raw: &str) -> Result<Value, String> {
+    if raw.trim().is_empty() {
+        return Ok(Value::Object(serde_json::Map::new()));
+    }
+    match serde_json::from_str::<Value>(raw) {
+        Ok(value @ Value::Object(_)) => Ok(value),
+        Ok(other) => Err(format!(
+            "Tool arguments must be a JSON object, got {}",
+            type_name(&other)
+        )),
+        Err(e) => Err(format!("Tool arguments are not valid JSON: {}", e)),
+    }
+}
+
+/// Validate `instance` against `schema`, returning one message per violation.
+/// Covers the subset of JSON Schema MCP servers use in practice, including local
+/// `$ref`s into `$defs`/`definitions` and the `anyOf`/`oneOf`/`allOf` combinators.
+pub(crate) fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    validate_named(schema, instance, "arguments")
+}
+
+/// Like `validate`, naming the instance `root_name` in messages
+pub(crate) fn validate_named(schema: &Value, instance: &Value, root_name: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    Validator {
+        root: schema,
+        root_name,
+    }
+    .validate_at(schema, instance, "", 0, &mut errors);
+    errors
+}
+
+/// `$ref`s followed without descending into the instance; deeper chains are reference cycles
+const MAX_REF_DEPTH: usize = 32;
+
+struct Validator<'a> {
+    root: &'a Value,
+    root_name: &'a str,
+}
+
+/// Resolve a local reference such as `#/$defs/address`
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    // JSON pointers escape '/' and '~'; `pointer` handles both, but refs may also be percent-encoded
+    root.pointer(&pointer.replace("%24", "$").replace("%25", "%"))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => match value {
+            Value::Number(n) => {
+                n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+            }
+            _ => false,
+        },
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+impl<'a> Validator<'a> {
+    fn display_path<'p>(&self, path: &'p str) -> &'p str
+    where
+        'a: 'p,
+    {
+        if path.is_empty() {
+            self.root_name
+        } else {
+            path
+        }
+    }
+
+    fn validate_at(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        path: &str,
+        ref_depth: usize,
+        errors: &mut Vec<String>,
+    ) {
+        // `true` accepts anything and `false` nothing; other non-object schemas are ignored
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                errors.push(format!("{} is not allowed", self.display_path(path)));
+                return;
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            if ref_depth >= MAX_REF_DEPTH {
+                errors.push(format!(
+                    "{}: schema reference {} is circular",
+                    self.display_path(path),
+                    reference
+                ));
+                return;
+            }
+            match resolve_ref(self.root, reference) {
+                Some(target) => self.validate_at(target, instance, path, ref_depth + 1, errors),
+                None => errors.push(format!(
+                    "{}: schema reference {} cannot be resolved",
+                    self.display_path(path),
+                    reference
+                )),
+            }
+            // Keywords next to $ref still apply (2019-09 and later)
+        }
+
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for sub in all {
+                self.validate_at(sub, instance, path, ref_depth + 1, errors);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf") {
+            let failures: Vec<Vec<String>> = any
+                .iter()
+                .map(|sub| self.collect(sub, instance, path, ref_depth))
+                .collect();
+            if !failures.iter().any(|f| f.is_empty()) {
+                errors.push(format!(
+                    "{} must match at least one of the allowed schemas ({})",
+                    self.display_path(path),
+                    summarize_alternatives(&failures)
+                ));
+            }
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            let failures: Vec<Vec<String>> = one
+                .iter()
+                .map(|sub| self.collect(sub, instance, path, ref_depth))
+                .collect();
+            match failures.iter().filter(|f| f.is_empty()).count() {
+                1 => {}
+                0 => errors.push(format!(
+                    "{} must match exactly one of the allowed schemas ({})",
+                    self.display_path(path),
+                    summarize_alternatives(&failures)
+                )),
+                n => errors.push(format!(
+                    "{} must match exactly one of the allowed schemas, but matches {}",
+                    self.display_path(path),
+                    n
+                )),
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.collect(not, instance, path, ref_depth).is_empty() {
+                errors.push(format!(
+                    "{} matches a schema it must not match",
+                    self.display_path(path)
+                ));
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let allowed: Vec<&str> = match expected {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, instance)) {
+                errors.push(format!(
+                    "{} must be of type {}, got {}",
+                    self.display_path(path),
+                    allowed.join(" or "),
+                    type_name(instance)
+                ));
+                // Further keywords would only repeat the same mistake
+                return;
+            }
+        }
+
+        if let Some(Value::Array(options)) = schema.get("enum") {
+            if !options.contains(instance) {
+                let options: Vec<String> = options.iter().map(|o| o.to_string()).collect();
+                errors.push(format!(
+                    "{} must be one of {}",
+                    self.display_path(path),
+                    options.join(", ")
+                ));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != instance {
+                errors.push(format!(
+                    "{} must equal {}",
+                    self.display_path(path),
+                    constant
+                ));
+            }
+        }
+
+        match instance {
+            Value::Object(object) => {
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for name in required.iter().filter_map(|r| r.as_str()) {
+                        if !object.contains_key(name) {
+                            errors.push(format!(
+                                "{} is missing required property \"{}\"",
+                                self.display_path(path),
+                                name
+                            ));
+                        }
+                    }
+                }
+                let properties = schema.get("properties").and_then(|p| p.as_object());
+                let additional = schema.get("additionalProperties");
+                for (name, value) in object {
+                    let property_path = format!("{}/{}", path, name);
+                    match (properties.and_then(|p| p.get(name)), additional) {
+                        (Some(property_schema), _) => {
+                            self.validate_at(property_schema, value, &property_path, 0, errors)
+                        }
+                        (None, Some(Value::Bool(false))) => errors.push(format!(
+                            "{} has unexpected property \"{}\"",
+                            self.display_path(path),
+                            name
+                        )),
+                        (None, Some(additional_schema)) => {
+                            self.validate_at(additional_schema, value, &property_path, 0, errors)
+                        }
+                        (None, None) => {}
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+                    if (items.len() as u64) < min {
+                        errors.push(format!(
+                            "{} must have at least {} items",
+                            self.display_path(path),
+                            min
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+                    if (items.len() as u64) > max {
+                        errors.push(format!(
+                            "{} must have at most {} items",
+                            self.display_path(path),
+                            max
+                        ));
+                    }
+                }
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.validate_at(item_schema, item, &format!("{}/{}", path, i), 0, errors);
+                    }
+                }
+            }
+            Value::String(text) => {
+                let len = text.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+                    if len < min {
+                        errors.push(format!(
+                            "{} must be at least {} characters",
+                            self.display_path(path),
+                            min
+                        ));
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+                    if len > max {
+                        errors.push(format!(
+                            "{} must be at most {} characters",
+                            self.display_path(path),
+                            max
+                        ));
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let Some(n) = number.as_f64() else {
+                    return;
+                };
+                if let Some(min) = schema.get("minimum").and_then(|m| m.as_f64()) {
+                    if n < min {
+                        errors.push(format!("{} must be >= {}", self.display_path(path), min));
+                    }
+                }
+                if let Some(max) = schema.get("maximum").and_then(|m| m.as_f64()) {
+                    if n > max {
+                        errors.push(format!("{} must be <= {}", self.display_path(path), max));
+                    }
+                }
+                if let Some(min) = schema.get("exclusiveMinimum").and_then(|m| m.as_f64()) {
+                    if n <= min {
+                        errors.push(format!("{} must be > {}", self.display_path(path), min));
+                    }
+                }
+                if let Some(max) = schema.get("exclusiveMaximum").and_then(|m| m.as_f64()) {
+                    if n >= max {
+                        errors.push(format!("{} must be < {}", self.display_path(path), max));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn collect(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        path: &str,
+        ref_depth: usize,
+    ) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.validate_at(schema, instance, path, ref_depth + 1, &mut errors);
+        errors
+    }
+}
+
+// First error of each alternative, so the model can see what each option expected
+fn summarize_alternatives(failures: &[Vec<String>]) -> String {
+    failures
+        .iter()
+        .enumerate()
+        .map(|(i, errors)| {
+            format!(
+                "option {}: {}",
+                i + 1,
+                errors.first().map(String::as_str).unwrap_or("ok")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_missing_and_mistyped_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" }, "limit": { "type": "integer" } },
+            "required": ["query"],
+        });
+        let errors = validate(&schema, &json!({ "limit": "ten" }));
+        assert_eq!(
+            errors,
+            vec![
+                "arguments is missing required property \"query\"",
+                "/limit must be of type integer, got string",
+            ]
+        );
+        assert!(validate(&schema, &json!({ "query": "spiders", "limit": 3 })).is_empty());
+    }
+
+    #[test]
+    fn follows_refs_into_defs_and_definitions() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "home": { "$ref": "#/$defs/address" },
+                "work": { "$ref": "#/definitions/address" },
+            },
+            "$defs": {
+                "address": {
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"],
+                },
+            },
+            "definitions": { "address": { "$ref": "#/$defs/address" } },
+        });
+        let errors = validate(&schema, &json!({ "home": {}, "work": { "city": 7 } }));
+        assert_eq!(
+            errors,
+            vec![
+                "/home is missing required property \"city\"",
+                "/work/city must be of type string, got integer",
+            ]
+        );
+    }
+
+    #[test]
+    fn recursive_schemas_validate_nested_data() {
+        let schema = json!({
+            "$ref": "#/$defs/node",
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/node" } },
+                    },
+                },
+            },
+        });
+        let tree =
+            json!({ "name": "a", "children": [{ "name": "b", "children": [{ "name": 1 }] }] });
+        assert_eq!(
+            validate(&schema, &tree),
+            vec!["/children/0/children/0/name must be of type string, got integer"]
+        );
+    }
+
+    #[test]
+    fn circular_refs_terminate() {
+        let schema = json!({ "$ref": "#/$defs/a", "$defs": { "a": { "$ref": "#/$defs/a" } } });
+        let errors = validate(&schema, &json!({}));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("circular"));
+    }
+
+    #[test]
+    fn unresolvable_refs_are_reported() {
+        let errors = validate(&json!({ "$ref": "#/$defs/missing" }), &json!({}));
+        assert_eq!(
+            errors,
+            vec!["arguments: schema reference #/$defs/missing cannot be resolved"]
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        let any_of = json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] });
+        assert!(validate(&any_of, &json!(3)).is_empty());
+        assert_eq!(validate(&any_of, &json!(true)).len(), 1);
+
+        let one_of = json!({ "oneOf": [{ "type": "integer" }, { "minimum": 0 }] });
+        assert!(validate(&one_of, &json!(-1)).is_empty());
+        assert!(validate(&one_of, &json!(1))[0].contains("matches 2"));
+
+        let all_of = json!({ "allOf": [{ "minimum": 0 }, { "maximum": 10 }] });
+        assert_eq!(
+            validate(&all_of, &json!(11)),
+            vec!["arguments must be <= 10"]
+        );
+    }
+
+    #[test]
+    fn additional_properties() {
+        let closed = json!({
+            "type": "object",
+            "properties": { "a": { "type": "string" } },
+            "additionalProperties": false,
+        });
+        assert_eq!(
+            validate(&closed, &json!({ "a": "x", "b": 1 })),
+            vec!["arguments has unexpected property \"b\""]
+        );
+
+        let typed = json!({ "type": "object", "additionalProperties": { "type": "number" } });
+        assert_eq!(
+            validate(&typed, &json!({ "x": "1" })),
+            vec!["/x must be of type number, got string"]
+        );
+    }
+
+    #[test]
+    fn structured_output_must_match_output_schema() {
+        let tool = Tool {
+            name: "weather".to_string(),
+            description: String::new(),
+            parameters: "{}".to_string(),
+            input_schema_json: None,
+            output_schema_json: Some(
+                json!({
+                    "type": "object",
+                    "properties": { "celsius": { "type": "number" } },
+                    "required": ["celsius"]
+                })
+                .to_string(),
+            ),
+        };
+        let result = |structured: Option<Value>, is_error: bool| McpCallToolResult {
+            content: Vec::new(),
+            structured_content: structured,
+            is_error,
+        };
+
+        assert!(
+            validate_tool_output(&tool, &result(Some(json!({ "celsius": 21.5 })), false)).is_ok()
+        );
+        assert_eq!(
+            validate_tool_output(&tool, &result(Some(json!({ "celsius": "warm" })), false)),
+            Err("Invalid structured content from tool weather: /celsius must be of type number, got string".to_string())
+        );
+        assert!(validate_tool_output(&tool, &result(None, false))
+            .unwrap_err()
+            .contains("no structured content"));
+        // Error results carry no structured content
+        assert!(validate_tool_output(&tool, &result(None, true)).is_ok());
+    }
+
+    #[test]
+    fn parses_tool_arguments() {
+        assert_eq!(parse_tool_arguments("").unwrap(), json!({}));
+        assert_eq!(
+            parse_tool_arguments(r#"{"a":1}"#).unwrap(),
+            json!({ "a": 1 })
+        );
+        assert!(parse_tool_arguments("[1]")
+            .unwrap_err()
+            .contains("JSON object"));
+        assert!(parse_tool_arguments("{\"a\":")
+            .unwrap_err()
+            .contains("not valid JSON"));
+    }
+}
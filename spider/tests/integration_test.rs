@@ -161,14 +161,16 @@ mod tests {
     async fn test_provider_factory() {
         use spider::create_llm_provider;
 
-        let anthropic_provider = create_llm_provider("anthropic", "test_key");
+        let anthropic_provider = create_llm_provider("anthropic", "test_key").unwrap();
         assert_eq!(anthropic_provider.name(), "anthropic");
 
-        let openai_provider = create_llm_provider("openai", "test_key");
+        let oauth_provider = create_llm_provider("anthropic-oauth", "test_key").unwrap();
+        assert_eq!(oauth_provider.name(), "anthropic");
+
+        let openai_provider = create_llm_provider("openai", "test_key").unwrap();
         assert_eq!(openai_provider.name(), "openai");
 
-        // Test default fallback
-        let unknown_provider = create_llm_provider("unknown", "test_key");
-        assert_eq!(unknown_provider.name(), "anthropic"); // Falls back to Anthropic
+        // Unknown providers are rejected rather than silently using Anthropic
+        assert!(create_llm_provider("unknown", "test_key").is_err());
     }
 }
@@ -1,4 +1,4 @@
-import { ApiError, exchangeOauthToken, refreshOauthToken } from '@caller-utils';
+import { ApiError, exchangeOauthToken, refreshOauthToken, getOauthTokenStatus } from '@caller-utils';
 
 export namespace AuthAnthropic {
   const CLIENT_ID = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
@@ -47,12 +47,14 @@ export namespace AuthAnthropic {
     };
   }
 
-  export async function exchange(code: string, verifier: string) {
+  // Spider stores the tokens server-side and refreshes them itself; only status comes back
+  export async function exchange(code: string, verifier: string, authKey: string) {
     try {
       // Use the backend proxy to avoid CORS issues
       const result = await exchangeOauthToken({
         code: code,
         verifier: verifier,
+        authKey: authKey,
       });
       
       return result;
@@ -64,19 +66,18 @@ export namespace AuthAnthropic {
     }
   }
 
-  export async function refresh(refreshToken: string) {
+  export async function refresh(authKey: string) {
     try {
-      // Use the backend proxy to avoid CORS issues
-      const result = await refreshOauthToken({
-        refreshToken: refreshToken,
-      });
-      
-      return result;
+      return await refreshOauthToken({ authKey });
     } catch (error) {
       throw new Error("Failed to refresh token");
     }
   }
 
+  export async function status(authKey: string) {
+    return getOauthTokenStatus({ authKey });
+  }
+
   export class ExchangeFailed extends Error {
     constructor() {
       super("Exchange failed");
@@ -12,6 +12,7 @@ interface ToolCall {
 interface ToolResult {
   tool_call_id: string;
   result: string;
+  is_error?: boolean;
 }
 
 function ToolCallModal({ toolCall, toolResult, onClose }: {
@@ -56,7 +57,7 @@ function ToolCallModal({ toolCall, toolResult, onClose }: {
           {toolResult && (
             <div className="modal-section">
               <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center' }}>
-                <h4>Tool Result</h4>
+                <h4>{toolResult.is_error ? 'Tool Error' : 'Tool Result'}</h4>
                 <button 
                   className="btn-icon copy-btn"
                   onClick={() => copyToClipboard(JSON.stringify(toolResult, null, 2))}
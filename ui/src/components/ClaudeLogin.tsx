@@ -43,18 +43,16 @@ export const ClaudeLogin: React.FC<ClaudeLoginProps> = ({ onSuccess, onCancel })
     setError(null);
     
     try {
-      const tokens = await AuthAnthropic.exchange(authCode, verifier);
-      
-      // Store tokens in localStorage
-      localStorage.setItem('claude_oauth', JSON.stringify({
-        refresh: tokens.refresh,
-        access: tokens.access,
-        expires: tokens.expires,
-      }));
+      const authKey = (window as any).__spiderAdminKey;
+      if (!authKey) {
+        throw new Error('Admin key not available. Please refresh the page.');
+      }
+
+      // Spider keeps the tokens server-side and refreshes them before they expire
+      await AuthAnthropic.exchange(authCode, verifier, authKey);
       
-      // Store as API key in Spider
       const store = useSpiderStore.getState();
-      await store.setApiKey('anthropic-oauth', tokens.access);
+      await store.loadApiKeys();
       
       onSuccess?.();
     } catch (err) {
@@ -1,8 +1,8 @@
-import { useEffect } from 'react';
+import React, { useEffect } from 'react';
 import { useSpiderStore } from '../store/spider';
 
 export default function Conversations() {
-  const { conversations, loadConversations, loadConversation, isLoading } = useSpiderStore();
+  const { conversations, loadConversations, loadConversation, deleteConversation, isLoading } = useSpiderStore();
 
   useEffect(() => {
     loadConversations();
@@ -16,6 +16,13 @@ export default function Conversations() {
     }
   };
 
+  const handleDeleteConversation = async (e: React.MouseEvent, id: string) => {
+    e.stopPropagation();
+    if (confirm('Move this conversation to the trash?')) {
+      await deleteConversation(id);
+    }
+  };
+
   return (
     <div className="component-container">
       <div className="component-header">
@@ -41,12 +48,20 @@ export default function Conversations() {
               onClick={() => handleSelectConversation(conv.id)}
             >
               <div className="conversation-info">
-                <h3>Conversation {conv.id.substring(0, 8)}...</h3>
+                <h3>{conv.title || `Conversation ${conv.id.substring(0, 8)}...`}</h3>
+                {conv.tags && conv.tags.length > 0 && <p>Tags: {conv.tags.join(', ')}</p>}
                 <p>Client: {conv.metadata.client}</p>
-                <p>Started: {conv.metadata.start_time}</p>
-                <p>Messages: {conv.messages.length}</p>
-                <p>Provider: {conv.llm_provider}</p>
+                <p>Started: {conv.metadata.startTime}</p>
+                {conv.summary && <p className="conversation-summary">{conv.summary}</p>}
+                <p>Messages: {conv.messageCount}</p>
+                <p>Provider: {conv.llmProvider}</p>
               </div>
+              <button
+                className="btn btn-danger"
+                onClick={(e) => handleDeleteConversation(e, conv.id)}
+              >
+                Delete
+              </button>
             </div>
           ))
           )}
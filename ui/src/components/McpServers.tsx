@@ -20,6 +20,7 @@ export default function McpServers() {
   const [hypergridToken, setHypergridToken] = useState('');
   const [hypergridClientId, setHypergridClientId] = useState('');
   const [hypergridNode, setHypergridNode] = useState('');
+  const [remoteNode, setRemoteNode] = useState('');
   const [connectingServers, setConnectingServers] = useState<Set<string>>(new Set());
 
   // Periodically refresh server status
@@ -41,7 +42,8 @@ export default function McpServers() {
       url: null,
       hypergridToken: null,
       hypergridClientId: null,
-      hypergridNode: null
+      hypergridNode: null,
+      remoteNode: null
     };
     
     if (transportType === 'websocket') {
@@ -51,6 +53,8 @@ export default function McpServers() {
       transport.hypergridToken = hypergridToken;
       transport.hypergridClientId = hypergridClientId;
       transport.hypergridNode = hypergridNode;
+    } else if (transportType === 'spider-remote') {
+      transport.remoteNode = remoteNode;
     }
     
     await addMcpServer(serverName, transport);
@@ -61,6 +65,7 @@ export default function McpServers() {
     setHypergridToken('');
     setHypergridClientId('');
     setHypergridNode('');
+    setRemoteNode('');
     setShowAddForm(false);
     
     // Refresh servers list after adding
@@ -151,6 +156,7 @@ export default function McpServers() {
             >
               <option value="websocket">WebSocket</option>
               <option value="hypergrid">Hypergrid</option>
+              <option value="spider-remote">Spider on another node</option>
             </select>
           </div>
           
@@ -234,6 +240,23 @@ export default function McpServers() {
             </>
           )}
           
+          {transportType === 'spider-remote' && (
+            <div className="form-group">
+              <label htmlFor="remote-node">Remote Node</label>
+              <input
+                id="remote-node"
+                type="text"
+                value={remoteNode}
+                onChange={(e) => setRemoteNode(e.target.value)}
+                placeholder="their-node.os"
+                required
+              />
+              <small className="form-help">
+                The remote Spider must list this node as a federation peer with tool access
+              </small>
+            </div>
+          )}
+          
           <button type="submit" className="btn btn-primary" disabled={isLoading}>
             {isLoading ? 'Adding...' : 'Add Server'}
           </button>
@@ -253,14 +276,21 @@ export default function McpServers() {
                   <h3>{server.name}</h3>
                   <p>
                     Status: {
-                      isConnecting ? '🟡 Connecting...' :
+                      isConnecting || server.state === 'connecting' ? '🟡 Connecting...' :
+                      server.state === 'degraded' ? '🟠 Degraded' :
+                      server.state === 'failed' ? '🔴 Failed (retrying)' :
                       server.connected ? '🟢 Connected' : 
                       '🔴 Disconnected'
                     }
                   </p>
+                  {server.lastError && server.state !== 'ready' && (
+                    <p className="mcp-server-error">Last error: {server.lastError}</p>
+                  )}
                   <p>
                     Transport: {server.transport.transportType === 'hypergrid' ? 
                       `Hypergrid - ${server.transport.hypergridNode || 'Not configured'}` :
+                      server.transport.transportType === 'spider-remote' ?
+                      `Spider - ${server.transport.remoteNode}` :
                       `WebSocket - ${server.transport.url || 'No URL specified'}`
                     }
                   </p>
@@ -5,15 +5,20 @@ export default function SpiderKeys() {
   const { spiderKeys, isLoading, error, createSpiderKey, revokeSpiderKey } = useSpiderStore();
   const [showAddForm, setShowAddForm] = useState(false);
   const [keyName, setKeyName] = useState('');
-  const [permissions, setPermissions] = useState<string[]>(['read']);
+  const [role, setRole] = useState<string>('user');
+  const [permissions, setPermissions] = useState<string[]>([]);
+  const [toolScopes, setToolScopes] = useState('');
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     if (!keyName.trim()) return;
     
-    await createSpiderKey(keyName, permissions);
+    const scopes = toolScopes.split(',').map(s => s.trim()).filter(Boolean);
+    await createSpiderKey(keyName, permissions, role || null, scopes.length > 0 ? scopes : null);
     setKeyName('');
-    setPermissions(['read']);
+    setToolScopes('');
+    setRole('user');
+    setPermissions([]);
     setShowAddForm(false);
   };
 
@@ -58,9 +63,30 @@ export default function SpiderKeys() {
           </div>
           
           <div className="form-group">
-            <label>Permissions</label>
+            <label htmlFor="key-role">Role</label>
+            <select id="key-role" value={role} onChange={(e) => setRole(e.target.value)}>
+              <option value="">None (permissions only)</option>
+              <option value="viewer">Viewer: read conversations</option>
+              <option value="user">User: chat and read conversations</option>
+              <option value="operator">Operator: everything except Spider keys</option>
+              <option value="admin">Admin: full control</option>
+            </select>
+          </div>
+
+          <div className="form-group">
+            <label>Additional Permissions</label>
             <div className="permissions-grid">
-              {['read', 'write', 'chat', 'admin'].map(perm => (
+              {[
+                'chat',
+                'conversations:read',
+                'conversations:write',
+                'conversations:delete',
+                'mcp:manage',
+                'keys:manage',
+                'config:write',
+                'agents:manage',
+                'admin',
+              ].map(perm => (
                 <label key={perm} className="checkbox-label">
                   <input
                     type="checkbox"
@@ -73,7 +99,22 @@ export default function SpiderKeys() {
             </div>
           </div>
           
-          <button type="submit" className="btn btn-primary" disabled={isLoading}>
+          <div className="form-group">
+            <label htmlFor="key-tool-scopes">Tool Scopes (MCP endpoint)</label>
+            <input
+              id="key-tool-scopes"
+              type="text"
+              value={toolScopes}
+              onChange={(e) => setToolScopes(e.target.value)}
+              placeholder="All tools, or e.g. server-id/*, server-id/tool, tool"
+            />
+          </div>
+
+          <button
+            type="submit"
+            className="btn btn-primary"
+            disabled={isLoading || (!role && permissions.length === 0)}
+          >
             {isLoading ? 'Generating...' : 'Generate Key'}
           </button>
         </form>
@@ -90,6 +131,7 @@ export default function SpiderKeys() {
                 <h3>{key.name}</h3>
                 <p className="key-value">Key: {key.key}</p>
                 <p>Permissions: {key.permissions.join(', ')}</p>
+                {key.toolScopes && <p>Tool scopes: {key.toolScopes.join(', ')}</p>}
                 <p>Created: {new Date(key.createdAt * 1000).toLocaleDateString()}</p>
               </div>
               <button
@@ -5,6 +5,8 @@ import {
   AuthMessage,
   ChatMessage,
   CancelMessage,
+  SubscribeJobMessage,
+  WatchConversationsMessage,
   PingMessage 
 } from '../types/websocket';
 
@@ -16,6 +18,10 @@ class WebSocketService {
   private reconnectTimeout: NodeJS.Timeout | null = null;
   private url: string = '';
   private isAuthenticated: boolean = false;
+  // Chat job in progress and the last event seen from it, so a reconnect can resume it
+  private currentJob: { id: string; lastSeq: number } | null = null;
+  // Conversations being watched, re-sent after a reconnect; 'all' watches every conversation
+  private watching: string[] | 'all' | null = null;
   
   connect(url: string): Promise<void> {
     return new Promise((resolve, reject) => {
@@ -56,6 +62,23 @@ class WebSocketService {
   }
   
   private handleMessage(message: WsServerMessage) {
+    if (message.type === 'job_started') {
+      this.currentJob = { id: message.jobId, lastSeq: 0 };
+    } else if (message.type === 'job_event') {
+      // Job events wrap the same messages a chat used to stream directly
+      if (this.currentJob?.id === message.jobId) {
+        if (message.seq <= this.currentJob.lastSeq) {
+          return;
+        }
+        this.currentJob.lastSeq = message.seq;
+        if (message.event.type === 'chat_complete' || message.event.type === 'error') {
+          this.currentJob = null;
+        }
+      }
+      this.handleMessage(message.event);
+      return;
+    }
+
     // Notify all handlers
     this.messageHandlers.forEach(handler => handler(message));
   }
@@ -72,6 +95,7 @@ class WebSocketService {
         if (message.type === 'auth_success') {
           this.isAuthenticated = true;
           this.removeMessageHandler(authHandler);
+          this.restoreSubscriptions();
           resolve();
         } else if (message.type === 'auth_error') {
           this.removeMessageHandler(authHandler);
@@ -90,7 +114,7 @@ class WebSocketService {
     });
   }
   
-  sendChatMessage(messages: Message[], llmProvider?: string, model?: string, mcpServers?: string[], metadata?: ConversationMetadata): void {
+  sendChatMessage(messages: Message[], llmProvider?: string, model?: string, mcpServers?: string[], metadata?: ConversationMetadata, conversationId?: string): void {
     if (!this.isAuthenticated) {
       throw new Error('Not authenticated');
     }
@@ -102,7 +126,8 @@ class WebSocketService {
         llmProvider,
         model,
         mcpServers,
-        metadata
+        metadata,
+        conversationId
       }
     };
     this.send(chatMsg);
@@ -114,10 +139,40 @@ class WebSocketService {
     }
     
     const cancelMsg: CancelMessage = {
-      type: 'cancel'
+      type: 'cancel',
+      jobId: this.currentJob?.id
     };
     this.send(cancelMsg);
   }
+
+  watchConversations(conversationIds?: string[]): void {
+    if (!this.isAuthenticated) {
+      throw new Error('Not authenticated');
+    }
+
+    this.watching = conversationIds ?? 'all';
+    const watchMsg: WatchConversationsMessage = {
+      type: 'watch_conversations',
+      conversationIds
+    };
+    this.send(watchMsg);
+  }
+
+  // Catch up on a chat job that was still running when the connection dropped, and watch the
+  // same conversations as before
+  private restoreSubscriptions(): void {
+    if (this.currentJob) {
+      const subscribeMsg: SubscribeJobMessage = {
+        type: 'subscribe_job',
+        jobId: this.currentJob.id,
+        afterSeq: this.currentJob.lastSeq
+      };
+      this.send(subscribeMsg);
+    }
+    if (this.watching) {
+      this.watchConversations(this.watching === 'all' ? undefined : this.watching);
+    }
+  }
   
   send(data: WsClientMessage): void {
     if (!this.ws || this.ws.readyState !== WebSocket.OPEN) {
@@ -2,7 +2,6 @@ import { create } from 'zustand';
 import * as api from '../utils/api';
 import { webSocketService } from '../services/websocket';
 import { WsServerMessage } from '../types/websocket';
-import { AuthAnthropic } from '../auth/anthropic';
 
 interface ApiKeyInfo {
   provider: string;
@@ -16,6 +15,7 @@ interface SpiderApiKey {
   name: string;
   permissions: string[];
   createdAt: number;
+  toolScopes?: string[] | null;
 }
 
 interface McpServer {
@@ -29,6 +29,7 @@ interface McpServer {
     hypergridToken?: string;
     hypergridClientId?: string;
     hypergridNode?: string;
+    remoteNode?: string;
   };
   tools: Array<{
     name: string;
@@ -37,6 +38,8 @@ interface McpServer {
     inputSchemaJson?: string;
   }>;
   connected: boolean;
+  state?: 'disconnected' | 'connecting' | 'ready' | 'degraded' | 'failed';
+  lastError?: string;
 }
 
 interface ConversationMetadata {
@@ -52,6 +55,21 @@ interface Conversation {
   llmProvider: string;
   model?: string;
   mcpServers: string[];
+  title?: string | null;
+  tags?: string[];
+  deletedAt?: number | null;
+}
+
+// Listing entry returned by list_conversations; message bodies are fetched on selection
+interface ConversationInfo {
+  id: string;
+  title?: string | null;
+  summary?: string | null;
+  tags: string[];
+  metadata: ConversationMetadata;
+  llmProvider: string;
+  messageCount: number;
+  deletedAt?: number | null;
 }
 
 interface Message {
@@ -73,7 +91,7 @@ interface SpiderStore {
   apiKeys: ApiKeyInfo[];
   spiderKeys: SpiderApiKey[];
   mcpServers: McpServer[];
-  conversations: Conversation[];
+  conversations: ConversationInfo[];
   activeConversation: Conversation | null;
   config: SpiderConfig;
   isLoading: boolean;
@@ -89,7 +107,7 @@ interface SpiderStore {
   setApiKey: (provider: string, key: string) => Promise<void>;
   removeApiKey: (provider: string) => Promise<void>;
   loadApiKeys: () => Promise<void>;
-  createSpiderKey: (name: string, permissions: string[]) => Promise<void>;
+  createSpiderKey: (name: string, permissions: string[], role?: string | null, toolScopes?: string[] | null) => Promise<void>;
   revokeSpiderKey: (key: string) => Promise<void>;
   loadSpiderKeys: () => Promise<void>;
   addMcpServer: (name: string, transport: any) => Promise<void>;
@@ -102,6 +120,7 @@ interface SpiderStore {
   clearActiveConversation: () => void;
   loadConversations: (client?: string, limit?: number) => Promise<void>;
   loadConversation: (id: string) => Promise<void>;
+  deleteConversation: (id: string) => Promise<void>;
   loadConfig: () => Promise<void>;
   updateConfig: (config: Partial<SpiderConfig>) => Promise<void>;
   clearError: () => void;
@@ -110,44 +129,45 @@ interface SpiderStore {
   disconnectWebSocket: () => void;
 }
 
+function toConversationInfo(conversation: Conversation): ConversationInfo {
+  return {
+    id: conversation.id,
+    title: conversation.title,
+    summary: null,
+    tags: conversation.tags || [],
+    metadata: conversation.metadata,
+    llmProvider: conversation.llmProvider,
+    messageCount: conversation.messages.length,
+    deletedAt: conversation.deletedAt,
+  };
+}
+
 // Helper function to fetch admin key using generated binding
 async function fetchAdminKey(): Promise<string> {
   return api.getAdminKey();
 }
 
-// Helper function to get API key for chat (checks OAuth tokens)
+// Helper function to get API key for chat.
+// Older UIs kept a Claude OAuth token in localStorage and sent it as the Spider key; Spider no
+// longer accepts that, so such tokens are migrated once to a Spider key linked to the login.
 async function getApiKeyForChat(provider: string): Promise<string | null> {
-  // Check if we have an OAuth token for Anthropic
-  if (provider === 'anthropic' || provider === 'anthropic-oauth') {
-    const oauthData = localStorage.getItem('claude_oauth');
-    if (oauthData) {
-      try {
-        const tokens = JSON.parse(oauthData);
-        
-        // Check if token is expired
-        if (tokens.expires && tokens.expires > Date.now()) {
-          return tokens.access;
-        }
-        
-        // Try to refresh the token
-        const refreshed = await AuthAnthropic.refresh(tokens.refresh);
-        
-        // Update stored tokens
-        localStorage.setItem('claude_oauth', JSON.stringify({
-          refresh: refreshed.refresh,
-          access: refreshed.access,
-          expires: refreshed.expires,
-        }));
-        
-        return refreshed.access;
-      } catch (error) {
-        console.error('Failed to refresh OAuth token:', error);
-        // Remove invalid tokens
-        localStorage.removeItem('claude_oauth');
-      }
+  const oauthData = localStorage.getItem('claude_oauth');
+  if (oauthData) {
+    try {
+      const tokens = JSON.parse(oauthData);
+      const linkedKey = await api.migrateOauthToken(tokens.access, tokens.refresh, tokens.expires);
+      localStorage.setItem('spider_claude_key', linkedKey.key);
+    } catch (error) {
+      console.error('Failed to migrate stored OAuth token:', error);
     }
+    localStorage.removeItem('claude_oauth');
   }
-  
+
+  const linkedKey = localStorage.getItem('spider_claude_key');
+  if (linkedKey && (provider === 'anthropic' || provider === 'anthropic-oauth')) {
+    return linkedKey;
+  }
+
   // Fall back to admin key
   return (window as any).__spiderAdminKey || null;
 }
@@ -255,10 +275,10 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
     }
   },
 
-  createSpiderKey: async (name: string, permissions: string[]) => {
+  createSpiderKey: async (name: string, permissions: string[], role: string | null = null, toolScopes: string[] | null = null) => {
     try {
       set({ isLoading: true, error: null });
-      await api.createSpiderKey(name, permissions);
+      await api.createSpiderKey(name, permissions, role, toolScopes);
       await get().loadSpiderKeys();
       set({ isLoading: false });
     } catch (error: any) {
@@ -380,6 +400,10 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
         timestamp: Date.now(),
       };
       
+      // Stored conversations continue server-side from their active branch, so only the new
+      // message is sent for them
+      const outgoing = conversation.id ? [userMessage] : [...conversation.messages, userMessage];
+
       // Update local state immediately for better UX
       conversation.messages.push(userMessage);
       set({ activeConversation: { ...conversation } });
@@ -388,11 +412,12 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
       if (get().useWebSocket && webSocketService.isReady) {
         // Send via WebSocket for progressive updates
         webSocketService.sendChatMessage(
-          conversation.messages,
+          outgoing,
           conversation.llmProvider,
           conversation.model,
           conversation.mcpServers,
-          conversation.metadata
+          conversation.metadata,
+          conversation.id || undefined
         );
         // WebSocket responses will be handled by the message handler
         return;
@@ -408,11 +433,12 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
       // Send to backend with abort signal support
       const response = await api.chat(
         apiKey,
-        conversation.messages,
+        outgoing,
         conversation.llmProvider,
         conversation.model,
         conversation.mcpServers,
         conversation.metadata,
+        conversation.id || undefined,
         signal
       );
       
@@ -433,9 +459,9 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
         const conversations = get().conversations;
         const existingIndex = conversations.findIndex(c => c.id === conversation.id);
         if (existingIndex >= 0) {
-          conversations[existingIndex] = conversation;
+          conversations[existingIndex] = toConversationInfo(conversation);
         } else {
-          conversations.unshift(conversation);
+          conversations.unshift(toConversationInfo(conversation));
         }
         
         set({ 
@@ -460,7 +486,10 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
 
   cancelRequest: async () => {
     set({ currentRequestId: null, isLoading: false });
-    // TODO: Send cancel request to backend if needed
+    // WebSocket chats run as server-side jobs that keep going unless cancelled
+    if (get().useWebSocket && webSocketService.isReady) {
+      webSocketService.sendCancel();
+    }
   },
 
   clearActiveConversation: () => {
@@ -485,6 +514,19 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
     }
   },
 
+  deleteConversation: async (id: string) => {
+    try {
+      await api.deleteConversation(id);
+      const { activeConversation } = get();
+      set({
+        conversations: get().conversations.filter(c => c.id !== id),
+        activeConversation: activeConversation?.id === id ? null : activeConversation,
+      });
+    } catch (error: any) {
+      set({ error: error.message || 'Failed to delete conversation' });
+    }
+  },
+
   loadConfig: async () => {
     try {
       const config = await api.getConfig();
@@ -554,9 +596,9 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
               const conversations = [...state.conversations];
               const existingIndex = conversations.findIndex(c => c.id === updatedConversation.id);
               if (existingIndex >= 0) {
-                conversations[existingIndex] = updatedConversation;
+                conversations[existingIndex] = toConversationInfo(updatedConversation);
               } else {
-                conversations.unshift(updatedConversation);
+                conversations.unshift(toConversationInfo(updatedConversation));
               }
               
               set({ 
@@ -568,6 +610,35 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
             }
             break;
             
+          case 'conversation_event':
+            // Runs started by other clients; our own run already updates the conversation
+            if (message.event.type === 'chat_complete') {
+              get().loadConversations();
+            }
+            if (state.isLoading || state.activeConversation?.id !== message.conversationId) {
+              break;
+            }
+            if (message.event.type === 'message') {
+              const updatedConversation = { ...state.activeConversation };
+              updatedConversation.messages = [...updatedConversation.messages, message.event.message];
+              set({ activeConversation: updatedConversation });
+            }
+            break;
+
+          case 'mcp_server_state':
+            // Health changes pushed by the MCP health loop
+            set({
+              mcpServers: state.mcpServers.map(s => s.id === message.server_id
+                ? {
+                    ...s,
+                    state: message.state,
+                    lastError: message.last_error || s.lastError,
+                    connected: message.state === 'ready' || message.state === 'degraded',
+                  }
+                : s),
+            });
+            break;
+
           case 'error':
             set({ 
               error: message.error || 'WebSocket error occurred',
@@ -586,6 +657,10 @@ export const useSpiderStore = create<SpiderStore>((set, get) => ({
         throw new Error('No valid API key available. Please add an API key or login with Claude.');
       }
       await webSocketService.authenticate(authKey);
+      // Only the admin key is sure to hold conversations:read, which watching requires
+      if (authKey === (window as any).__spiderAdminKey) {
+        webSocketService.watchConversations();
+      }
       
       set({ wsConnected: true });
     } catch (error: any) {
@@ -1,12 +1,15 @@
 // WebSocket message types for Spider chat
 
-import { Message, ConversationMetadata, ChatResponse } from '@caller-utils';
+import { Message, ConversationMetadata, ChatResponse, TaskRun } from '@caller-utils';
 
 // Client -> Server messages
 export type WsClientMessage = 
   | AuthMessage 
   | ChatMessage 
   | CancelMessage
+  | SubscribeJobMessage
+  | WatchConversationsMessage
+  | UnwatchConversationsMessage
   | PingMessage;
 
 export interface AuthMessage {
@@ -21,11 +24,32 @@ export interface ChatMessage {
     llmProvider?: string;
     mcpServers?: string[];
     metadata?: ConversationMetadata;
+    conversationId?: string;
+    profileId?: string;
   };
 }
 
 export interface CancelMessage {
   type: 'cancel';
+  jobId?: string;
+}
+
+// Resume a chat job's events after a reconnect
+export interface SubscribeJobMessage {
+  type: 'subscribe_job';
+  jobId: string;
+  afterSeq?: number;
+}
+
+// Follow runs on these conversations (all of them when omitted), whichever client started them
+export interface WatchConversationsMessage {
+  type: 'watch_conversations';
+  conversationIds?: string[];
+}
+
+export interface UnwatchConversationsMessage {
+  type: 'unwatch_conversations';
+  conversationIds?: string[];
 }
 
 export interface PingMessage {
@@ -41,7 +65,12 @@ export type WsServerMessage =
   | MessageUpdate
   | ChatCompleteMessage
   | ErrorMessage
-  | PongMessage;
+  | PongMessage
+  | JobStartedMessage
+  | JobEventMessage
+  | ConversationEventMessage
+  | TaskFinishedMessage
+  | McpServerStateMessage;
 
 export interface AuthSuccessMessage {
   type: 'auth_success';
@@ -83,4 +112,38 @@ export interface ErrorMessage {
 
 export interface PongMessage {
   type: 'pong';
-}
\ No newline at end of file
+}
+
+export interface JobStartedMessage {
+  type: 'job_started';
+  jobId: string;
+}
+
+export interface JobEventMessage {
+  type: 'job_event';
+  jobId: string;
+  seq: number;
+  event: WsServerMessage;
+}
+
+export interface ConversationEventMessage {
+  type: 'conversation_event';
+  conversationId: string;
+  event: WsServerMessage;
+}
+
+export interface TaskFinishedMessage {
+  type: 'task_finished';
+  taskId: string;
+  taskName: string;
+  run: TaskRun;
+}
+
+export type McpConnectionState = 'disconnected' | 'connecting' | 'ready' | 'degraded' | 'failed';
+
+export interface McpServerStateMessage {
+  type: 'mcp_server_state';
+  server_id: string;
+  state: McpConnectionState;
+  last_error?: string;
+}
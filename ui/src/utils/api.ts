@@ -13,18 +13,46 @@ import {
   removeMcpServer as _removeMcpServer,
   listConversations as _listConversations,
   getConversation as _getConversation,
+  searchConversations as _searchConversations,
+  updateConversation as _updateConversation,
+  deleteConversation as _deleteConversation,
+  restoreConversation as _restoreConversation,
+  forkConversation as _forkConversation,
+  selectBranch as _selectBranch,
+  regenerateResponse as _regenerateResponse,
+  exportConversations as _exportConversations,
+  exportTranscripts as _exportTranscripts,
+  importTranscripts as _importTranscripts,
   getConfig as _getConfig,
   updateConfig as _updateConfig,
   chat as _chat,
+  startChatJob as _startChatJob,
+  getJobStatus as _getJobStatus,
+  listAgentProfiles as _listAgentProfiles,
+  saveAgentProfile as _saveAgentProfile,
+  deleteAgentProfile as _deleteAgentProfile,
+  listScheduledTasks as _listScheduledTasks,
+  saveScheduledTask as _saveScheduledTask,
+  deleteScheduledTask as _deleteScheduledTask,
+  listTriggers as _listTriggers,
+  saveTrigger as _saveTrigger,
+  deleteTrigger as _deleteTrigger,
   getAdminKey as _getAdminKey,
+  migrateOauthToken as _migrateOauthToken,
   type ApiKeyInfo,
   type SpiderApiKey,
   type McpServer,
   type Conversation,
   type ConfigResponse,
   type ChatResponse,
+  type ChatJob,
+  type AgentProfile,
+  type ScheduledTask,
+  type Trigger,
   type Message,
   type ConversationMetadata,
+  type ConversationSearchHit,
+  type ConversationInfo,
   type TransportConfig,
 } from '@caller-utils';
 
@@ -32,12 +60,27 @@ export async function getAdminKey(): Promise<string> {
   return _getAdminKey();
 }
 
+// Trade an OAuth token saved by an older UI for a Spider key linked to that Claude login
+export async function migrateOauthToken(oauthToken: string, refreshToken?: string, expiresAt?: number): Promise<SpiderApiKey> {
+  const adminKey = (window as any).__spiderAdminKey;
+  if (!adminKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _migrateOauthToken({
+    oauthToken,
+    refreshToken: refreshToken || null,
+    expiresAt: expiresAt || null,
+    name: 'Claude login (migrated from browser)',
+    adminKey,
+  });
+}
+
 export async function setApiKey(provider: string, key: string) {
   const authKey = (window as any).__spiderAdminKey;
   if (!authKey) {
     throw new Error('Admin key not available. Please refresh the page.');
   }
-  return _setApiKey({ provider, key, authKey });
+  return _setApiKey({ provider, key, addToPool: null, authKey });
 }
 
 export async function listApiKeys(): Promise<ApiKeyInfo[]> {
@@ -53,15 +96,20 @@ export async function removeApiKey(provider: string) {
   if (!authKey) {
     throw new Error('Admin key not available. Please refresh the page.');
   }
-  return _removeApiKey({ provider, authKey });
+  return _removeApiKey({ provider, keyId: null, authKey });
 }
 
-export async function createSpiderKey(name: string, permissions: string[]): Promise<SpiderApiKey> {
+export async function createSpiderKey(
+  name: string,
+  permissions: string[],
+  role: string | null = null,
+  toolScopes: string[] | null = null,
+): Promise<SpiderApiKey> {
   const adminKey = (window as any).__spiderAdminKey;
   if (!adminKey) {
     throw new Error('Admin key not available. Please refresh the page.');
   }
-  return _createSpiderKey({ name, permissions, adminKey });
+  return _createSpiderKey({ name, permissions, role, toolScopes, adminKey });
 }
 
 export async function listSpiderKeys(): Promise<SpiderApiKey[]> {
@@ -120,7 +168,7 @@ export async function removeMcpServer(serverId: string) {
   return _removeMcpServer({ serverId, authKey });
 }
 
-export async function listConversations(client?: string, limit?: number, offset?: number): Promise<Conversation[]> {
+export async function listConversations(client?: string, limit?: number, offset?: number, tag?: string, includeDeleted?: boolean): Promise<ConversationInfo[]> {
   const authKey = (window as any).__spiderAdminKey;
   if (!authKey) {
     throw new Error('Admin key not available. Please refresh the page.');
@@ -129,6 +177,8 @@ export async function listConversations(client?: string, limit?: number, offset?
     client: client || null,
     limit: limit || null,
     offset: offset || null,
+    tag: tag || null,
+    includeDeleted: includeDeleted || null,
     authKey
   });
 }
@@ -141,6 +191,90 @@ export async function getConversation(conversationId: string): Promise<Conversat
   return _getConversation({ conversationId, authKey });
 }
 
+export async function searchConversations(query: string, tag?: string, limit?: number): Promise<ConversationSearchHit[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _searchConversations({ query, limit: limit || null, tag: tag || null, includeDeleted: null, authKey });
+}
+
+export async function updateConversation(conversationId: string, title?: string | null, tags?: string[] | null): Promise<Conversation> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _updateConversation({ conversationId, title: title ?? null, tags: tags ?? null, authKey });
+}
+
+export async function deleteConversation(conversationId: string, permanent?: boolean): Promise<string> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _deleteConversation({ conversationId, permanent: permanent || null, authKey });
+}
+
+export type TranscriptFormat = 'markdown' | 'anthropic-jsonl' | 'openai-jsonl';
+
+export async function exportTranscripts(format: TranscriptFormat, conversationIds?: string[], includeDeleted?: boolean): Promise<string> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _exportTranscripts({ conversationIds: conversationIds || null, format, includeDeleted: includeDeleted || null, authKey });
+}
+
+export async function importTranscripts(format: TranscriptFormat, content: string): Promise<ConversationInfo[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _importTranscripts({ format, content, authKey });
+}
+
+export async function forkConversation(conversationId: string, messageIndex: number, content?: string): Promise<Conversation> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _forkConversation({ conversationId, messageIndex, content: content ?? null, authKey });
+}
+
+export async function selectBranch(conversationId: string, messageId: string): Promise<Conversation> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _selectBranch({ conversationId, messageId, authKey });
+}
+
+export async function regenerateResponse(apiKey: string, conversationId: string, llmProvider?: string, model?: string, mcpServers?: string[]): Promise<ChatResponse> {
+  return _regenerateResponse({
+    apiKey,
+    conversationId,
+    llmProvider: llmProvider || null,
+    model: model || null,
+    mcpServers: mcpServers || null
+  });
+}
+
+export async function restoreConversation(conversationId: string): Promise<Conversation> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _restoreConversation({ conversationId, authKey });
+}
+
+export async function exportConversations(conversationIds?: string[], includeDeleted?: boolean): Promise<Conversation[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _exportConversations({ conversationIds: conversationIds || null, includeDeleted: includeDeleted || null, authKey });
+}
+
 export async function getConfig(): Promise<ConfigResponse> {
   const authKey = (window as any).__spiderAdminKey;
   if (!authKey) {
@@ -158,11 +292,18 @@ export async function updateConfig(config: Partial<ConfigResponse>): Promise<str
     defaultLlmProvider: config.defaultLlmProvider || null,
     maxTokens: config.maxTokens || null,
     temperature: config.temperature || null,
+    context: null,
+    fallbackChains: null,
+    keySelection: null,
+    federationPeers: null,
+    conversationRetentionDays: config.conversationRetentionDays ?? null,
+    summaries: config.summaries ?? null,
+    builtinTools: config.builtinTools ?? null,
     authKey
   });
 }
 
-export async function chat(apiKey: string, messages: Message[], llmProvider?: string, model?: string, mcpServers?: string[], metadata?: ConversationMetadata, signal?: AbortSignal): Promise<ChatResponse> {
+export async function chat(apiKey: string, messages: Message[], llmProvider?: string, model?: string, mcpServers?: string[], metadata?: ConversationMetadata, conversationId?: string, signal?: AbortSignal): Promise<ChatResponse> {
   // TODO: Pass signal to the underlying API call when supported
   return _chat({
     apiKey,
@@ -170,6 +311,148 @@ export async function chat(apiKey: string, messages: Message[], llmProvider?: st
     llmProvider: llmProvider || null,
     model: model || null,
     mcpServers: mcpServers || null,
-    metadata: metadata || null
+    metadata: metadata || null,
+    conversationId: conversationId || null,
+    profileId: null
+  });
+}
+export async function startChatJob(apiKey: string, messages: Message[], llmProvider?: string, model?: string, mcpServers?: string[], metadata?: ConversationMetadata, conversationId?: string): Promise<string> {
+  return _startChatJob({
+    apiKey,
+    messages,
+    llmProvider: llmProvider || null,
+    model: model || null,
+    mcpServers: mcpServers || null,
+    metadata: metadata || null,
+    conversationId: conversationId || null,
+    profileId: null
+  });
+}
+
+export async function getJobStatus(authKey: string, jobId: string, afterSeq?: number): Promise<ChatJob> {
+  return _getJobStatus({ jobId, afterSeq: afterSeq ?? null, authKey });
+}
+
+export async function listAgentProfiles(): Promise<AgentProfile[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _listAgentProfiles({ authKey });
+}
+
+export async function saveAgentProfile(profile: { id?: string; name: string; llmProvider?: string; model?: string; mcpServers?: string[]; builtinTools?: string[] }): Promise<AgentProfile> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _saveAgentProfile({
+    id: profile.id || null,
+    name: profile.name,
+    llmProvider: profile.llmProvider || null,
+    model: profile.model || null,
+    mcpServers: profile.mcpServers || null,
+    builtinTools: profile.builtinTools || null,
+    authKey
   });
-}
\ No newline at end of file
+}
+
+export async function deleteAgentProfile(profileId: string): Promise<string> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _deleteAgentProfile({ profileId, authKey });
+}
+
+export async function listScheduledTasks(): Promise<ScheduledTask[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _listScheduledTasks({ authKey });
+}
+
+export interface ScheduledTaskInput {
+  taskId?: string;
+  name: string;
+  prompt: string;
+  profileId?: string;
+  mcpServers?: string[];
+  cron?: string;
+  runAt?: number;
+  enabled?: boolean;
+  notifyWatchers?: boolean;
+  notifyProcess?: string;
+}
+
+export async function saveScheduledTask(task: ScheduledTaskInput): Promise<ScheduledTask> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _saveScheduledTask({
+    taskId: task.taskId || null,
+    name: task.name,
+    prompt: task.prompt,
+    profileId: task.profileId || null,
+    mcpServers: task.mcpServers || null,
+    cron: task.cron || null,
+    runAt: task.runAt ?? null,
+    enabled: task.enabled ?? null,
+    notifyWatchers: task.notifyWatchers ?? null,
+    notifyProcess: task.notifyProcess || null,
+    authKey
+  });
+}
+
+export async function deleteScheduledTask(taskId: string): Promise<string> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _deleteScheduledTask({ taskId, authKey });
+}
+
+export async function listTriggers(): Promise<Trigger[]> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _listTriggers({ authKey });
+}
+
+export interface TriggerInput {
+  triggerId?: string;
+  name: string;
+  promptTemplate: string;
+  profileId?: string;
+  callbackUrl?: string;
+  enabled?: boolean;
+  rotateSecret?: boolean;
+}
+
+export async function saveTrigger(trigger: TriggerInput): Promise<Trigger> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _saveTrigger({
+    triggerId: trigger.triggerId || null,
+    name: trigger.name,
+    promptTemplate: trigger.promptTemplate,
+    profileId: trigger.profileId || null,
+    callbackUrl: trigger.callbackUrl || null,
+    enabled: trigger.enabled ?? null,
+    rotateSecret: trigger.rotateSecret ?? null,
+    authKey
+  });
+}
+
+export async function deleteTrigger(triggerId: string): Promise<string> {
+  const authKey = (window as any).__spiderAdminKey;
+  if (!authKey) {
+    throw new Error('Admin key not available. Please refresh the page.');
+  }
+  return _deleteTrigger({ triggerId, authKey });
+}